@@ -0,0 +1,580 @@
+// APNG: the frames after the first are stored in fdAT chunks, each one described
+// by the fcTL chunk before it. The IDAT image is the first frame when an fcTL
+// comes before it, otherwise it's only shown by decoders that don't know APNG.
+// https://www.w3.org/TR/png-3/#apng-frame-control
+
+use crate::chunks::{fcTL, ParsedChunk, IHDR};
+use crate::error::{AnimationError, ImageError};
+use crate::inflate;
+use crate::parser::{Chunk, ChunkType, Png};
+use crate::pixels::PixelBuffer;
+use crate::writer;
+
+// Chunks copied into a frame's own PNG so its colors come out the same
+const COLOR_CHUNKS: [ChunkType; 4] = [
+    ChunkType::cHRM,
+    ChunkType::gAMA,
+    ChunkType::iCCP,
+    ChunkType::sRGB,
+];
+
+// Raw frames keep the image's pixel format, so they need these as well
+const PIXEL_FORMAT_CHUNKS: [ChunkType; 3] = [ChunkType::PLTE, ChunkType::sBIT, ChunkType::tRNS];
+
+// One frame of the animation, before it's drawn onto the canvas
+#[derive(Debug, Clone)]
+pub struct Frame {
+    // Counting from 0
+    pub number: usize,
+    pub control: fcTL,
+    // Whether the frame's data is the IDAT image
+    pub is_default_image: bool,
+    compressed: Vec<u8>,
+}
+
+impl Frame {
+    // The frame's zlib stream, with the fdAT sequence numbers taken out
+    pub fn compressed_data(&self) -> &[u8] {
+        &self.compressed
+    }
+
+    // The image's header with the frame's size, as if the frame were a PNG of its own
+    pub fn header(&self, image_header: &IHDR) -> IHDR {
+        IHDR {
+            width: self.control.width,
+            height: self.control.height,
+            ..image_header.clone()
+        }
+    }
+
+    // The frame's own pixels, not drawn onto the canvas. Like IDAT, extra data after
+    // what the frame needs is ignored.
+    pub fn pixels(&self, png: &Png) -> Result<PixelBuffer, ImageError> {
+        let header = self.header(png.ihdr());
+        PixelBuffer::from_image_data(
+            &header,
+            png.palette(),
+            png.transparency(),
+            &inflate::zlib_decompress_prefix(
+                &self.compressed,
+                header.image_data_size().unwrap_or(usize::MAX),
+            )?
+            .data,
+        )
+    }
+
+    // The frame as a PNG of its own, at the frame's size & not drawn onto the canvas.
+    // The image data is copied over as is, so nothing is re-encoded.
+    pub fn to_png(&self, png: &Png) -> Vec<u8> {
+        let chunks = copied_chunks(png, |chunk_type| {
+            COLOR_CHUNKS.contains(&chunk_type) || PIXEL_FORMAT_CHUNKS.contains(&chunk_type)
+        });
+        writer::write_png(&self.header(png.ihdr()), &chunks, &self.compressed)
+    }
+}
+
+// A canvas from `Png::canvases` as a PNG of its own (8 bit RGBA)
+pub fn canvas_to_png(png: &Png, canvas: &[[u8; 4]]) -> Vec<u8> {
+    let header = IHDR {
+        width: png.ihdr().width,
+        height: png.ihdr().height,
+        bit_depth: 8,
+        color_type: 6,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: 0,
+    };
+    // A greyscale image's ICC profile is a grey one, which can't describe RGB pixels
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#11iCCP
+    let is_greyscale = matches!(png.ihdr().color_type, 0 | 4);
+    let chunks = copied_chunks(png, |chunk_type| {
+        COLOR_CHUNKS.contains(&chunk_type) && !(is_greyscale && chunk_type == ChunkType::iCCP)
+    });
+    writer::write_png(
+        &header,
+        &chunks,
+        &writer::rgba8_image_data(header.width, canvas),
+    )
+}
+
+// The valid chunks of the given types, in the order they were in
+fn copied_chunks(png: &Png, copy: impl Fn(ChunkType) -> bool) -> Vec<&Chunk> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| copy(*chunk.chunk_type()))
+        .filter(|chunk| !matches!(chunk.parsed(), ParsedChunk::Invalid(..)))
+        .collect()
+}
+
+// Groups the fcTL, IDAT & fdAT chunks into frames, checking the sequence numbers
+pub(crate) fn frames(png: &Png) -> Result<Vec<Frame>, AnimationError> {
+    if png.animation_control().is_none() {
+        return Err(AnimationError::NotAnimated);
+    }
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut current: Option<Frame> = None;
+    let mut expected_sequence_number = 0;
+
+    let mut check_sequence_number = |found| {
+        if found != expected_sequence_number {
+            return Err(AnimationError::SequenceNumber {
+                expected: expected_sequence_number,
+                found,
+            });
+        }
+        expected_sequence_number += 1;
+        Ok(())
+    };
+
+    let finish = |frame: Frame, frames: &mut Vec<Frame>| {
+        if frame.compressed.is_empty() {
+            return Err(AnimationError::MissingFrameData {
+                frame: frame.number,
+            });
+        }
+        frames.push(frame);
+        Ok(())
+    };
+
+    for chunk in png.chunks() {
+        match chunk.parsed() {
+            ParsedChunk::fcTL(control) => {
+                check_sequence_number(control.sequence_number)?;
+
+                if let Some(frame) = current.take() {
+                    finish(frame, &mut frames)?;
+                }
+                current = Some(Frame {
+                    number: frames.len(),
+                    control: control.clone(),
+                    is_default_image: false,
+                    compressed: Vec::new(),
+                });
+            }
+            ParsedChunk::fdAT(data) => {
+                check_sequence_number(data.sequence_number)?;
+
+                match &mut current {
+                    Some(frame) if !frame.is_default_image => {
+                        // Skipping the sequence number, the rest is the same as IDAT's data
+                        frame.compressed.extend_from_slice(&chunk.data()[4..]);
+                    }
+                    _ => {
+                        return Err(AnimationError::UnexpectedFrameData {
+                            sequence_number: data.sequence_number,
+                        })
+                    }
+                }
+            }
+            // Only part of the animation if its fcTL came first
+            ParsedChunk::IDAT => {
+                if let Some(frame) = &mut current {
+                    frame.is_default_image = true;
+                    frame.compressed.extend_from_slice(chunk.data());
+                }
+            }
+            ParsedChunk::Invalid(error)
+                if matches!(chunk.chunk_type(), ChunkType::fcTL | ChunkType::fdAT) =>
+            {
+                return Err(AnimationError::InvalidChunk {
+                    chunk_type: *chunk.chunk_type(),
+                    error: error.clone(),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(frame) = current {
+        finish(frame, &mut frames)?;
+    }
+
+    Ok(frames)
+}
+
+// Draws each frame onto the canvas in turn, yielding the whole canvas (8 bit RGBA,
+// rows top to bottom) as it's shown for each frame. Canvases are made one at a time,
+// so only the current one is kept between frames.
+// https://www.w3.org/TR/png-3/#fcTL-chunk
+#[derive(Debug, Clone)]
+pub struct Canvases<'a> {
+    png: &'a Png,
+    frames: std::vec::IntoIter<Frame>,
+    // Empty until the first frame is drawn
+    canvas: Vec<[u8; 4]>,
+}
+
+impl<'a> Canvases<'a> {
+    pub(crate) fn new(png: &'a Png, frames: Vec<Frame>) -> Self {
+        Canvases {
+            png,
+            frames: frames.into_iter(),
+            canvas: Vec::new(),
+        }
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<Vec<[u8; 4]>, AnimationError> {
+        let image_error = |error| AnimationError::Image {
+            frame: frame.number,
+            error,
+        };
+        let width = self.png.ihdr().width as usize;
+        let height = self.png.ihdr().height as usize;
+
+        // The canvas starts out fully transparent black
+        if self.canvas.is_empty() {
+            let size = width
+                .checked_mul(height)
+                .ok_or(image_error(ImageError::TooLarge {
+                    width: self.png.ihdr().width,
+                    height: self.png.ihdr().height,
+                }))?;
+            self.canvas = vec![[0; 4]; size];
+        }
+
+        let pixels = frame
+            .pixels(self.png)
+            .and_then(|pixels| pixels.to_rgba8())
+            .map_err(image_error)?;
+        let control = &frame.control;
+        let canvas = &mut self.canvas;
+
+        // Disposing to previous goes back to the canvas from before this frame. For
+        // the first frame that's the transparent canvas, the same as disposing to background.
+        let previous = match control.dispose_op {
+            fcTL::DISPOSE_OP_PREVIOUS => Some(canvas.clone()),
+            _ => None,
+        };
+
+        let region = |x: usize, y: usize| {
+            (control.y_offset as usize + y) * width + control.x_offset as usize + x
+        };
+        let frame_width = control.width as usize;
+
+        for y in 0..control.height as usize {
+            for x in 0..frame_width {
+                let source = pixels[y * frame_width + x];
+                let destination = &mut canvas[region(x, y)];
+                *destination = match control.blend_op {
+                    fcTL::BLEND_OP_SOURCE => source,
+                    _ => blend_over(source, *destination),
+                };
+            }
+        }
+
+        let shown = canvas.clone();
+
+        match (control.dispose_op, previous) {
+            (fcTL::DISPOSE_OP_BACKGROUND, _) => {
+                for y in 0..control.height as usize {
+                    for x in 0..frame_width {
+                        canvas[region(x, y)] = [0; 4];
+                    }
+                }
+            }
+            (_, Some(previous)) => *canvas = previous,
+            _ => (),
+        }
+
+        Ok(shown)
+    }
+}
+
+impl Iterator for Canvases<'_> {
+    type Item = Result<Vec<[u8; 4]>, AnimationError>;
+
+    // Stops after the first error, as the frames after it would be drawn on the wrong canvas
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        let canvas = self.draw(&frame);
+        if canvas.is_err() {
+            self.frames = Vec::new().into_iter();
+        }
+        Some(canvas)
+    }
+}
+
+// Alpha compositing of the frame's pixel over the canvas
+// https://www.w3.org/TR/png-3/#13Alpha-channel-processing
+fn blend_over(source: [u8; 4], destination: [u8; 4]) -> [u8; 4] {
+    let source_alpha = source[3] as u32;
+    match source_alpha {
+        255 => return source,
+        0 => return destination,
+        _ => (),
+    }
+
+    // How much of the canvas still shows through
+    let destination_alpha = destination[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + destination_alpha;
+
+    let mut blended = [0; 4];
+    for channel in 0..3 {
+        blended[channel] = ((source[channel] as u32 * source_alpha
+            + destination[channel] as u32 * destination_alpha)
+            / alpha) as u8;
+    }
+    blended[3] = alpha as u8;
+    blended
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::PNG_SIGNATURE;
+    use crate::testing;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0; 4];
+
+    // A frame's place on the canvas, how it's drawn & its pixels. A frame without
+    // pixels is 1 x 1 with data that can't be decompressed.
+    struct TestFrame {
+        x_offset: u32,
+        y_offset: u32,
+        width: u32,
+        dispose_op: u8,
+        blend_op: u8,
+        pixels: Vec<[u8; 4]>,
+    }
+
+    fn frame(x_offset: u32, y_offset: u32, width: u32, pixels: &[[u8; 4]]) -> TestFrame {
+        TestFrame {
+            x_offset,
+            y_offset,
+            width,
+            dispose_op: fcTL::DISPOSE_OP_NONE,
+            blend_op: fcTL::BLEND_OP_SOURCE,
+            pixels: pixels.to_vec(),
+        }
+    }
+
+    // A 2 x 2 RGBA APNG, with the first frame as the IDAT image
+    fn apng(frames: &[TestFrame]) -> Png {
+        let mut data = PNG_SIGNATURE.to_vec();
+        let mut sequence_number = 0;
+        let mut next = || {
+            sequence_number += 1;
+            (sequence_number - 1u32).to_be_bytes()
+        };
+
+        writer::write_chunk(
+            &mut data,
+            ChunkType::IHDR,
+            &[0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0],
+        );
+        let actl = [(frames.len() as u32).to_be_bytes(), 0u32.to_be_bytes()].concat();
+        writer::write_chunk(&mut data, ChunkType::acTL, &actl);
+
+        for (index, frame) in frames.iter().enumerate() {
+            let height = (frame.pixels.len() as u32 / frame.width).max(1);
+            let mut fctl = next().to_vec();
+            for value in [frame.width, height, frame.x_offset, frame.y_offset] {
+                fctl.extend_from_slice(&value.to_be_bytes());
+            }
+            fctl.extend_from_slice(&[0, 1, 0, 10, frame.dispose_op, frame.blend_op]);
+            writer::write_chunk(&mut data, ChunkType::fcTL, &fctl);
+
+            let image_data = match frame.pixels.is_empty() {
+                true => vec![0x78, 0x01, 0x07],
+                false => writer::rgba8_image_data(frame.width, &frame.pixels),
+            };
+            match index {
+                0 => writer::write_chunk(&mut data, ChunkType::IDAT, &image_data),
+                _ => {
+                    let fdat = [&next()[..], &image_data].concat();
+                    writer::write_chunk(&mut data, ChunkType::fdAT, &fdat);
+                }
+            }
+        }
+        writer::write_chunk(&mut data, ChunkType::IEND, &[]);
+
+        Png::from_bytes(&data).unwrap()
+    }
+
+    fn canvases(frames: &[TestFrame]) -> Vec<Vec<[u8; 4]>> {
+        let png = apng(frames);
+        let canvases = png.canvases().unwrap();
+        canvases.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn frames_are_grouped() {
+        let png = apng(&[frame(0, 0, 2, &[RED; 4]), frame(1, 1, 1, &[BLUE])]);
+        let frames = png.frames().unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].is_default_image);
+        assert!(!frames[1].is_default_image);
+        assert_eq!(frames[1].control.x_offset, 1);
+        assert_eq!(frames[1].pixels(&png).unwrap().width(), 1);
+    }
+
+    #[test]
+    fn dispose_none() {
+        let canvases = canvases(&[
+            frame(0, 0, 2, &[RED; 4]),
+            frame(1, 1, 1, &[BLUE]),
+            frame(0, 0, 1, &[GREEN]),
+        ]);
+
+        assert_eq!(canvases[0], [RED; 4]);
+        assert_eq!(canvases[1], [RED, RED, RED, BLUE]);
+        assert_eq!(canvases[2], [GREEN, RED, RED, BLUE]);
+    }
+
+    #[test]
+    fn dispose_background() {
+        let mut first = frame(0, 0, 2, &[RED; 4]);
+        first.dispose_op = fcTL::DISPOSE_OP_BACKGROUND;
+        let mut second = frame(1, 0, 1, &[BLUE, BLUE]);
+        second.dispose_op = fcTL::DISPOSE_OP_BACKGROUND;
+        let canvases = canvases(&[first, second, frame(0, 0, 1, &[GREEN])]);
+
+        // Each frame is shown, then only its own region is cleared
+        assert_eq!(canvases[0], [RED; 4]);
+        assert_eq!(canvases[1], [CLEAR, BLUE, CLEAR, BLUE]);
+        assert_eq!(canvases[2], [GREEN, CLEAR, CLEAR, CLEAR]);
+    }
+
+    #[test]
+    fn dispose_previous() {
+        let mut second = frame(0, 0, 1, &[GREEN]);
+        second.dispose_op = fcTL::DISPOSE_OP_PREVIOUS;
+        let canvases = canvases(&[frame(0, 0, 2, &[RED; 4]), second, frame(1, 1, 1, &[BLUE])]);
+
+        assert_eq!(canvases[1], [GREEN, RED, RED, RED]);
+        // The green pixel is gone again
+        assert_eq!(canvases[2], [RED, RED, RED, BLUE]);
+    }
+
+    #[test]
+    fn dispose_previous_on_the_first_frame() {
+        // There's no canvas before the first frame, so it goes back to transparent
+        let mut first = frame(0, 0, 2, &[RED; 4]);
+        first.dispose_op = fcTL::DISPOSE_OP_PREVIOUS;
+        let canvases = canvases(&[first, frame(0, 0, 1, &[BLUE])]);
+
+        assert_eq!(canvases[0], [RED; 4]);
+        assert_eq!(canvases[1], [BLUE, CLEAR, CLEAR, CLEAR]);
+    }
+
+    #[test]
+    fn blend_source_replaces() {
+        // Even fully transparent pixels replace what's under them
+        let canvases = canvases(&[
+            frame(0, 0, 2, &[RED; 4]),
+            frame(0, 0, 2, &[CLEAR, [0, 0, 255, 128], RED, RED]),
+        ]);
+
+        assert_eq!(canvases[1], [CLEAR, [0, 0, 255, 128], RED, RED]);
+    }
+
+    #[test]
+    fn blend_over_mixes() {
+        let mut second = frame(0, 0, 2, &[CLEAR, [0, 0, 255, 128], BLUE, [0, 0, 255, 128]]);
+        second.blend_op = fcTL::BLEND_OP_OVER;
+        let canvases = canvases(&[frame(0, 0, 2, &[RED, RED, RED, CLEAR]), second]);
+
+        assert_eq!(
+            canvases[1],
+            [RED, [127, 0, 128, 255], BLUE, [0, 0, 255, 128]]
+        );
+    }
+
+    #[test]
+    fn blend_over_alpha() {
+        assert_eq!(blend_over(BLUE, RED), BLUE);
+        assert_eq!(blend_over(CLEAR, RED), RED);
+        // Half over half: the canvas keeps 128 * 127 / 255 = 63 of its alpha
+        assert_eq!(
+            blend_over([0, 0, 255, 128], [255, 0, 0, 128]),
+            [84, 0, 170, 191]
+        );
+    }
+
+    #[test]
+    fn canvases_stop_after_an_error() {
+        let png = apng(&[
+            frame(0, 0, 2, &[RED; 4]),
+            frame(0, 0, 1, &[]),
+            frame(0, 0, 1, &[GREEN]),
+        ]);
+        let mut canvases = png.canvases().unwrap();
+        assert!(canvases.next().unwrap().is_ok());
+        assert!(matches!(
+            canvases.next(),
+            Some(Err(AnimationError::Image { frame: 1, .. }))
+        ));
+        assert!(canvases.next().is_none());
+    }
+
+    #[test]
+    fn not_animated() {
+        let mut data = PNG_SIGNATURE.to_vec();
+        writer::write_chunk(
+            &mut data,
+            ChunkType::IHDR,
+            &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0],
+        );
+        writer::write_chunk(&mut data, ChunkType::IDAT, &writer::zlib_stored(&[0, 0]));
+        writer::write_chunk(&mut data, ChunkType::IEND, &[]);
+        let png = Png::from_bytes(&data).unwrap();
+
+        assert!(matches!(png.canvases(), Err(AnimationError::NotAnimated)));
+    }
+
+    // The chunk types of a canvas written out for an image of the given color type
+    fn canvas_chunk_types(color_type: u8) -> Vec<ChunkType> {
+        let iccp = [b"icc\0\0".as_slice(), &writer::zlib_stored(b"profile")].concat();
+        let bytes = testing::png(&[
+            (b"IHDR", &testing::ihdr(1, 1, 8, color_type)),
+            (b"gAMA", &[0, 0, 0xb1, 0x8f]),
+            (b"iCCP", &iccp),
+            (b"IDAT", &writer::zlib_stored(&[0, 0, 0, 0, 0])),
+            (b"IEND", &[]),
+        ]);
+        let png = Png::from_bytes(&bytes).unwrap();
+
+        let canvas = Png::from_bytes(&canvas_to_png(&png, &[RED])).unwrap();
+        assert_eq!(canvas.ihdr().color_type, 6);
+        assert_eq!(canvas.pixels().unwrap().to_rgba8().unwrap(), [RED]);
+        canvas
+            .chunks()
+            .iter()
+            .map(|chunk| *chunk.chunk_type())
+            .collect()
+    }
+
+    #[test]
+    fn canvases_keep_the_color_chunks() {
+        assert_eq!(
+            canvas_chunk_types(2),
+            [
+                ChunkType::IHDR,
+                ChunkType::gAMA,
+                ChunkType::iCCP,
+                ChunkType::IDAT,
+                ChunkType::IEND
+            ]
+        );
+    }
+
+    #[test]
+    fn canvases_drop_grey_icc_profiles() {
+        for color_type in [0, 4] {
+            assert_eq!(
+                canvas_chunk_types(color_type),
+                [
+                    ChunkType::IHDR,
+                    ChunkType::gAMA,
+                    ChunkType::IDAT,
+                    ChunkType::IEND
+                ]
+            );
+        }
+    }
+}
@@ -0,0 +1,3497 @@
+// Typed versions of the standard chunks, decoded from a `Chunk`'s data.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11Chunks
+#![allow(non_camel_case_types)]
+
+use std::fmt;
+
+use crate::colorimetry::Gamut;
+use crate::error::{ChunkError, ExifError};
+use crate::exif::Exif;
+use crate::icc::IccProfile;
+use crate::inflate;
+use crate::interlace;
+use crate::parser::{Chunk, ChunkType};
+use crate::reader::ChunkReader;
+
+// Implemented by every chunk struct. Some chunks can only be understood with
+// chunks that came before them (tRNS needs IHDR's color type), which is what
+// `ParseContext` is for.
+pub trait Parse: Sized {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError>;
+}
+
+// The already decoded chunks a chunk may depend on.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParseContext<'a> {
+    pub header: Option<&'a IHDR>,
+    pub pallet: Option<&'a PLTE>,
+}
+
+impl<'a> ParseContext<'a> {
+    fn header(&self) -> Result<&'a IHDR, ChunkError> {
+        self.header.ok_or(ChunkError::MissingIhdr)
+    }
+
+    fn pallet(&self) -> Result<&'a PLTE, ChunkError> {
+        self.pallet.ok_or(ChunkError::MissingPlte)
+    }
+}
+
+// The decoded form of a chunk, stored next to its raw bytes.
+#[derive(Debug, Clone)]
+pub enum ParsedChunk {
+    IHDR(IHDR),
+    PLTE(PLTE),
+    // Image data is only meaningful once all IDAT chunks are put together
+    IDAT,
+    IEND,
+
+    tRNS(tRNS),
+    cHRM(cHRM),
+    gAMA(gAMA),
+    iCCP(iCCP),
+    sBIT(sBIT),
+    sRGB(sRGB),
+    bKGD(bKGD),
+    hIST(hIST),
+    pHYs(pHYs),
+    sPLT(sPLT),
+    tIME(tIME),
+    tEXt(tEXt),
+    zTXt(zTXt),
+    iTXt(iTXt),
+
+    acTL(acTL),
+    fcTL(fcTL),
+    fdAT(fdAT),
+
+    cICP(cICP),
+    mDCV(mDCV),
+    cLLI(cLLI),
+    eXIf(eXIf),
+
+    oFFs(oFFs),
+    pCAL(pCAL),
+    sCAL(sCAL),
+    sTER(sTER),
+    gIFg(gIFg),
+    gIFx(gIFx),
+
+    // Chunks that aren't decoded, the raw data is still on the chunk
+    Raw,
+
+    // The chunk type is known but its data isn't valid
+    Invalid(ChunkError),
+}
+
+impl ParsedChunk {
+    // Decodes the chunk based on its type
+    pub fn parse(chunk: &Chunk, context: &ParseContext) -> Result<ParsedChunk, ChunkError> {
+        Ok(match chunk.chunk_type() {
+            ChunkType::IHDR => ParsedChunk::IHDR(IHDR::parse(chunk, context)?),
+            ChunkType::PLTE => ParsedChunk::PLTE(PLTE::parse(chunk, context)?),
+            ChunkType::IDAT => ParsedChunk::IDAT,
+            ChunkType::IEND => {
+                expect_length(chunk, 0)?;
+                ParsedChunk::IEND
+            }
+
+            ChunkType::tRNS => ParsedChunk::tRNS(tRNS::parse(chunk, context)?),
+            ChunkType::cHRM => ParsedChunk::cHRM(cHRM::parse(chunk, context)?),
+            ChunkType::gAMA => ParsedChunk::gAMA(gAMA::parse(chunk, context)?),
+            ChunkType::iCCP => ParsedChunk::iCCP(iCCP::parse(chunk, context)?),
+            ChunkType::sBIT => ParsedChunk::sBIT(sBIT::parse(chunk, context)?),
+            ChunkType::sRGB => ParsedChunk::sRGB(sRGB::parse(chunk, context)?),
+            ChunkType::bKGD => ParsedChunk::bKGD(bKGD::parse(chunk, context)?),
+            ChunkType::hIST => ParsedChunk::hIST(hIST::parse(chunk, context)?),
+            ChunkType::pHYs => ParsedChunk::pHYs(pHYs::parse(chunk, context)?),
+            ChunkType::sPLT => ParsedChunk::sPLT(sPLT::parse(chunk, context)?),
+            ChunkType::tIME => ParsedChunk::tIME(tIME::parse(chunk, context)?),
+
+            ChunkType::tEXt => ParsedChunk::tEXt(tEXt::parse(chunk, context)?),
+            ChunkType::zTXt => ParsedChunk::zTXt(zTXt::parse(chunk, context)?),
+            ChunkType::iTXt => ParsedChunk::iTXt(iTXt::parse(chunk, context)?),
+
+            ChunkType::acTL => ParsedChunk::acTL(acTL::parse(chunk, context)?),
+            ChunkType::fcTL => ParsedChunk::fcTL(fcTL::parse(chunk, context)?),
+            ChunkType::fdAT => ParsedChunk::fdAT(fdAT::parse(chunk, context)?),
+
+            ChunkType::cICP => ParsedChunk::cICP(cICP::parse(chunk, context)?),
+            ChunkType::mDCV => ParsedChunk::mDCV(mDCV::parse(chunk, context)?),
+            ChunkType::cLLI => ParsedChunk::cLLI(cLLI::parse(chunk, context)?),
+            ChunkType::eXIf => ParsedChunk::eXIf(eXIf::parse(chunk, context)?),
+
+            ChunkType::oFFs => ParsedChunk::oFFs(oFFs::parse(chunk, context)?),
+            ChunkType::pCAL => ParsedChunk::pCAL(pCAL::parse(chunk, context)?),
+            ChunkType::sCAL => ParsedChunk::sCAL(sCAL::parse(chunk, context)?),
+            ChunkType::sTER => ParsedChunk::sTER(sTER::parse(chunk, context)?),
+            ChunkType::gIFg => ParsedChunk::gIFg(gIFg::parse(chunk, context)?),
+            ChunkType::gIFx => ParsedChunk::gIFx(gIFx::parse(chunk, context)?),
+
+            ChunkType::Unknown(..) => ParsedChunk::Raw,
+        })
+    }
+}
+
+// Passes the formatter (and so the `{:#}` descriptive flag) on to the chunk struct.
+impl fmt::Display for ParsedChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsedChunk::IHDR(chunk) => chunk.fmt(f),
+            ParsedChunk::PLTE(chunk) => chunk.fmt(f),
+            ParsedChunk::IDAT | ParsedChunk::Raw => Ok(()),
+            ParsedChunk::IEND => writeln!(f, " (no data)"),
+
+            ParsedChunk::tRNS(chunk) => chunk.fmt(f),
+            ParsedChunk::cHRM(chunk) => chunk.fmt(f),
+            ParsedChunk::gAMA(chunk) => chunk.fmt(f),
+            ParsedChunk::iCCP(chunk) => chunk.fmt(f),
+            ParsedChunk::sBIT(chunk) => chunk.fmt(f),
+            ParsedChunk::sRGB(chunk) => chunk.fmt(f),
+            ParsedChunk::bKGD(chunk) => chunk.fmt(f),
+            ParsedChunk::hIST(chunk) => chunk.fmt(f),
+            ParsedChunk::pHYs(chunk) => chunk.fmt(f),
+            ParsedChunk::sPLT(chunk) => chunk.fmt(f),
+            ParsedChunk::tIME(chunk) => chunk.fmt(f),
+            ParsedChunk::tEXt(chunk) => chunk.fmt(f),
+            ParsedChunk::zTXt(chunk) => chunk.fmt(f),
+            ParsedChunk::iTXt(chunk) => chunk.fmt(f),
+            ParsedChunk::acTL(chunk) => chunk.fmt(f),
+            ParsedChunk::fcTL(chunk) => chunk.fmt(f),
+            ParsedChunk::fdAT(chunk) => chunk.fmt(f),
+            ParsedChunk::cICP(chunk) => chunk.fmt(f),
+            ParsedChunk::mDCV(chunk) => chunk.fmt(f),
+            ParsedChunk::cLLI(chunk) => chunk.fmt(f),
+            ParsedChunk::eXIf(chunk) => chunk.fmt(f),
+            ParsedChunk::oFFs(chunk) => chunk.fmt(f),
+            ParsedChunk::pCAL(chunk) => chunk.fmt(f),
+            ParsedChunk::sCAL(chunk) => chunk.fmt(f),
+            ParsedChunk::sTER(chunk) => chunk.fmt(f),
+            ParsedChunk::gIFg(chunk) => chunk.fmt(f),
+            ParsedChunk::gIFx(chunk) => chunk.fmt(f),
+
+            ParsedChunk::Invalid(error) => writeln!(f, " invalid: {}", error),
+        }
+    }
+}
+
+// For chunks that always have the same length
+fn expect_length(chunk: &Chunk, length: usize) -> Result<(), ChunkError> {
+    if chunk.length() != length {
+        return Err(ChunkError::InvalidLength {
+            length: chunk.length(),
+        });
+    }
+    Ok(())
+}
+
+// The most iCCP, zTXt & iTXt are allowed to decompress to. Far more than any real
+// profile or text needs (libpng's own default limit is 8 MB).
+const MAX_DECOMPRESSED_CHUNK: usize = 16 * 1024 * 1024;
+
+// Compression method 0 (zlib) is the only one defined, for iCCP, zTXt & iTXt
+fn decompress(compression_method: u8, data: &[u8]) -> Result<Vec<u8>, ChunkError> {
+    if compression_method != 0 {
+        return Err(ChunkError::InvalidValue {
+            field: "compression method",
+            value: compression_method as u32,
+        });
+    }
+
+    Ok(inflate::zlib_decompress(data, MAX_DECOMPRESSED_CHUNK)?)
+}
+
+// Reads a compression method, checking it's the only one defined (0, zlib)
+fn read_compression_method(reader: &mut ChunkReader) -> Result<u8, ChunkError> {
+    let compression_method = reader.read_u8()?;
+    if compression_method != 0 {
+        return Err(ChunkError::InvalidValue {
+            field: "compression method",
+            value: compression_method as u32,
+        });
+    }
+    Ok(compression_method)
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.IHDR
+#[derive(Debug, Clone)]
+pub struct IHDR {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+impl Parse for IHDR {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 13)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let ihdr = IHDR {
+            width: reader.read_u32()?,
+            height: reader.read_u32()?,
+            bit_depth: reader.read_u8()?,
+            color_type: reader.read_u8()?,
+            compression_method: reader.read_u8()?,
+            filter_method: reader.read_u8()?,
+            interlace_method: reader.read_u8()?,
+        };
+
+        ihdr.validate()?;
+        Ok(ihdr)
+    }
+}
+
+impl IHDR {
+    // Samples per pixel for each color type
+    pub fn channels_for(color_type: u8) -> usize {
+        match color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        IHDR::channels_for(self.color_type)
+    }
+
+    pub fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    // How many bytes back the "left" byte is when filtering, the bytes per pixel
+    // rounded up to 1 for bit depths under 8
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#9Filter-types
+    pub fn filter_distance(&self) -> usize {
+        self.bits_per_pixel().div_ceil(8)
+    }
+
+    // Bytes in a scanline of `width` pixels, not counting the filter type byte
+    pub fn scanline_length(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    // Bytes of filtered image data the image needs, including the filter type bytes.
+    // None when that doesn't fit in a usize, as no image that big could be decoded anyway.
+    pub fn image_data_size(&self) -> Option<usize> {
+        match self.interlace_method {
+            0 => (self.scanline_length(self.width) + 1).checked_mul(self.height as usize),
+            _ => interlace::adam7_passes(self)
+                .iter()
+                .try_fold(0usize, |size, pass| size.checked_add(pass.byte_size(self)?)),
+        }
+    }
+
+    // Checks every field holds a value the spec allows
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#11IHDR
+    fn validate(&self) -> Result<(), ChunkError> {
+        // Dimensions are 1 to 2^31 - 1
+        for (field, value) in [("width", self.width), ("height", self.height)] {
+            if value == 0 || value > i32::MAX as u32 {
+                return Err(ChunkError::InvalidValue { field, value });
+            }
+        }
+
+        let allowed_bit_depths: &[u8] = match self.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            2 | 4 | 6 => &[8, 16],
+            3 => &[1, 2, 4, 8],
+            color_type => return Err(ChunkError::InvalidColorType { color_type }),
+        };
+
+        if !allowed_bit_depths.contains(&self.bit_depth) {
+            return Err(ChunkError::InvalidBitDepth {
+                color_type: self.color_type,
+                bit_depth: self.bit_depth,
+            });
+        }
+
+        // Only method 0 is defined for compression & filtering, interlacing also has 1 (Adam7)
+        let methods = [
+            ("compression method", self.compression_method, 0),
+            ("filter method", self.filter_method, 0),
+            ("interlace method", self.interlace_method, 1),
+        ];
+        for (field, value, max) in methods {
+            if value > max {
+                return Err(ChunkError::InvalidValue {
+                    field,
+                    value: value as u32,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for IHDR {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(f, " width: {} | Width in Pixels", self.width)?;
+            writeln!(f, " height: {} | Height in Pixels", self.height)?;
+            writeln!(
+                f,
+                " bit depth: {} | Bits Per Sample / Bits per pallet index",
+                self.bit_depth
+            )?;
+            writeln!(f, " color type: {} | 0 = Greyscale; 2 = RGB; 3 = Pallet Index; 4 = Greyscale + Alpha; 6 = RGB + Alpha", self.color_type)?;
+            writeln!(
+                f,
+                " compression method: {} | Only \"0\" is defined",
+                self.compression_method
+            )?;
+            writeln!(
+                f,
+                " filter method: {} | Any preprossessing applied before compression",
+                self.filter_method
+            )?;
+            writeln!(
+                f,
+                " interlace method: {} | Type of interlasing. 0 = None; 1 = Adam7",
+                self.interlace_method
+            )
+        } else {
+            writeln!(f, " width: {}", self.width)?;
+            writeln!(f, " height: {}", self.height)?;
+            writeln!(f, " bit depth: {}", self.bit_depth)?;
+            writeln!(f, " color type: {}", self.color_type)?;
+            writeln!(f, " compression method: {}", self.compression_method)?;
+            writeln!(f, " filter method: {}", self.filter_method)?;
+            writeln!(f, " interlace method: {}", self.interlace_method)
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.PLTE
+#[derive(Debug, Clone)]
+pub struct PLTE {
+    // R,G,B
+    pub pallet: Vec<(u8, u8, u8)>,
+}
+
+impl Parse for PLTE {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        // 1 to 256 entries of 3 bytes each
+        if chunk.length() == 0 || !chunk.length().is_multiple_of(3) || chunk.length() > 256 * 3 {
+            return Err(ChunkError::InvalidLength {
+                length: chunk.length(),
+            });
+        }
+
+        let mut reader = ChunkReader::new(chunk.data());
+        let mut pallet = Vec::new();
+        while !reader.is_empty() {
+            pallet.push((reader.read_u8()?, reader.read_u8()?, reader.read_u8()?));
+        }
+
+        Ok(PLTE { pallet })
+    }
+}
+
+impl fmt::Display for PLTE {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " {} entries | Scheme: Index: (Red, Green, Blue)",
+                self.pallet.len()
+            )?;
+        }
+        for (index, colors) in self.pallet.iter().enumerate() {
+            writeln!(f, " {}: {:?}", index, colors)?;
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.tRNS
+#[derive(Debug, Clone)]
+pub enum tRNS {
+    // Color type 0
+    Greyscale {
+        grey_sample_value: u16,
+    },
+    // Type 2
+    Truecolor {
+        red_sample_value: u16,
+        green_sample_value: u16,
+        blue_sample_value: u16,
+    },
+    // Type 3, one alpha per pallet entry
+    Indexed {
+        pallet: Vec<u8>,
+    },
+}
+
+impl Parse for tRNS {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+        let header = context.header()?;
+
+        // Key colors have to fit in the image's bit depth
+        let max_sample = (1u32 << header.bit_depth) - 1;
+        let read_sample = |reader: &mut ChunkReader, field| {
+            let value = reader.read_u16()?;
+            if value as u32 > max_sample {
+                return Err(ChunkError::InvalidValue {
+                    field,
+                    value: value as u32,
+                });
+            }
+            Ok(value)
+        };
+
+        match header.color_type {
+            0 => {
+                expect_length(chunk, 2)?;
+                Ok(tRNS::Greyscale {
+                    grey_sample_value: read_sample(&mut reader, "grey sample")?,
+                })
+            }
+            2 => {
+                expect_length(chunk, 6)?;
+                Ok(tRNS::Truecolor {
+                    red_sample_value: read_sample(&mut reader, "red sample")?,
+                    green_sample_value: read_sample(&mut reader, "green sample")?,
+                    blue_sample_value: read_sample(&mut reader, "blue sample")?,
+                })
+            }
+            3 => {
+                let pallet = reader.read_rest().to_vec();
+                let max = context.pallet()?.pallet.len();
+                if pallet.len() > max {
+                    return Err(ChunkError::TooManyEntries {
+                        entries: pallet.len(),
+                        max,
+                    });
+                }
+                Ok(tRNS::Indexed { pallet })
+            }
+            // Types 4 & 6 already have an alpha channel
+            4 | 6 => Err(ChunkError::NotAllowedForColorType {
+                color_type: header.color_type,
+            }),
+            color_type => Err(ChunkError::InvalidColorType { color_type }),
+        }
+    }
+}
+
+impl tRNS {
+    // The alpha of pallet entry `index`, entries past the end of tRNS are opaque
+    pub fn pallet_alpha(&self, index: u8) -> u8 {
+        match self {
+            tRNS::Indexed { pallet } => pallet.get(index as usize).copied().unwrap_or(255),
+            _ => 255,
+        }
+    }
+}
+
+impl fmt::Display for tRNS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match f.alternate() {
+            true => " | Pixels of exactly this value are fully transparent",
+            false => "",
+        };
+
+        match self {
+            tRNS::Greyscale { grey_sample_value } => {
+                writeln!(f, " transparent grey: {}{}", grey_sample_value, description)
+            }
+            tRNS::Truecolor {
+                red_sample_value,
+                green_sample_value,
+                blue_sample_value,
+            } => writeln!(
+                f,
+                " transparent color: ({}, {}, {}){}",
+                red_sample_value, green_sample_value, blue_sample_value, description
+            ),
+            tRNS::Indexed { pallet } => {
+                if f.alternate() {
+                    writeln!(
+                        f,
+                        " {} entries | Scheme: Index: Alpha, later pallet entries are opaque",
+                        pallet.len()
+                    )?;
+                }
+                for (index, alpha) in pallet.iter().enumerate() {
+                    writeln!(f, " {}: {}", index, alpha)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.cHRM
+// Each value is the chromaticity times 100000
+#[derive(Debug, Clone)]
+pub struct cHRM {
+    pub white_x: u32,
+    pub white_y: u32,
+
+    pub red_x: u32,
+    pub red_y: u32,
+
+    pub green_x: u32,
+    pub green_y: u32,
+
+    pub blue_x: u32,
+    pub blue_y: u32,
+}
+
+impl Parse for cHRM {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 32)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        Ok(cHRM {
+            white_x: reader.read_u32()?,
+            white_y: reader.read_u32()?,
+            red_x: reader.read_u32()?,
+            red_y: reader.read_u32()?,
+            green_x: reader.read_u32()?,
+            green_y: reader.read_u32()?,
+            blue_x: reader.read_u32()?,
+            blue_y: reader.read_u32()?,
+        })
+    }
+}
+
+impl cHRM {
+    // The values as chromaticities, so divided by 100000
+    pub fn gamut(&self) -> Gamut {
+        let fraction = |x: u32, y: u32| (x as f64 / 100000.0, y as f64 / 100000.0);
+
+        Gamut {
+            name: "cHRM",
+            white: fraction(self.white_x, self.white_y),
+            red: fraction(self.red_x, self.red_y),
+            green: fraction(self.green_x, self.green_y),
+            blue: fraction(self.blue_x, self.blue_y),
+        }
+    }
+}
+
+impl fmt::Display for cHRM {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gamut = self.gamut();
+        let points = [
+            (
+                "white point",
+                gamut.white,
+                "The point which should always be displayed as white",
+            ),
+            ("red", gamut.red, "Red chromaticity"),
+            ("green", gamut.green, "Green chromaticity"),
+            ("blue", gamut.blue, "Blue chromaticity"),
+        ];
+
+        for (name, (x, y), description) in points {
+            if f.alternate() {
+                writeln!(f, " {}: ({:.5}, {:.5}) | {}", name, x, y, description)?;
+            } else {
+                writeln!(f, " {}: ({:.5}, {:.5})", name, x, y)?;
+            }
+        }
+
+        match gamut.known() {
+            Some(known) => writeln!(f, " gamut: {}", known.name)?,
+            None => writeln!(f, " gamut: not a known gamut")?,
+        }
+
+        if f.alternate() {
+            match gamut.rgb_to_xyz() {
+                Some(matrix) => {
+                    writeln!(
+                        f,
+                        " RGB to XYZ: | Turns linear RGB into CIE XYZ, white has Y = 1"
+                    )?;
+                    for (name, row) in ["X", "Y", "Z"].iter().zip(matrix) {
+                        writeln!(
+                            f,
+                            "  {} = {:.6} R + {:.6} G + {:.6} B",
+                            name, row[0], row[1], row[2]
+                        )?;
+                    }
+                }
+                None => writeln!(
+                    f,
+                    " RGB to XYZ: none | These chromaticities can't make a matrix"
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.gAMA
+#[derive(Debug, Clone)]
+pub struct gAMA {
+    // Gamma times 100000
+    pub image_gama: u32,
+}
+
+impl Parse for gAMA {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 4)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        // A gamma of 0 would make every pixel black
+        let image_gama = reader.read_u32()?;
+        if image_gama == 0 {
+            return Err(ChunkError::InvalidValue {
+                field: "gamma",
+                value: image_gama,
+            });
+        }
+
+        Ok(gAMA { image_gama })
+    }
+}
+
+impl gAMA {
+    // The value sRGB's gAMA should have, 1 / 2.2
+    pub const SRGB: u32 = 45455;
+
+    // The encoding gamma, like 0.45455
+    pub fn gamma(&self) -> f64 {
+        self.image_gama as f64 / 100000.0
+    }
+
+    // The exponent a display decodes with, like 2.2
+    pub fn display_exponent(&self) -> f64 {
+        1.0 / self.gamma()
+    }
+}
+
+impl fmt::Display for gAMA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " gamma: {:.5} | Stored as gamma times 100,000 ({})",
+                self.gamma(),
+                self.image_gama
+            )?;
+            writeln!(
+                f,
+                " display exponent: {:.2} | 1 / gamma, the power a display raises samples to",
+                self.display_exponent()
+            )
+        } else {
+            writeln!(
+                f,
+                " gamma: {:.5} (display exponent {:.2})",
+                self.gamma(),
+                self.display_exponent()
+            )
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.iCCP
+#[derive(Debug, Clone)]
+pub struct iCCP {
+    pub profile_name: String, // Bytes 1-79
+
+    //null_separator: u8,
+    pub compression_method: u8,
+    pub compressed_profile: Vec<u8>,
+}
+
+impl Parse for iCCP {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let profile_name = reader.read_keyword()?;
+        let compression_method = read_compression_method(&mut reader)?;
+
+        Ok(iCCP {
+            profile_name,
+            compression_method,
+            compressed_profile: reader.read_rest().to_vec(),
+        })
+    }
+}
+
+impl iCCP {
+    // The ICC profile, decompressed. This is what a standalone .icc file holds.
+    pub fn profile(&self) -> Result<Vec<u8>, ChunkError> {
+        decompress(self.compression_method, &self.compressed_profile)
+    }
+}
+
+impl fmt::Display for iCCP {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " profile name: {} | Only a label, the profile itself has its own description",
+                self.profile_name
+            )?;
+            writeln!(
+                f,
+                " compression method: {} | 0 = zlib, the only one defined",
+                self.compression_method
+            )?;
+        } else {
+            writeln!(f, " profile name: {}", self.profile_name)?;
+            writeln!(f, " compression method: {}", self.compression_method)?;
+        }
+        writeln!(
+            f,
+            " compressed profile: {} bytes",
+            self.compressed_profile.len()
+        )?;
+        let profile = match self.profile() {
+            Ok(x) => x,
+            Err(error) => return writeln!(f, " profile: {}", error),
+        };
+        writeln!(f, " profile: {} bytes", profile.len())?;
+
+        // What's inside the profile, nested under the chunk
+        if f.alternate() {
+            match IccProfile::parse(&profile) {
+                Ok(profile) => {
+                    writeln!(f, " ICC profile:")?;
+                    write!(f, "{}", profile)?;
+                }
+                Err(error) => writeln!(f, " ICC profile: invalid, {}", error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.sBIT
+#[derive(Debug, Clone)]
+pub enum sBIT {
+    // Type 0
+    Greyscale {
+        significant_greyscale_bits: u8,
+    },
+    // Type 2 & 3
+    Truecolor {
+        significant_red_bits: u8,
+        significant_green_bits: u8,
+        significant_blue_bits: u8,
+    },
+    // Type 4
+    GreyscaleAlpha {
+        significant_greyscale_bits: u8,
+        significant_alpha_bits: u8,
+    },
+    // Type 6
+    TruecolorAlpha {
+        significant_red_bits: u8,
+        significant_green_bits: u8,
+        significant_blue_bits: u8,
+        significant_alpha_bits: u8,
+    },
+}
+
+impl Parse for sBIT {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+        let header = context.header()?;
+
+        // Each value has to be from 1 to the sample depth, which is always 8 for indexed images
+        let sample_depth = match header.color_type {
+            3 => 8,
+            _ => header.bit_depth,
+        };
+        let mut read_bits = |field| {
+            let bits = reader.read_u8()?;
+            if bits == 0 || bits > sample_depth {
+                return Err(ChunkError::InvalidValue {
+                    field,
+                    value: bits as u32,
+                });
+            }
+            Ok(bits)
+        };
+
+        match header.color_type {
+            0 => {
+                expect_length(chunk, 1)?;
+                Ok(sBIT::Greyscale {
+                    significant_greyscale_bits: read_bits("significant grey bits")?,
+                })
+            }
+            2 | 3 => {
+                expect_length(chunk, 3)?;
+                Ok(sBIT::Truecolor {
+                    significant_red_bits: read_bits("significant red bits")?,
+                    significant_green_bits: read_bits("significant green bits")?,
+                    significant_blue_bits: read_bits("significant blue bits")?,
+                })
+            }
+            4 => {
+                expect_length(chunk, 2)?;
+                Ok(sBIT::GreyscaleAlpha {
+                    significant_greyscale_bits: read_bits("significant grey bits")?,
+                    significant_alpha_bits: read_bits("significant alpha bits")?,
+                })
+            }
+            6 => {
+                expect_length(chunk, 4)?;
+                Ok(sBIT::TruecolorAlpha {
+                    significant_red_bits: read_bits("significant red bits")?,
+                    significant_green_bits: read_bits("significant green bits")?,
+                    significant_blue_bits: read_bits("significant blue bits")?,
+                    significant_alpha_bits: read_bits("significant alpha bits")?,
+                })
+            }
+            color_type => Err(ChunkError::InvalidColorType { color_type }),
+        }
+    }
+}
+
+impl sBIT {
+    // Each channel's name & significant bits, in the order they're stored
+    pub fn channels(&self) -> Vec<(&'static str, u8)> {
+        match *self {
+            sBIT::Greyscale {
+                significant_greyscale_bits,
+            } => vec![("grey", significant_greyscale_bits)],
+            sBIT::Truecolor {
+                significant_red_bits,
+                significant_green_bits,
+                significant_blue_bits,
+            } => vec![
+                ("red", significant_red_bits),
+                ("green", significant_green_bits),
+                ("blue", significant_blue_bits),
+            ],
+            sBIT::GreyscaleAlpha {
+                significant_greyscale_bits,
+                significant_alpha_bits,
+            } => vec![
+                ("grey", significant_greyscale_bits),
+                ("alpha", significant_alpha_bits),
+            ],
+            sBIT::TruecolorAlpha {
+                significant_red_bits,
+                significant_green_bits,
+                significant_blue_bits,
+                significant_alpha_bits,
+            } => vec![
+                ("red", significant_red_bits),
+                ("green", significant_green_bits),
+                ("blue", significant_blue_bits),
+                ("alpha", significant_alpha_bits),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for sBIT {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " significant bits: | How many bits of each sample hold the original data"
+            )?;
+        } else {
+            writeln!(f, " significant bits:")?;
+        }
+        for (channel, bits) in self.channels() {
+            writeln!(f, "  {}: {}", channel, bits)?;
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.sRGB
+#[derive(Debug, Clone)]
+pub struct sRGB {
+    pub rendering_intent: u8,
+}
+
+impl Parse for sRGB {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 1)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let rendering_intent = reader.read_u8()?;
+        if rendering_intent > 3 {
+            return Err(ChunkError::InvalidValue {
+                field: "rendering intent",
+                value: rendering_intent as u32,
+            });
+        }
+
+        Ok(sRGB { rendering_intent })
+    }
+}
+
+impl sRGB {
+    // The ICC rendering intents, which ICC profiles use too
+    pub fn intent_name(rendering_intent: u8) -> &'static str {
+        match rendering_intent {
+            0 => "Perceptual",
+            1 => "Relative colorimetric",
+            2 => "Saturation",
+            3 => "Absolute colorimetric",
+            _ => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for sRGB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = sRGB::intent_name(self.rendering_intent);
+
+        if f.alternate() {
+            let description = match self.rendering_intent {
+                0 => {
+                    "For images like photographs, keeps colors looking right relative to each other"
+                }
+                1 => "For logos & graphics, keeps in gamut colors exact and clips the rest",
+                2 => "For charts & graphs, keeps colors saturated at the cost of accuracy",
+                _ => "For proofs, keeps colors exact including the white point",
+            };
+            writeln!(
+                f,
+                " rendering intent: {} ({}) | {}",
+                name, self.rendering_intent, description
+            )
+        } else {
+            writeln!(f, " rendering intent: {} ({})", name, self.rendering_intent)
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.bKGD
+#[derive(Debug, Clone)]
+pub enum bKGD {
+    // Type 0 & 4
+    Greyscale { greyscale: u16 },
+    // Type 2 & 6
+    Truecolor { red: u16, green: u16, blue: u16 },
+    // Type 3
+    Indexed { palette_index: u8 },
+}
+
+impl Parse for bKGD {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+        let header = context.header()?;
+
+        // Samples have to fit in the image's bit depth
+        let max_sample = (1u32 << header.bit_depth) - 1;
+        let mut read_sample = |field| {
+            let value = reader.read_u16()?;
+            if value as u32 > max_sample {
+                return Err(ChunkError::InvalidValue {
+                    field,
+                    value: value as u32,
+                });
+            }
+            Ok(value)
+        };
+
+        match header.color_type {
+            0 | 4 => {
+                expect_length(chunk, 2)?;
+                Ok(bKGD::Greyscale {
+                    greyscale: read_sample("grey background")?,
+                })
+            }
+            2 | 6 => {
+                expect_length(chunk, 6)?;
+                Ok(bKGD::Truecolor {
+                    red: read_sample("red background")?,
+                    green: read_sample("green background")?,
+                    blue: read_sample("blue background")?,
+                })
+            }
+            3 => {
+                expect_length(chunk, 1)?;
+                let palette_index = reader.read_u8()?;
+                if palette_index as usize >= context.pallet()?.pallet.len() {
+                    return Err(ChunkError::InvalidValue {
+                        field: "background pallet index",
+                        value: palette_index as u32,
+                    });
+                }
+                Ok(bKGD::Indexed { palette_index })
+            }
+            color_type => Err(ChunkError::InvalidColorType { color_type }),
+        }
+    }
+}
+
+impl fmt::Display for bKGD {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            bKGD::Greyscale { greyscale } => write!(f, " greyscale background: {}", greyscale)?,
+            bKGD::Truecolor { red, green, blue } => {
+                write!(f, " rgb background: ({}, {}, {})", red, green, blue)?
+            }
+            bKGD::Indexed { palette_index } => write!(f, " pallet background: {}", palette_index)?,
+        }
+
+        if f.alternate() {
+            match self {
+                bKGD::Indexed { .. } => writeln!(f, " | The pallet entry to show the image on"),
+                _ => writeln!(
+                    f,
+                    " | The color to show the image on, at the image's bit depth"
+                ),
+            }
+        } else {
+            writeln!(f)
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.hIST
+#[derive(Debug, Clone)]
+pub struct hIST {
+    // One frequency per pallet entry
+    pub histogram: Vec<u16>,
+}
+
+impl Parse for hIST {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError> {
+        if !chunk.length().is_multiple_of(2) {
+            return Err(ChunkError::InvalidLength {
+                length: chunk.length(),
+            });
+        }
+
+        let mut reader = ChunkReader::new(chunk.data());
+        let mut histogram = Vec::new();
+        while !reader.is_empty() {
+            histogram.push(reader.read_u16()?);
+        }
+
+        let expected = context.pallet()?.pallet.len();
+        if histogram.len() != expected {
+            return Err(ChunkError::EntryCountMismatch {
+                entries: histogram.len(),
+                expected,
+            });
+        }
+
+        Ok(hIST { histogram })
+    }
+}
+
+impl fmt::Display for hIST {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Frequencies are only relative to each other, so they're shown as a share of the total
+        let total: u32 = self
+            .histogram
+            .iter()
+            .map(|frequency| *frequency as u32)
+            .sum();
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " {} entries | Scheme: Index: Frequency (share of all pixels, approximate)",
+                self.histogram.len()
+            )?;
+        }
+        for (index, frequency) in self.histogram.iter().enumerate() {
+            if total == 0 {
+                writeln!(f, " {}: {}", index, frequency)?;
+            } else {
+                writeln!(
+                    f,
+                    " {}: {} ({:.1}%)",
+                    index,
+                    frequency,
+                    *frequency as f64 * 100.0 / total as f64
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.pHYs
+#[derive(Debug, Clone)]
+pub struct pHYs {
+    pub pixels_per_x: u32,
+    pub pixels_per_y: u32,
+    pub unit_specifier: u8,
+}
+
+impl Parse for pHYs {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 9)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let phys = pHYs {
+            pixels_per_x: reader.read_u32()?,
+            pixels_per_y: reader.read_u32()?,
+            unit_specifier: reader.read_u8()?,
+        };
+
+        if phys.unit_specifier > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "unit specifier",
+                value: phys.unit_specifier as u32,
+            });
+        }
+
+        Ok(phys)
+    }
+}
+
+impl pHYs {
+    const INCHES_PER_METRE: f64 = 0.0254;
+
+    // Dots per inch (x, y), only known when the unit is the metre
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit_specifier {
+            1 => Some((
+                self.pixels_per_x as f64 * pHYs::INCHES_PER_METRE,
+                self.pixels_per_y as f64 * pHYs::INCHES_PER_METRE,
+            )),
+            _ => None,
+        }
+    }
+
+    // How much wider than tall each pixel is
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        match self.pixels_per_x {
+            0 => None,
+            _ => Some(self.pixels_per_y as f64 / self.pixels_per_x as f64),
+        }
+    }
+}
+
+impl fmt::Display for pHYs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit_specifier {
+            1 => "metre",
+            _ => "unknown unit",
+        };
+        writeln!(
+            f,
+            " pixels per unit: {} x {} ({})",
+            self.pixels_per_x, self.pixels_per_y, unit
+        )?;
+
+        if let Some((x, y)) = self.dpi() {
+            if f.alternate() {
+                writeln!(
+                    f,
+                    " DPI: {:.2} x {:.2} | Pixels per inch, for printing",
+                    x, y
+                )?;
+            } else {
+                writeln!(f, " DPI: {:.2} x {:.2}", x, y)?;
+            }
+        }
+
+        if f.alternate() {
+            if let Some(aspect_ratio) = self.aspect_ratio() {
+                writeln!(
+                    f,
+                    " pixel aspect ratio: {:.4} | Pixel width divided by height, 1 is square",
+                    aspect_ratio
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.sPLT
+#[derive(Debug, Clone)]
+pub struct sPLT {
+    pub palette_name: String, // First 1-79 bytes
+    // null_seperator: u8,
+    pub sample_depth: u8,
+    pub entries: Vec<sPLTEntry>,
+}
+
+// Samples are stored as u16 so 8 & 16 bit pallets fit in the same struct
+#[derive(Debug, Clone)]
+pub struct sPLTEntry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    pub frequency: u16,
+}
+
+impl Parse for sPLT {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let palette_name = read_text_keyword(&mut reader)?;
+        let sample_depth = reader.read_u8()?;
+
+        let entry_size = match sample_depth {
+            8 => 6,
+            16 => 10,
+            _ => {
+                return Err(ChunkError::InvalidValue {
+                    field: "sample depth",
+                    value: sample_depth as u32,
+                })
+            }
+        };
+
+        if !reader.remaining().is_multiple_of(entry_size) {
+            return Err(ChunkError::InvalidLength {
+                length: chunk.length(),
+            });
+        }
+
+        let mut entries = Vec::new();
+        while !reader.is_empty() {
+            let entry = if sample_depth == 8 {
+                let [red, green, blue, alpha] = reader.read_array()?;
+                sPLTEntry {
+                    red: red as u16,
+                    green: green as u16,
+                    blue: blue as u16,
+                    alpha: alpha as u16,
+                    frequency: reader.read_u16()?,
+                }
+            } else {
+                sPLTEntry {
+                    red: reader.read_u16()?,
+                    green: reader.read_u16()?,
+                    blue: reader.read_u16()?,
+                    alpha: reader.read_u16()?,
+                    frequency: reader.read_u16()?,
+                }
+            };
+            entries.push(entry);
+        }
+
+        Ok(sPLT {
+            palette_name,
+            sample_depth,
+            entries,
+        })
+    }
+}
+
+impl fmt::Display for sPLT {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " pallet name: {} | A suggested pallet, for displays with a limited number of colors",
+                self.palette_name
+            )?;
+            writeln!(
+                f,
+                " sample depth: {} | Bits per sample of the entries",
+                self.sample_depth
+            )?;
+            writeln!(
+                f,
+                " {} entries | Scheme: Index: (Red, Green, Blue, Alpha) frequency",
+                self.entries.len()
+            )?;
+        } else {
+            writeln!(f, " pallet name: {}", self.palette_name)?;
+            writeln!(f, " sample depth: {}", self.sample_depth)?;
+        }
+        for (index, entry) in self.entries.iter().enumerate() {
+            writeln!(
+                f,
+                " {}: ({}, {}, {}, {}) frequency {}",
+                index, entry.red, entry.green, entry.blue, entry.alpha, entry.frequency
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.tIME
+#[derive(Debug, Clone)]
+pub struct tIME {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Parse for tIME {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 7)?;
+        let mut reader = ChunkReader::new(chunk.data());
+        let year = reader.read_u16()?;
+
+        let mut read_field = |field, range: std::ops::RangeInclusive<u8>| {
+            let value = reader.read_u8()?;
+            if !range.contains(&value) {
+                return Err(ChunkError::InvalidValue {
+                    field,
+                    value: value as u32,
+                });
+            }
+            Ok(value)
+        };
+
+        Ok(tIME {
+            year,
+            month: read_field("month", 1..=12)?,
+            day: read_field("day", 1..=31)?,
+            hour: read_field("hour", 0..=23)?,
+            minute: read_field("minute", 0..=59)?,
+            // 60 allows for leap seconds
+            second: read_field("second", 0..=60)?,
+        })
+    }
+}
+
+impl tIME {
+    // Like "2024-05-01T13:45:00Z", tIME is always in UTC
+    pub fn to_iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    // Seconds since 1970-01-01T00:00:00Z, for sorting & comparing
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    pub fn unix_time(&self) -> i64 {
+        let month = self.month as i64;
+        let year = self.year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+}
+
+impl fmt::Display for tIME {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " last modified: {} | When the image was last changed, in UTC",
+                self.to_iso8601()
+            )?;
+            writeln!(
+                f,
+                " unix time: {} | Seconds since 1970-01-01T00:00:00Z",
+                self.unix_time()
+            )
+        } else {
+            writeln!(f, " last modified: {}", self.to_iso8601())
+        }
+    }
+}
+
+// Keywords the spec gives a meaning to. Any other keyword is allowed too.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11keywords
+pub const TEXT_KEYWORDS: [(&str, &str); 10] = [
+    ("Title", "Short (one line) title or caption for image"),
+    ("Author", "Name of image's creator"),
+    ("Description", "Description of image (possibly long)"),
+    ("Copyright", "Copyright notice"),
+    ("Creation Time", "Time of original image creation"),
+    ("Software", "Software used to create the image"),
+    ("Disclaimer", "Legal disclaimer"),
+    ("Warning", "Warning of nature of content"),
+    ("Source", "Device used to create the image"),
+    ("Comment", "Miscellaneous comment"),
+];
+
+// What a reserved keyword means, or None for any other keyword
+pub fn keyword_description(keyword: &str) -> Option<&'static str> {
+    TEXT_KEYWORDS
+        .iter()
+        .find(|(reserved, _)| *reserved == keyword)
+        .map(|(_, description)| *description)
+}
+
+// Reads a text chunk's keyword, checking it only uses the characters the spec allows
+fn read_text_keyword(reader: &mut ChunkReader) -> Result<String, ChunkError> {
+    let keyword = reader.read_keyword()?;
+    let invalid = |reason| {
+        Err(ChunkError::InvalidKeyword {
+            keyword: keyword.clone(),
+            reason,
+        })
+    };
+
+    if keyword
+        .chars()
+        .any(|c| !matches!(c as u32, 32..=126 | 161..=255))
+    {
+        return invalid("has a character that isn't printable Latin-1");
+    }
+    if keyword.starts_with(' ') || keyword.ends_with(' ') {
+        return invalid("starts or ends with a space");
+    }
+    if keyword.contains("  ") {
+        return invalid("has consecutive spaces");
+    }
+
+    Ok(keyword)
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| *byte as char).collect()
+}
+
+// Text can span several lines, so later lines are indented to line up under the first
+fn write_text(f: &mut fmt::Formatter<'_>, label: &str, text: &str) -> fmt::Result {
+    write!(f, " {}: ", label)?;
+    for (index, line) in text.lines().enumerate() {
+        if index > 0 {
+            write!(f, "\n {:width$}  ", "", width = label.len())?;
+        }
+        write!(f, "{}", line)?;
+    }
+    writeln!(f)
+}
+
+fn write_keyword(f: &mut fmt::Formatter<'_>, keyword: &str) -> fmt::Result {
+    if f.alternate() {
+        match keyword_description(keyword) {
+            Some(description) => writeln!(f, " keyword: {} | {}", keyword, description),
+            None => writeln!(
+                f,
+                " keyword: {} | Not one of the reserved keywords",
+                keyword
+            ),
+        }
+    } else {
+        writeln!(f, " keyword: {}", keyword)
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.tEXt
+#[derive(Debug, Clone)]
+pub struct tEXt {
+    pub keyword: String,
+    // Latin-1, lines are separated by a single line feed
+    pub text: String,
+}
+
+impl Parse for tEXt {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+        let keyword = read_text_keyword(&mut reader)?;
+
+        let text = reader.read_rest();
+        if text.contains(&0) {
+            return Err(ChunkError::NullInText);
+        }
+
+        Ok(tEXt {
+            keyword,
+            text: latin1(text),
+        })
+    }
+}
+
+impl fmt::Display for tEXt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_keyword(f, &self.keyword)?;
+        write_text(f, "text", &self.text)
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.zTXt
+#[derive(Debug, Clone)]
+pub struct zTXt {
+    pub keyword: String,
+    pub compression_method: u8,
+    pub compressed_text: Vec<u8>,
+}
+
+impl Parse for zTXt {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+        let keyword = read_text_keyword(&mut reader)?;
+        let compression_method = read_compression_method(&mut reader)?;
+
+        Ok(zTXt {
+            keyword,
+            compression_method,
+            compressed_text: reader.read_rest().to_vec(),
+        })
+    }
+}
+
+impl zTXt {
+    // The Latin-1 text, decompressed
+    pub fn text(&self) -> Result<String, ChunkError> {
+        Ok(latin1(&decompress(
+            self.compression_method,
+            &self.compressed_text,
+        )?))
+    }
+}
+
+impl fmt::Display for zTXt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_keyword(f, &self.keyword)?;
+        writeln!(f, " compression method: {}", self.compression_method)?;
+        writeln!(f, " compressed text: {} bytes", self.compressed_text.len())?;
+        match self.text() {
+            Ok(text) => write_text(f, "text", &text),
+            Err(error) => writeln!(f, " text: {}", error),
+        }
+    }
+}
+
+// http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.iTXt
+#[derive(Debug, Clone)]
+pub struct iTXt {
+    pub keyword: String,
+    // 0 for uncompressed text, 1 for compressed
+    pub compression_flag: u8,
+    pub compression_method: u8,
+    // Like "en" or "x-klingon", empty if unknown
+    pub language_tag: String,
+    // The keyword, translated into the language
+    pub translated_keyword: String,
+    // UTF-8 text, still compressed if `compression_flag` is 1
+    pub raw_text: Vec<u8>,
+}
+
+impl Parse for iTXt {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let keyword = read_text_keyword(&mut reader)?;
+        let compression_flag = reader.read_u8()?;
+        if compression_flag > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "compression flag",
+                value: compression_flag as u32,
+            });
+        }
+        // Only checked for compressed text, decoders are told to ignore it otherwise
+        let compression_method = match compression_flag {
+            1 => read_compression_method(&mut reader)?,
+            _ => reader.read_u8()?,
+        };
+
+        // The language tag is ASCII letters, digits & hyphens
+        // https://www.w3.org/TR/2003/REC-PNG-20031110/#11iTXt
+        let language_tag = latin1(reader.read_null_terminated()?);
+        if !language_tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(ChunkError::InvalidLanguageTag { tag: language_tag });
+        }
+
+        let translated_keyword = match String::from_utf8(reader.read_null_terminated()?.to_vec()) {
+            Ok(x) => x,
+            Err(..) => {
+                return Err(ChunkError::InvalidUtf8 {
+                    field: "translated keyword",
+                })
+            }
+        };
+
+        Ok(iTXt {
+            keyword,
+            compression_flag,
+            compression_method,
+            language_tag,
+            translated_keyword,
+            raw_text: reader.read_rest().to_vec(),
+        })
+    }
+}
+
+impl iTXt {
+    pub fn is_compressed(&self) -> bool {
+        self.compression_flag == 1
+    }
+
+    // The text, decompressed if needed
+    pub fn text(&self) -> Result<String, ChunkError> {
+        let text = match self.is_compressed() {
+            true => decompress(self.compression_method, &self.raw_text)?,
+            false => self.raw_text.clone(),
+        };
+
+        String::from_utf8(text).map_err(|_| ChunkError::InvalidUtf8 { field: "text" })
+    }
+}
+
+impl fmt::Display for iTXt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_keyword(f, &self.keyword)?;
+        if self.is_compressed() {
+            writeln!(
+                f,
+                " compressed: yes, method {}, {} bytes",
+                self.compression_method,
+                self.raw_text.len()
+            )?;
+        } else {
+            writeln!(f, " compressed: no")?;
+        }
+        if f.alternate() {
+            writeln!(
+                f,
+                " language tag: {} | The language the text is in, empty if unknown",
+                self.language_tag
+            )?;
+        } else {
+            writeln!(f, " language tag: {}", self.language_tag)?;
+        }
+        writeln!(f, " translated keyword: {}", self.translated_keyword)?;
+        match self.text() {
+            Ok(text) => write_text(f, "text", &text),
+            Err(error) => writeln!(f, " text: {}", error),
+        }
+    }
+}
+
+// https://www.w3.org/TR/png-3/#acTL-chunk
+#[derive(Debug, Clone)]
+pub struct acTL {
+    pub num_frames: u32,
+    // 0 means the animation loops forever
+    pub num_plays: u32,
+}
+
+impl Parse for acTL {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 8)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let actl = acTL {
+            num_frames: reader.read_u32()?,
+            num_plays: reader.read_u32()?,
+        };
+
+        if actl.num_frames == 0 {
+            return Err(ChunkError::InvalidValue {
+                field: "number of frames",
+                value: 0,
+            });
+        }
+
+        Ok(actl)
+    }
+}
+
+impl fmt::Display for acTL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plays = match self.num_plays {
+            0 => "forever".to_string(),
+            plays => plays.to_string(),
+        };
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " frames: {} | Includes the IDAT image if it's part of the animation",
+                self.num_frames
+            )?;
+            writeln!(f, " plays: {} | Times the animation loops", plays)
+        } else {
+            writeln!(f, " frames: {}", self.num_frames)?;
+            writeln!(f, " plays: {}", plays)
+        }
+    }
+}
+
+// https://www.w3.org/TR/png-3/#fcTL-chunk
+#[derive(Debug, Clone)]
+pub struct fcTL {
+    // Shared with fdAT, counting up from 0 so the chunks can be put back in order
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    // The delay is the fraction delay_num / delay_den seconds
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: u8,
+    pub blend_op: u8,
+}
+
+impl Parse for fcTL {
+    fn parse(chunk: &Chunk, context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 26)?;
+        let header = context.header()?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let fctl = fcTL {
+            sequence_number: reader.read_u32()?,
+            width: reader.read_u32()?,
+            height: reader.read_u32()?,
+            x_offset: reader.read_u32()?,
+            y_offset: reader.read_u32()?,
+            delay_num: reader.read_u16()?,
+            delay_den: reader.read_u16()?,
+            dispose_op: reader.read_u8()?,
+            blend_op: reader.read_u8()?,
+        };
+
+        for (field, value) in [("width", fctl.width), ("height", fctl.height)] {
+            if value == 0 {
+                return Err(ChunkError::InvalidValue { field, value });
+            }
+        }
+
+        let fits = |offset: u32, size: u32, image_size: u32| {
+            offset
+                .checked_add(size)
+                .is_some_and(|end| end <= image_size)
+        };
+        if !fits(fctl.x_offset, fctl.width, header.width)
+            || !fits(fctl.y_offset, fctl.height, header.height)
+        {
+            return Err(ChunkError::FrameOutsideImage {
+                x_offset: fctl.x_offset,
+                y_offset: fctl.y_offset,
+                width: fctl.width,
+                height: fctl.height,
+            });
+        }
+
+        if fctl.dispose_op > 2 {
+            return Err(ChunkError::InvalidValue {
+                field: "dispose op",
+                value: fctl.dispose_op as u32,
+            });
+        }
+        if fctl.blend_op > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "blend op",
+                value: fctl.blend_op as u32,
+            });
+        }
+
+        Ok(fctl)
+    }
+}
+
+impl fcTL {
+    pub const DISPOSE_OP_NONE: u8 = 0;
+    pub const DISPOSE_OP_BACKGROUND: u8 = 1;
+    pub const DISPOSE_OP_PREVIOUS: u8 = 2;
+
+    pub const BLEND_OP_SOURCE: u8 = 0;
+    pub const BLEND_OP_OVER: u8 = 1;
+
+    // How long the frame is shown for, in seconds. A denominator of 0 means 100.
+    pub fn delay(&self) -> f64 {
+        let denominator = match self.delay_den {
+            0 => 100,
+            denominator => denominator,
+        };
+        self.delay_num as f64 / denominator as f64
+    }
+
+    pub fn dispose_op_name(dispose_op: u8) -> &'static str {
+        match dispose_op {
+            fcTL::DISPOSE_OP_NONE => "none",
+            fcTL::DISPOSE_OP_BACKGROUND => "background",
+            _ => "previous",
+        }
+    }
+
+    pub fn blend_op_name(blend_op: u8) -> &'static str {
+        match blend_op {
+            fcTL::BLEND_OP_SOURCE => "source",
+            _ => "over",
+        }
+    }
+}
+
+impl fmt::Display for fcTL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, " sequence number: {}", self.sequence_number)?;
+        writeln!(f, " size: {} x {}", self.width, self.height)?;
+        writeln!(f, " offset: ({}, {})", self.x_offset, self.y_offset)?;
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " delay: {}/{} ({:.3}s) | How long the frame is shown, a denominator of 0 means 100",
+                self.delay_num,
+                self.delay_den,
+                self.delay()
+            )?;
+            writeln!(
+                f,
+                " dispose op: {} ({}) | 0 = Leave it; 1 = Clear to transparent black; 2 = Go back to the previous canvas",
+                fcTL::dispose_op_name(self.dispose_op),
+                self.dispose_op
+            )?;
+            writeln!(
+                f,
+                " blend op: {} ({}) | 0 = Replace the canvas; 1 = Alpha blend over the canvas",
+                fcTL::blend_op_name(self.blend_op),
+                self.blend_op
+            )
+        } else {
+            writeln!(
+                f,
+                " delay: {}/{} ({:.3}s)",
+                self.delay_num,
+                self.delay_den,
+                self.delay()
+            )?;
+            writeln!(
+                f,
+                " dispose op: {} ({})",
+                fcTL::dispose_op_name(self.dispose_op),
+                self.dispose_op
+            )?;
+            writeln!(
+                f,
+                " blend op: {} ({})",
+                fcTL::blend_op_name(self.blend_op),
+                self.blend_op
+            )
+        }
+    }
+}
+
+// https://www.w3.org/TR/png-3/#fdAT-chunk
+// Like IDAT, the frame data is only meaningful once a frame's chunks are put
+// together, so it stays on the chunk.
+#[derive(Debug, Clone)]
+pub struct fdAT {
+    pub sequence_number: u32,
+    // Length of the frame data after the sequence number
+    pub data_length: usize,
+}
+
+impl Parse for fdAT {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        Ok(fdAT {
+            sequence_number: reader.read_u32()?,
+            data_length: reader.remaining(),
+        })
+    }
+}
+
+impl fmt::Display for fdAT {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, " sequence number: {}", self.sequence_number)?;
+        writeln!(f, " frame data: {} bytes", self.data_length)
+    }
+}
+
+// Coding-independent code points, from ITU-T H.273. They say which color space the
+// image is in, taking priority over iCCP, sRGB, cHRM & gAMA.
+// https://www.w3.org/TR/png-3/#cICP-chunk
+#[derive(Debug, Clone)]
+pub struct cICP {
+    pub colour_primaries: u8,
+    pub transfer_function: u8,
+    // Always 0 (RGB) in PNG
+    pub matrix_coefficients: u8,
+    // 1 if the samples use their full range, 0 for video's narrow range
+    pub video_full_range_flag: u8,
+}
+
+impl Parse for cICP {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 4)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let cicp = cICP {
+            colour_primaries: reader.read_u8()?,
+            transfer_function: reader.read_u8()?,
+            matrix_coefficients: reader.read_u8()?,
+            video_full_range_flag: reader.read_u8()?,
+        };
+
+        if cicp.matrix_coefficients != 0 {
+            return Err(ChunkError::InvalidValue {
+                field: "matrix coefficients",
+                value: cicp.matrix_coefficients as u32,
+            });
+        }
+        if cicp.video_full_range_flag > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "video full range flag",
+                value: cicp.video_full_range_flag as u32,
+            });
+        }
+
+        Ok(cicp)
+    }
+}
+
+impl cICP {
+    // H.273 table 2
+    pub fn primaries_name(colour_primaries: u8) -> &'static str {
+        match colour_primaries {
+            1 => "BT.709 / sRGB",
+            2 => "Unspecified",
+            4 => "BT.470 System M",
+            5 => "BT.601 625 line",
+            6 => "BT.601 525 line",
+            7 => "SMPTE 240M",
+            8 => "Generic film",
+            9 => "BT.2020 / BT.2100",
+            10 => "SMPTE ST 428-1 (CIE XYZ)",
+            11 => "DCI-P3",
+            12 => "Display P3",
+            22 => "EBU Tech 3213-E",
+            _ => "Reserved",
+        }
+    }
+
+    // H.273 table 3
+    pub fn transfer_name(transfer_function: u8) -> &'static str {
+        match transfer_function {
+            1 => "BT.709",
+            2 => "Unspecified",
+            4 => "Gamma 2.2",
+            5 => "Gamma 2.8",
+            6 => "BT.601",
+            7 => "SMPTE 240M",
+            8 => "Linear",
+            9 => "Logarithmic (100:1)",
+            10 => "Logarithmic (316:1)",
+            11 => "IEC 61966-2-4 (xvYCC)",
+            12 => "BT.1361",
+            13 => "sRGB",
+            14 => "BT.2020 10 bit",
+            15 => "BT.2020 12 bit",
+            16 => "PQ (SMPTE ST 2084)",
+            17 => "SMPTE ST 428-1",
+            18 => "HLG (ARIB STD-B67)",
+            _ => "Reserved",
+        }
+    }
+
+    // H.273 table 4
+    pub fn matrix_name(matrix_coefficients: u8) -> &'static str {
+        match matrix_coefficients {
+            0 => "Identity (RGB)",
+            1 => "BT.709",
+            2 => "Unspecified",
+            4 => "FCC",
+            5 => "BT.601 625 line",
+            6 => "BT.601 525 line",
+            7 => "SMPTE 240M",
+            8 => "YCgCo",
+            9 => "BT.2020 non-constant luminance",
+            10 => "BT.2020 constant luminance",
+            11 => "SMPTE ST 2085",
+            12 => "Chromaticity derived non-constant luminance",
+            13 => "Chromaticity derived constant luminance",
+            14 => "ICtCp",
+            _ => "Reserved",
+        }
+    }
+
+    // The common color spaces the primaries & transfer function add up to
+    pub fn color_space(&self) -> Option<&'static str> {
+        match (self.colour_primaries, self.transfer_function) {
+            (1, 13) => Some("sRGB"),
+            (1, 8) => Some("Linear sRGB"),
+            (1, 1) => Some("BT.709"),
+            (12, 13) => Some("Display P3"),
+            (11, 17) => Some("DCI-P3"),
+            (9, 16) => Some("BT.2100 PQ (HDR10)"),
+            (9, 18) => Some("BT.2100 HLG"),
+            (9, 14 | 15) => Some("BT.2020"),
+            _ => None,
+        }
+    }
+
+    // Whether the transfer function is one of the HDR ones (PQ or HLG)
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer_function, 16 | 18)
+    }
+}
+
+impl fmt::Display for cICP {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = match self.video_full_range_flag {
+            1 => "full",
+            _ => "narrow",
+        };
+        let fields = [
+            (
+                "colour primaries",
+                cICP::primaries_name(self.colour_primaries),
+                self.colour_primaries,
+                "The red, green, blue & white chromaticities",
+            ),
+            (
+                "transfer function",
+                cICP::transfer_name(self.transfer_function),
+                self.transfer_function,
+                "How sample values map to light",
+            ),
+            (
+                "matrix coefficients",
+                cICP::matrix_name(self.matrix_coefficients),
+                self.matrix_coefficients,
+                "Only 0 is allowed, PNG samples are always RGB",
+            ),
+            (
+                "video full range",
+                range,
+                self.video_full_range_flag,
+                "Narrow range leaves headroom above & below, like video does",
+            ),
+        ];
+
+        for (name, value_name, value, description) in fields {
+            if f.alternate() {
+                writeln!(f, " {}: {} ({}) | {}", name, value_name, value, description)?;
+            } else {
+                writeln!(f, " {}: {} ({})", name, value_name, value)?;
+            }
+        }
+
+        let color_space = self.color_space().unwrap_or("not a common color space");
+        if f.alternate() {
+            writeln!(
+                f,
+                " color space: {} | Takes priority over iCCP, sRGB, cHRM & gAMA",
+                color_space
+            )
+        } else {
+            writeln!(f, " color space: {}", color_space)
+        }
+    }
+}
+
+// Mastering display color volume, the display the image was graded on
+// https://www.w3.org/TR/png-3/#mDCV-chunk
+#[derive(Debug, Clone)]
+pub struct mDCV {
+    // Red, green, blue (x, y), each in units of 0.00002
+    pub primaries: [(u16, u16); 3],
+    pub white_point: (u16, u16),
+    // In units of 0.0001 cd/m²
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+impl Parse for mDCV {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 24)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let mut read_point = || Ok::<_, ChunkError>((reader.read_u16()?, reader.read_u16()?));
+        let primaries = [read_point()?, read_point()?, read_point()?];
+        let white_point = read_point()?;
+
+        Ok(mDCV {
+            primaries,
+            white_point,
+            max_luminance: reader.read_u32()?,
+            min_luminance: reader.read_u32()?,
+        })
+    }
+}
+
+impl mDCV {
+    // The values as chromaticities, so times 0.00002
+    pub fn gamut(&self) -> Gamut {
+        let fraction = |(x, y): (u16, u16)| (x as f64 * 0.00002, y as f64 * 0.00002);
+
+        Gamut {
+            name: "mDCV",
+            white: fraction(self.white_point),
+            red: fraction(self.primaries[0]),
+            green: fraction(self.primaries[1]),
+            blue: fraction(self.primaries[2]),
+        }
+    }
+}
+
+// Light levels are stored in units of 0.0001 cd/m²
+fn candelas(value: u32) -> f64 {
+    value as f64 / 10000.0
+}
+
+impl fmt::Display for mDCV {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gamut = self.gamut();
+        let points = [
+            ("red", gamut.red),
+            ("green", gamut.green),
+            ("blue", gamut.blue),
+            ("white point", gamut.white),
+        ];
+        for (name, (x, y)) in points {
+            writeln!(f, " {}: ({:.5}, {:.5})", name, x, y)?;
+        }
+
+        match gamut.known() {
+            Some(known) => writeln!(f, " gamut: {}", known.name)?,
+            None => writeln!(f, " gamut: not a known gamut")?,
+        }
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " luminance: {} to {} cd/m² | The darkest & brightest the mastering display could show",
+                candelas(self.min_luminance),
+                candelas(self.max_luminance)
+            )
+        } else {
+            writeln!(
+                f,
+                " luminance: {} to {} cd/m²",
+                candelas(self.min_luminance),
+                candelas(self.max_luminance)
+            )
+        }
+    }
+}
+
+// Content light level information, how bright the image gets
+// https://www.w3.org/TR/png-3/#cLLI-chunk
+#[derive(Debug, Clone)]
+pub struct cLLI {
+    // Both in units of 0.0001 cd/m², 0 when unknown
+    pub max_content_light_level: u32,
+    pub max_frame_average_light_level: u32,
+}
+
+impl Parse for cLLI {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 8)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        Ok(cLLI {
+            max_content_light_level: reader.read_u32()?,
+            max_frame_average_light_level: reader.read_u32()?,
+        })
+    }
+}
+
+impl fmt::Display for cLLI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let levels = [
+            (
+                "max content light level",
+                self.max_content_light_level,
+                "The brightest pixel (MaxCLL)",
+            ),
+            (
+                "max frame average light level",
+                self.max_frame_average_light_level,
+                "The brightest frame on average (MaxFALL)",
+            ),
+        ];
+
+        for (name, value, description) in levels {
+            let value = match value {
+                0 => "unknown".to_string(),
+                value => format!("{} cd/m²", candelas(value)),
+            };
+            if f.alternate() {
+                writeln!(f, " {}: {} | {}", name, value, description)?;
+            } else {
+                writeln!(f, " {}: {}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Exif metadata, stored the same way as in a JPEG's APP1 segment minus the "Exif\0\0"
+// https://www.w3.org/TR/png-3/#eXIf-chunk
+#[derive(Debug, Clone)]
+pub struct eXIf {
+    // A TIFF structure, starting with its byte order
+    pub data: Vec<u8>,
+}
+
+impl Parse for eXIf {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let data = chunk.data();
+
+        // "II" for little-endian or "MM" for big-endian, then 42
+        match data.get(..4) {
+            Some(b"II\x2a\x00" | b"MM\x00\x2a") => Ok(eXIf {
+                data: data.to_vec(),
+            }),
+            _ => Err(ChunkError::MissingTiffHeader),
+        }
+    }
+}
+
+impl eXIf {
+    pub fn is_little_endian(&self) -> bool {
+        self.data.starts_with(b"II")
+    }
+
+    // The tags inside the TIFF structure
+    pub fn exif(&self) -> Result<Exif, ExifError> {
+        Exif::parse(&self.data)
+    }
+}
+
+impl fmt::Display for eXIf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let byte_order = match self.is_little_endian() {
+            true => "little-endian (II)",
+            false => "big-endian (MM)",
+        };
+
+        writeln!(f, " length: {}", self.data.len())?;
+        if f.alternate() {
+            writeln!(
+                f,
+                " byte order: {} | The byte order of every value in the Exif data",
+                byte_order
+            )?;
+        } else {
+            writeln!(f, " byte order: {}", byte_order)?;
+        }
+
+        // What's inside, nested under the chunk
+        match self.exif() {
+            Ok(exif) => {
+                writeln!(f, " Exif:")?;
+                if f.alternate() {
+                    write!(f, "{:#}", exif)
+                } else {
+                    write!(f, "{}", exif)
+                }
+            }
+            Err(error) => writeln!(f, " Exif: invalid, {}", error),
+        }
+    }
+}
+
+// Extension chunks, registered with the PNG group but not part of the spec itself
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html
+
+// A floating point number written out in ASCII, like "1.5e-3"
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.Float
+fn read_float(field: &'static str, bytes: &[u8]) -> Result<f64, ChunkError> {
+    let text = latin1(bytes);
+    let invalid = |reason| ChunkError::InvalidNumber {
+        field,
+        text: text.clone(),
+        reason,
+    };
+
+    // Checked first, as Rust also reads things like "inf" & "NaN"
+    if !text
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+    {
+        return Err(invalid("isn't a floating point number"));
+    }
+
+    text.parse()
+        .map_err(|_| invalid("isn't a floating point number"))
+}
+
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.oFFs
+#[derive(Debug, Clone)]
+pub struct oFFs {
+    // Where the image's top left corner is on the page
+    pub x_position: i32,
+    pub y_position: i32,
+    // 0 = pixels, 1 = micrometres
+    pub unit_specifier: u8,
+}
+
+impl Parse for oFFs {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 9)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let offs = oFFs {
+            x_position: reader.read_u32()? as i32,
+            y_position: reader.read_u32()? as i32,
+            unit_specifier: reader.read_u8()?,
+        };
+
+        if offs.unit_specifier > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "unit specifier",
+                value: offs.unit_specifier as u32,
+            });
+        }
+
+        Ok(offs)
+    }
+}
+
+impl fmt::Display for oFFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit_specifier {
+            0 => "pixels",
+            _ => "micrometres",
+        };
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " position: ({}, {}) {} | Where the top left corner goes on the page",
+                self.x_position, self.y_position, unit
+            )
+        } else {
+            writeln!(
+                f,
+                " position: ({}, {}) {}",
+                self.x_position, self.y_position, unit
+            )
+        }
+    }
+}
+
+// Pixel calibration, turning samples into physical values (like temperatures)
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.pCAL
+#[derive(Debug, Clone)]
+pub struct pCAL {
+    pub calibration_name: String,
+    // The original samples' range, which the stored samples are scaled to
+    pub x0: i32,
+    pub x1: i32,
+    pub equation_type: u8,
+    // The unit of the physical values, can be empty
+    pub unit_name: String,
+    // p0, p1, ...
+    pub parameters: Vec<f64>,
+}
+
+impl Parse for pCAL {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let calibration_name = read_text_keyword(&mut reader)?;
+        let x0 = reader.read_u32()? as i32;
+        let x1 = reader.read_u32()? as i32;
+        let equation_type = reader.read_u8()?;
+        let parameter_count = reader.read_u8()? as usize;
+        let unit_name = latin1(reader.read_null_terminated()?);
+
+        if x0 == x1 {
+            return Err(ChunkError::InvalidNumber {
+                field: "X1",
+                text: x1.to_string(),
+                reason: "is the same as X0",
+            });
+        }
+
+        let expected = match pCAL::parameter_count(equation_type) {
+            Some(x) => x,
+            None => {
+                return Err(ChunkError::InvalidValue {
+                    field: "equation type",
+                    value: equation_type as u32,
+                })
+            }
+        };
+        if parameter_count != expected {
+            return Err(ChunkError::InvalidValue {
+                field: "parameter count",
+                value: parameter_count as u32,
+            });
+        }
+
+        // Separated by nulls, with no null after the last one
+        let parameters = reader
+            .read_rest()
+            .split(|byte| *byte == 0)
+            .map(|parameter| read_float("parameter", parameter))
+            .collect::<Result<Vec<_>, _>>()?;
+        if parameters.len() != parameter_count {
+            return Err(ChunkError::InvalidValue {
+                field: "number of parameters",
+                value: parameters.len() as u32,
+            });
+        }
+
+        Ok(pCAL {
+            calibration_name,
+            x0,
+            x1,
+            equation_type,
+            unit_name,
+            parameters,
+        })
+    }
+}
+
+impl pCAL {
+    // How many parameters each equation type takes
+    pub fn parameter_count(equation_type: u8) -> Option<usize> {
+        match equation_type {
+            0 => Some(2),
+            1 | 2 => Some(3),
+            3 => Some(4),
+            _ => None,
+        }
+    }
+
+    pub fn equation_name(equation_type: u8) -> &'static str {
+        match equation_type {
+            0 => "Linear",
+            1 => "Base-e exponential",
+            2 => "Arbitrary base exponential",
+            3 => "Hyperbolic",
+            _ => "Unknown",
+        }
+    }
+
+    // The equation with the parameters filled in. X is the original sample,
+    // between X0 & X1.
+    pub fn equation(&self) -> String {
+        let p = &self.parameters;
+        let range = self.x1 as i64 - self.x0 as i64;
+
+        match self.equation_type {
+            0 => format!("{} + {} * X / {}", p[0], p[1], range),
+            1 => format!("{} + {} * e^({} * X / {})", p[0], p[1], p[2], range),
+            2 => format!("{} + {} * {}^(X / {})", p[0], p[1], p[2], range),
+            _ => format!(
+                "{} + {} * sinh({} * (X - {}) / {})",
+                p[0], p[1], p[2], p[3], range
+            ),
+        }
+    }
+
+    // The physical value for an original sample (a stored sample already scaled to X0..X1)
+    pub fn physical_value(&self, original_sample: f64) -> f64 {
+        let p = &self.parameters;
+        let range = self.x1 as f64 - self.x0 as f64;
+
+        match self.equation_type {
+            0 => p[0] + p[1] * original_sample / range,
+            1 => p[0] + p[1] * (p[2] * original_sample / range).exp(),
+            2 => p[0] + p[1] * p[2].powf(original_sample / range),
+            _ => p[0] + p[1] * (p[2] * (original_sample - p[3]) / range).sinh(),
+        }
+    }
+
+    // Stored samples go from 0 to the largest value at the bit depth, and are scaled
+    // to X0..X1 before the equation is applied
+    pub fn original_sample(&self, stored_sample: u32, max_stored_sample: u32) -> f64 {
+        let range = self.x1 as i64 - self.x0 as i64;
+        let max = max_stored_sample.max(1) as i64;
+        ((stored_sample as i64 * range + max / 2) / max + self.x0 as i64) as f64
+    }
+}
+
+impl fmt::Display for pCAL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit_name.as_str() {
+            "" => "none",
+            unit => unit,
+        };
+
+        writeln!(f, " calibration name: {}", self.calibration_name)?;
+        if f.alternate() {
+            writeln!(
+                f,
+                " original range: {} to {} | Stored samples are scaled to this range, giving X",
+                self.x0, self.x1
+            )?;
+        } else {
+            writeln!(f, " original range: {} to {}", self.x0, self.x1)?;
+        }
+        writeln!(
+            f,
+            " equation type: {} ({})",
+            pCAL::equation_name(self.equation_type),
+            self.equation_type
+        )?;
+        writeln!(f, " equation: value = {}", self.equation())?;
+        writeln!(f, " unit: {}", unit)?;
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " physical range: {} to {}{} | The values at X0 & X1",
+                self.physical_value(self.x0 as f64),
+                self.physical_value(self.x1 as f64),
+                match self.unit_name.as_str() {
+                    "" => String::new(),
+                    unit => format!(" {}", unit),
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The physical size of each pixel
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.sCAL
+#[derive(Debug, Clone)]
+pub struct sCAL {
+    // 1 = metres, 2 = radians
+    pub unit_specifier: u8,
+    pub pixel_width: f64,
+    pub pixel_height: f64,
+}
+
+impl Parse for sCAL {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let unit_specifier = reader.read_u8()?;
+        if !matches!(unit_specifier, 1 | 2) {
+            return Err(ChunkError::InvalidValue {
+                field: "unit specifier",
+                value: unit_specifier as u32,
+            });
+        }
+
+        let read_size = |field, bytes: &[u8]| {
+            let size = read_float(field, bytes)?;
+            if size <= 0.0 {
+                return Err(ChunkError::InvalidNumber {
+                    field,
+                    text: latin1(bytes),
+                    reason: "isn't more than 0",
+                });
+            }
+            Ok(size)
+        };
+        let pixel_width = read_size("pixel width", reader.read_null_terminated()?)?;
+        let pixel_height = read_size("pixel height", reader.read_rest())?;
+
+        Ok(sCAL {
+            unit_specifier,
+            pixel_width,
+            pixel_height,
+        })
+    }
+}
+
+impl fmt::Display for sCAL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit_specifier {
+            1 => "metres",
+            _ => "radians",
+        };
+
+        if f.alternate() {
+            writeln!(
+                f,
+                " pixel size: {} x {} {} | How much of the subject each pixel covers",
+                self.pixel_width, self.pixel_height, unit
+            )
+        } else {
+            writeln!(
+                f,
+                " pixel size: {} x {} {}",
+                self.pixel_width, self.pixel_height, unit
+            )
+        }
+    }
+}
+
+// The image is a stereo pair, left & right side by side
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.sTER
+#[derive(Debug, Clone)]
+pub struct sTER {
+    // 0 = cross-fuse (right eye image on the left), 1 = diverging-fuse
+    pub mode: u8,
+}
+
+impl Parse for sTER {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 1)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        let mode = reader.read_u8()?;
+        if mode > 1 {
+            return Err(ChunkError::InvalidValue {
+                field: "mode",
+                value: mode as u32,
+            });
+        }
+
+        Ok(sTER { mode })
+    }
+}
+
+impl fmt::Display for sTER {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, description) = match self.mode {
+            0 => ("cross-fuse", "The right eye's image is on the left"),
+            _ => ("diverging-fuse", "The left eye's image is on the left"),
+        };
+
+        if f.alternate() {
+            writeln!(f, " mode: {} ({}) | {}", name, self.mode, description)
+        } else {
+            writeln!(f, " mode: {} ({})", name, self.mode)
+        }
+    }
+}
+
+// A GIF Graphic Control Extension, kept when converting a GIF
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.gIFg
+#[derive(Debug, Clone)]
+pub struct gIFg {
+    pub disposal_method: u8,
+    pub user_input_flag: u8,
+    // In hundredths of a second
+    pub delay_time: u16,
+}
+
+impl Parse for gIFg {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        expect_length(chunk, 4)?;
+        let mut reader = ChunkReader::new(chunk.data());
+
+        Ok(gIFg {
+            disposal_method: reader.read_u8()?,
+            user_input_flag: reader.read_u8()?,
+            delay_time: reader.read_u16()?,
+        })
+    }
+}
+
+impl fmt::Display for gIFg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            writeln!(
+                f,
+                " disposal method: {} | 0 = None; 1 = Leave it; 2 = Restore the background; 3 = Restore the previous image",
+                self.disposal_method
+            )?;
+            writeln!(
+                f,
+                " user input: {} | Whether to wait for the viewer before carrying on",
+                self.user_input_flag != 0
+            )?;
+        } else {
+            writeln!(f, " disposal method: {}", self.disposal_method)?;
+            writeln!(f, " user input: {}", self.user_input_flag != 0)?;
+        }
+        writeln!(f, " delay: {:.2}s", self.delay_time as f64 / 100.0)
+    }
+}
+
+// A GIF Application Extension, kept when converting a GIF
+// https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html#C.gIFx
+#[derive(Debug, Clone)]
+pub struct gIFx {
+    // Like "NETSCAPE"
+    pub application_identifier: [u8; 8],
+    pub authentication_code: [u8; 3],
+    pub application_data: Vec<u8>,
+}
+
+impl Parse for gIFx {
+    fn parse(chunk: &Chunk, _context: &ParseContext) -> Result<Self, ChunkError> {
+        let mut reader = ChunkReader::new(chunk.data());
+
+        Ok(gIFx {
+            application_identifier: reader.read_array()?,
+            authentication_code: reader.read_array()?,
+            application_data: reader.read_rest().to_vec(),
+        })
+    }
+}
+
+impl fmt::Display for gIFx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            " application: {}{}",
+            latin1(&self.application_identifier),
+            latin1(&self.authentication_code)
+        )?;
+        writeln!(
+            f,
+            " application data: {} bytes",
+            self.application_data.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PngError;
+    use crate::parser::Png;
+    use crate::testing;
+    use crate::writer;
+
+    // Decodes the chunks after the IHDR of an 8 bit image, returning the last one's
+    // decoded form. Invalid chunks come back as their error, critical or not.
+    fn parse_in(color_type: u8, chunks: &[(&[u8; 4], &[u8])]) -> Result<ParsedChunk, ChunkError> {
+        let ihdr = testing::ihdr(1, 1, 8, color_type);
+        let mut all = vec![(b"IHDR", &ihdr[..])];
+        all.extend_from_slice(chunks);
+        all.push((b"IEND", &[]));
+
+        match Png::from_bytes(&testing::png(&all)) {
+            Ok(png) => match png.chunks()[chunks.len()].parsed() {
+                ParsedChunk::Invalid(error) => Err(error.clone()),
+                parsed => Ok(parsed.clone()),
+            },
+            Err(PngError::InvalidChunk { error, .. }) => Err(error),
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    fn parse(chunk_type: &[u8; 4], data: &[u8]) -> Result<ParsedChunk, ChunkError> {
+        parse_in(2, &[(chunk_type, data)])
+    }
+
+    fn header(bit_depth: u8, color_type: u8) -> IHDR {
+        IHDR {
+            width: 1,
+            height: 1,
+            bit_depth,
+            color_type,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    #[test]
+    fn ihdr_bit_depths() {
+        let allowed: [(u8, &[u8]); 5] = [
+            (0, &[1, 2, 4, 8, 16]),
+            (2, &[8, 16]),
+            (3, &[1, 2, 4, 8]),
+            (4, &[8, 16]),
+            (6, &[8, 16]),
+        ];
+        for (color_type, bit_depths) in allowed {
+            for bit_depth in [0, 1, 2, 3, 4, 5, 8, 12, 16, 32] {
+                let result = header(bit_depth, color_type).validate();
+                if bit_depths.contains(&bit_depth) {
+                    assert_eq!(result, Ok(()));
+                } else {
+                    assert_eq!(
+                        result,
+                        Err(ChunkError::InvalidBitDepth {
+                            color_type,
+                            bit_depth
+                        })
+                    );
+                }
+            }
+        }
+
+        for color_type in [1, 5, 7, 255] {
+            assert_eq!(
+                header(8, color_type).validate(),
+                Err(ChunkError::InvalidColorType { color_type })
+            );
+        }
+    }
+
+    #[test]
+    fn ihdr_dimensions() {
+        let cases = [
+            (0, 1, "width", 0),
+            (1, 0, "height", 0),
+            (1 << 31, 1, "width", 1 << 31),
+            (1, u32::MAX, "height", u32::MAX),
+        ];
+        for (width, height, field, value) in cases {
+            let ihdr = IHDR {
+                width,
+                height,
+                ..header(8, 0)
+            };
+            assert_eq!(
+                ihdr.validate(),
+                Err(ChunkError::InvalidValue { field, value })
+            );
+        }
+
+        let largest = IHDR {
+            width: i32::MAX as u32,
+            height: i32::MAX as u32,
+            ..header(8, 0)
+        };
+        assert_eq!(largest.validate(), Ok(()));
+    }
+
+    #[test]
+    fn ihdr_methods() {
+        let cases = [
+            ([1, 0, 0], "compression method", 1),
+            ([0, 1, 0], "filter method", 1),
+            ([0, 0, 2], "interlace method", 2),
+        ];
+        for ([compression_method, filter_method, interlace_method], field, value) in cases {
+            let ihdr = IHDR {
+                compression_method,
+                filter_method,
+                interlace_method,
+                ..header(8, 0)
+            };
+            assert_eq!(
+                ihdr.validate(),
+                Err(ChunkError::InvalidValue { field, value })
+            );
+        }
+
+        let adam7 = IHDR {
+            interlace_method: 1,
+            ..header(8, 0)
+        };
+        assert_eq!(adam7.validate(), Ok(()));
+    }
+
+    #[test]
+    fn ihdr_is_decoded() {
+        let error = Png::from_bytes(&testing::png(&[(b"IHDR", &testing::ihdr(1, 1, 3, 2))]));
+        assert!(matches!(
+            error,
+            Err(PngError::InvalidChunk {
+                error: ChunkError::InvalidBitDepth {
+                    color_type: 2,
+                    bit_depth: 3
+                },
+                ..
+            })
+        ));
+
+        let data = [&testing::ihdr(1, 1, 8, 0)[..], &[0]].concat();
+        let error = Png::from_bytes(&testing::png(&[(b"IHDR", &data)]));
+        assert!(matches!(
+            error,
+            Err(PngError::InvalidChunk {
+                error: ChunkError::InvalidLength { length: 14 },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn plte_lengths() {
+        for length in [3, 6, 256 * 3] {
+            let data = vec![0; length];
+            let Ok(ParsedChunk::PLTE(plte)) = parse_in(3, &[(b"PLTE", &data)]) else {
+                panic!("{} bytes of PLTE", length);
+            };
+            assert_eq!(plte.pallet.len(), length / 3);
+        }
+        for length in [0, 2, 4, 257 * 3] {
+            let data = vec![0; length];
+            assert_eq!(
+                parse_in(3, &[(b"PLTE", &data)]).unwrap_err(),
+                ChunkError::InvalidLength { length }
+            );
+        }
+    }
+
+    #[test]
+    fn trns_lengths() {
+        // Greyscale & truecolor hold one 2 byte sample per channel
+        for (color_type, length) in [(0, 2), (2, 6)] {
+            assert!(parse_in(color_type, &[(b"tRNS", &vec![0; length])]).is_ok());
+            for wrong in [0, length - 1, length + 1] {
+                assert_eq!(
+                    parse_in(color_type, &[(b"tRNS", &vec![0; wrong])]).unwrap_err(),
+                    ChunkError::InvalidLength { length: wrong }
+                );
+            }
+        }
+
+        // Indexed holds up to one alpha per pallet entry
+        let plte = [0; 2 * 3];
+        for length in [0, 1, 2] {
+            let Ok(ParsedChunk::tRNS(tRNS::Indexed { pallet })) =
+                parse_in(3, &[(b"PLTE", &plte), (b"tRNS", &vec![7; length])])
+            else {
+                panic!("{} bytes of tRNS", length);
+            };
+            assert_eq!(pallet, vec![7; length]);
+        }
+        assert_eq!(
+            parse_in(3, &[(b"PLTE", &plte), (b"tRNS", &[0; 3])]).unwrap_err(),
+            ChunkError::TooManyEntries { entries: 3, max: 2 }
+        );
+        assert_eq!(
+            parse_in(3, &[(b"tRNS", &[0])]).unwrap_err(),
+            ChunkError::MissingPlte
+        );
+
+        // Alpha channels make tRNS pointless
+        for color_type in [4, 6] {
+            assert_eq!(
+                parse_in(color_type, &[(b"tRNS", &[0, 0])]).unwrap_err(),
+                ChunkError::NotAllowedForColorType { color_type }
+            );
+        }
+    }
+
+    #[test]
+    fn trns_samples_fit_the_bit_depth() {
+        assert!(parse_in(0, &[(b"tRNS", &[0, 255])]).is_ok());
+        assert_eq!(
+            parse_in(0, &[(b"tRNS", &[1, 0])]).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "grey sample",
+                value: 256
+            }
+        );
+        assert_eq!(
+            parse_in(2, &[(b"tRNS", &[0, 0, 0, 0, 1, 0])]).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "blue sample",
+                value: 256
+            }
+        );
+    }
+
+    #[test]
+    fn keyword_lengths() {
+        assert_eq!(
+            parse(b"tEXt", b"\0text").unwrap_err(),
+            ChunkError::InvalidKeywordLength { length: 0 }
+        );
+
+        let longest = [&[b'k'; 79][..], b"\0text"].concat();
+        let Ok(ParsedChunk::tEXt(text)) = parse(b"tEXt", &longest) else {
+            panic!("a 79 byte keyword");
+        };
+        assert_eq!(text.keyword.len(), 79);
+
+        let too_long = [&[b'k'; 80][..], b"\0text"].concat();
+        assert_eq!(
+            parse(b"tEXt", &too_long).unwrap_err(),
+            ChunkError::InvalidKeywordLength { length: 80 }
+        );
+
+        assert_eq!(
+            parse(b"tEXt", b"Title").unwrap_err(),
+            ChunkError::MissingNullSeparator
+        );
+    }
+
+    #[test]
+    fn keyword_characters() {
+        let cases: [(&[u8], &str); 4] = [
+            (b" Title\0", "starts or ends with a space"),
+            (b"Title \0", "starts or ends with a space"),
+            (b"Image  Title\0", "has consecutive spaces"),
+            (b"Ti\ttle\0", "has a character that isn't printable Latin-1"),
+        ];
+        for (data, reason) in cases {
+            let keyword = latin1(&data[..data.len() - 1]);
+            assert_eq!(
+                parse(b"tEXt", data).unwrap_err(),
+                ChunkError::InvalidKeyword { keyword, reason }
+            );
+        }
+    }
+
+    #[test]
+    fn text_is_latin1() {
+        let Ok(ParsedChunk::tEXt(text)) = parse(b"tEXt", b"Caf\xe9\0cr\xe8me br\xfbl\xe9e") else {
+            panic!("Latin-1 tEXt");
+        };
+        assert_eq!(text.keyword, "Caf\u{e9}");
+        assert_eq!(text.text, "cr\u{e8}me br\u{fb}l\u{e9}e");
+        assert_eq!(
+            keyword_description("Title"),
+            Some("Short (one line) title or caption for image")
+        );
+        assert_eq!(keyword_description(&text.keyword), None);
+    }
+
+    #[test]
+    fn text_cant_hold_nulls() {
+        assert_eq!(
+            parse(b"tEXt", b"Title\0one\0two").unwrap_err(),
+            ChunkError::NullInText
+        );
+    }
+
+    #[test]
+    fn compressed_text() {
+        let data = [b"Comment\0\0".as_slice(), &writer::zlib_stored(b"caf\xe9")].concat();
+        let Ok(ParsedChunk::zTXt(text)) = parse(b"zTXt", &data) else {
+            panic!("zTXt");
+        };
+        assert_eq!(text.keyword, "Comment");
+        assert_eq!(text.text().unwrap(), "caf\u{e9}");
+
+        let data = [b"Comment\0\x01".as_slice(), &writer::zlib_stored(b"text")].concat();
+        assert_eq!(
+            parse(b"zTXt", &data).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "compression method",
+                value: 1
+            }
+        );
+
+        // Broken compressed data is only found when the text is read
+        let Ok(ParsedChunk::zTXt(text)) = parse(b"zTXt", b"Comment\0\0\x78\x01\x07") else {
+            panic!("zTXt");
+        };
+        assert!(matches!(text.text(), Err(ChunkError::Decompress(..))));
+    }
+
+    #[test]
+    fn international_text() {
+        let data = "Title\0\0\0de-DE\0Titel\0Gr\u{fc}\u{df}e".as_bytes();
+        let Ok(ParsedChunk::iTXt(text)) = parse(b"iTXt", data) else {
+            panic!("iTXt");
+        };
+        assert_eq!(text.keyword, "Title");
+        assert!(!text.is_compressed());
+        assert_eq!(text.language_tag, "de-DE");
+        assert_eq!(text.translated_keyword, "Titel");
+        assert_eq!(text.text().unwrap(), "Gr\u{fc}\u{df}e");
+
+        // Ignored when the text isn't compressed
+        let data = b"Title\0\0\x05en\0\0text";
+        assert!(parse(b"iTXt", data).is_ok());
+
+        assert_eq!(
+            parse(b"iTXt", b"Title\0\x02\0en\0\0text").unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "compression flag",
+                value: 2
+            }
+        );
+        assert_eq!(
+            parse(b"iTXt", b"Title\0\0\0e n\0\0text").unwrap_err(),
+            ChunkError::InvalidLanguageTag {
+                tag: "e n".to_string()
+            }
+        );
+        assert_eq!(
+            parse(b"iTXt", b"Title\0\0\0en\0\xff\0text").unwrap_err(),
+            ChunkError::InvalidUtf8 {
+                field: "translated keyword"
+            }
+        );
+    }
+
+    #[test]
+    fn compressed_international_text() {
+        let text = "Gr\u{fc}\u{df}e".as_bytes();
+        let data = [
+            b"Title\0\x01\0en\0\0".as_slice(),
+            &writer::zlib_stored(text),
+        ]
+        .concat();
+        let Ok(ParsedChunk::iTXt(chunk)) = parse(b"iTXt", &data) else {
+            panic!("iTXt");
+        };
+        assert!(chunk.is_compressed());
+        assert_eq!(chunk.text().unwrap(), "Gr\u{fc}\u{df}e");
+
+        let data = [
+            b"Title\0\x01\x01en\0\0".as_slice(),
+            &writer::zlib_stored(text),
+        ]
+        .concat();
+        assert_eq!(
+            parse(b"iTXt", &data).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "compression method",
+                value: 1
+            }
+        );
+
+        let data = [
+            b"Title\0\x01\0en\0\0".as_slice(),
+            &writer::zlib_stored(b"\xff"),
+        ]
+        .concat();
+        let Ok(ParsedChunk::iTXt(chunk)) = parse(b"iTXt", &data) else {
+            panic!("iTXt");
+        };
+        assert_eq!(chunk.text(), Err(ChunkError::InvalidUtf8 { field: "text" }));
+    }
+
+    fn time(data: &[u8]) -> Result<tIME, ChunkError> {
+        match parse(b"tIME", data)? {
+            ParsedChunk::tIME(time) => Ok(time),
+            parsed => panic!("{:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn time_formats() {
+        let leap_day = time(&[0x07, 0xd0, 2, 29, 0, 0, 0]).unwrap();
+        assert_eq!(leap_day.to_iso8601(), "2000-02-29T00:00:00Z");
+        assert_eq!(leap_day.unix_time(), 951782400);
+
+        let epoch = time(&[0x07, 0xb2, 1, 1, 0, 0, 0]).unwrap();
+        assert_eq!(epoch.unix_time(), 0);
+
+        let time = time(&[0x07, 0xe8, 5, 1, 13, 45, 9]).unwrap();
+        assert_eq!(time.to_iso8601(), "2024-05-01T13:45:09Z");
+        assert_eq!(time.unix_time(), 1714571109);
+    }
+
+    #[test]
+    fn time_leap_second() {
+        // Counted as the first second of the next day, as unix time has no leap seconds
+        let leap_second = time(&[0x07, 0xe0, 12, 31, 23, 59, 60]).unwrap();
+        assert_eq!(leap_second.to_iso8601(), "2016-12-31T23:59:60Z");
+        assert_eq!(leap_second.unix_time(), 1483228800);
+    }
+
+    #[test]
+    fn time_ranges() {
+        let cases = [
+            ([0, 1, 0, 0, 0], "month", 0),
+            ([13, 1, 0, 0, 0], "month", 13),
+            ([1, 0, 0, 0, 0], "day", 0),
+            ([1, 32, 0, 0, 0], "day", 32),
+            ([1, 1, 24, 0, 0], "hour", 24),
+            ([1, 1, 0, 60, 0], "minute", 60),
+            ([1, 1, 0, 0, 61], "second", 61),
+        ];
+        for (fields, field, value) in cases {
+            let data = [&[0x07, 0xe8][..], &fields].concat();
+            assert_eq!(
+                time(&data).unwrap_err(),
+                ChunkError::InvalidValue { field, value }
+            );
+        }
+
+        assert!(time(&[0x07, 0xe8, 12, 31, 23, 59, 59]).is_ok());
+        assert_eq!(
+            time(&[0x07, 0xe8, 1, 1, 0, 0]).unwrap_err(),
+            ChunkError::InvalidLength { length: 6 }
+        );
+    }
+
+    fn cicp(data: &[u8]) -> Result<cICP, ChunkError> {
+        match parse(b"cICP", data)? {
+            ParsedChunk::cICP(cicp) => Ok(cicp),
+            parsed => panic!("{:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn cicp_names() {
+        let primaries = [
+            (1, "BT.709 / sRGB"),
+            (3, "Reserved"),
+            (9, "BT.2020 / BT.2100"),
+            (12, "Display P3"),
+            (22, "EBU Tech 3213-E"),
+            (23, "Reserved"),
+        ];
+        for (value, name) in primaries {
+            assert_eq!(cICP::primaries_name(value), name);
+        }
+        let transfers = [
+            (1, "BT.709"),
+            (3, "Reserved"),
+            (8, "Linear"),
+            (13, "sRGB"),
+            (16, "PQ (SMPTE ST 2084)"),
+            (18, "HLG (ARIB STD-B67)"),
+            (19, "Reserved"),
+        ];
+        for (value, name) in transfers {
+            assert_eq!(cICP::transfer_name(value), name);
+        }
+        let matrices = [
+            (0, "Identity (RGB)"),
+            (3, "Reserved"),
+            (9, "BT.2020 non-constant luminance"),
+            (14, "ICtCp"),
+            (15, "Reserved"),
+        ];
+        for (value, name) in matrices {
+            assert_eq!(cICP::matrix_name(value), name);
+        }
+    }
+
+    #[test]
+    fn cicp_color_spaces() {
+        let pq = cicp(&[9, 16, 0, 1]).unwrap();
+        assert_eq!(pq.color_space(), Some("BT.2100 PQ (HDR10)"));
+        assert!(pq.is_hdr());
+
+        let hlg = cicp(&[9, 18, 0, 0]).unwrap();
+        assert_eq!(hlg.color_space(), Some("BT.2100 HLG"));
+        assert!(hlg.is_hdr());
+
+        let srgb = cicp(&[1, 13, 0, 1]).unwrap();
+        assert_eq!(srgb.color_space(), Some("sRGB"));
+        assert!(!srgb.is_hdr());
+
+        assert_eq!(
+            cicp(&[12, 13, 0, 1]).unwrap().color_space(),
+            Some("Display P3")
+        );
+        assert_eq!(cicp(&[5, 6, 0, 1]).unwrap().color_space(), None);
+    }
+
+    #[test]
+    fn cicp_errors() {
+        assert_eq!(
+            cicp(&[9, 16, 9, 1]).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "matrix coefficients",
+                value: 9
+            }
+        );
+        assert_eq!(
+            cicp(&[9, 16, 0, 2]).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "video full range flag",
+                value: 2
+            }
+        );
+        assert_eq!(
+            cicp(&[9, 16, 0]).unwrap_err(),
+            ChunkError::InvalidLength { length: 3 }
+        );
+    }
+
+    #[test]
+    fn mdcv_units() {
+        // Display P3 & D65 in 0.00002 units, from 0.0001 to 1000 cd/m²
+        let values: [u16; 8] = [34000, 16000, 13250, 34500, 7500, 3000, 15635, 16450];
+        let mut data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        data.extend_from_slice(&10_000_000u32.to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+
+        let Ok(ParsedChunk::mDCV(mdcv)) = parse(b"mDCV", &data) else {
+            panic!("mDCV");
+        };
+        let gamut = mdcv.gamut();
+        let close = |(x, y): (f64, f64), expected: (f64, f64)| {
+            (x - expected.0).abs() < 1e-9 && (y - expected.1).abs() < 1e-9
+        };
+        assert!(close(gamut.red, (0.68, 0.32)));
+        assert!(close(gamut.green, (0.265, 0.69)));
+        assert!(close(gamut.blue, (0.15, 0.06)));
+        assert!(close(gamut.white, (0.3127, 0.329)));
+        assert_eq!(gamut.known().map(|known| known.name), Some("Display P3"));
+
+        assert_eq!(candelas(mdcv.max_luminance), 1000.0);
+        assert_eq!(candelas(mdcv.min_luminance), 0.0001);
+        assert!(mdcv
+            .to_string()
+            .contains(" luminance: 0.0001 to 1000 cd/m²\n"));
+
+        assert_eq!(
+            parse(b"mDCV", &data[..23]).unwrap_err(),
+            ChunkError::InvalidLength { length: 23 }
+        );
+    }
+
+    #[test]
+    fn clli_units() {
+        let data = [10_000_000u32.to_be_bytes(), 0u32.to_be_bytes()].concat();
+        let Ok(ParsedChunk::cLLI(clli)) = parse(b"cLLI", &data) else {
+            panic!("cLLI");
+        };
+        assert_eq!(clli.max_content_light_level, 10_000_000);
+        assert_eq!(
+            clli.to_string(),
+            " max content light level: 1000 cd/m²\n max frame average light level: unknown\n"
+        );
+    }
+
+    #[test]
+    fn float_grammar() {
+        let valid = [
+            ("1", 1.0),
+            ("-2.5", -2.5),
+            ("+3", 3.0),
+            (".5", 0.5),
+            ("1.", 1.0),
+            ("1e3", 1000.0),
+            ("2.5E-2", 0.025),
+            ("-.25e+1", -2.5),
+        ];
+        for (text, value) in valid {
+            assert_eq!(read_float("value", text.as_bytes()), Ok(value), "{}", text);
+        }
+
+        let invalid = [
+            "", ".", "+", "1.2.3", "e5", "1e", "--1", "inf", "NaN", "0x10", " 1", "1,5",
+        ];
+        for text in invalid {
+            assert_eq!(
+                read_float("value", text.as_bytes()),
+                Err(ChunkError::InvalidNumber {
+                    field: "value",
+                    text: text.to_string(),
+                    reason: "isn't a floating point number"
+                }),
+                "{}",
+                text
+            );
+        }
+    }
+
+    fn calibration(equation_type: u8, parameters: &[f64]) -> pCAL {
+        pCAL {
+            calibration_name: "test".to_string(),
+            x0: 0,
+            x1: 100,
+            equation_type,
+            unit_name: String::new(),
+            parameters: parameters.to_vec(),
+        }
+    }
+
+    #[test]
+    fn calibration_equations() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // p0 + p1 * X / 100
+        let linear = calibration(0, &[10.0, 2.0]);
+        assert!(close(linear.physical_value(50.0), 11.0));
+        assert_eq!(linear.equation(), "10 + 2 * X / 100");
+
+        // p0 + p1 * e^(p2 * X / 100)
+        let exponential = calibration(1, &[1.0, 2.0, 1.0]);
+        assert!(close(exponential.physical_value(0.0), 3.0));
+        assert!(close(
+            exponential.physical_value(100.0),
+            1.0 + 2.0 * 1f64.exp()
+        ));
+
+        // p0 + p1 * p2^(X / 100)
+        let arbitrary_base = calibration(2, &[0.0, 1.0, 10.0]);
+        assert!(close(arbitrary_base.physical_value(200.0), 100.0));
+        assert!(close(arbitrary_base.physical_value(-100.0), 0.1));
+
+        // p0 + p1 * sinh(p2 * (X - p3) / 100)
+        let hyperbolic = calibration(3, &[5.0, 2.0, 1.0, 50.0]);
+        assert!(close(hyperbolic.physical_value(50.0), 5.0));
+        assert!(close(
+            hyperbolic.physical_value(150.0),
+            5.0 + 2.0 * 1f64.sinh()
+        ));
+        assert_eq!(hyperbolic.equation(), "5 + 2 * sinh(1 * (X - 50) / 100)");
+    }
+
+    #[test]
+    fn calibration_original_samples() {
+        let mut pcal = calibration(0, &[0.0, 1.0]);
+        pcal.x1 = 1000;
+        assert_eq!(pcal.original_sample(0, 255), 0.0);
+        assert_eq!(pcal.original_sample(255, 255), 1000.0);
+        // (128 * 1000 + 127) / 255, rounded down
+        assert_eq!(pcal.original_sample(128, 255), 502.0);
+
+        pcal.x0 = -100;
+        pcal.x1 = 100;
+        assert_eq!(pcal.original_sample(0, 65535), -100.0);
+        assert_eq!(pcal.original_sample(65535, 65535), 100.0);
+    }
+
+    fn pcal_data(x0: i32, x1: i32, equation_type: u8, parameters: &[&str]) -> Vec<u8> {
+        let mut data = b"depth\0".to_vec();
+        data.extend_from_slice(&x0.to_be_bytes());
+        data.extend_from_slice(&x1.to_be_bytes());
+        data.extend_from_slice(&[equation_type, parameters.len() as u8]);
+        data.extend_from_slice(b"m\0");
+        data.extend_from_slice(parameters.join("\0").as_bytes());
+        data
+    }
+
+    #[test]
+    fn calibration_chunk() {
+        let Ok(ParsedChunk::pCAL(pcal)) = parse(b"pCAL", &pcal_data(0, 255, 0, &["-1", "2.5e1"]))
+        else {
+            panic!("pCAL");
+        };
+        assert_eq!(pcal.calibration_name, "depth");
+        assert_eq!(pcal.unit_name, "m");
+        assert_eq!(pcal.parameters, [-1.0, 25.0]);
+
+        assert_eq!(
+            parse(b"pCAL", &pcal_data(0, 255, 0, &["1", "2", "3"])).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "parameter count",
+                value: 3
+            }
+        );
+        assert_eq!(
+            parse(b"pCAL", &pcal_data(0, 255, 4, &["1"])).unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "equation type",
+                value: 4
+            }
+        );
+        assert_eq!(
+            parse(b"pCAL", &pcal_data(7, 7, 0, &["1", "2"])).unwrap_err(),
+            ChunkError::InvalidNumber {
+                field: "X1",
+                text: "7".to_string(),
+                reason: "is the same as X0"
+            }
+        );
+        assert_eq!(
+            parse(b"pCAL", &pcal_data(0, 255, 0, &["1", "two"])).unwrap_err(),
+            ChunkError::InvalidNumber {
+                field: "parameter",
+                text: "two".to_string(),
+                reason: "isn't a floating point number"
+            }
+        );
+    }
+
+    #[test]
+    fn scale_sizes() {
+        let Ok(ParsedChunk::sCAL(scal)) = parse(b"sCAL", b"\x01.5\x002.5E-1") else {
+            panic!("sCAL");
+        };
+        assert_eq!((scal.pixel_width, scal.pixel_height), (0.5, 0.25));
+
+        let cases: [(&[u8], &str, &str); 4] = [
+            (b"\x010\x001", "pixel width", "0"),
+            (b"\x01-1\x001", "pixel width", "-1"),
+            (b"\x010.0e5\x001", "pixel width", "0.0e5"),
+            (b"\x011\x00-0.5", "pixel height", "-0.5"),
+        ];
+        for (data, field, text) in cases {
+            assert_eq!(
+                parse(b"sCAL", data).unwrap_err(),
+                ChunkError::InvalidNumber {
+                    field,
+                    text: text.to_string(),
+                    reason: "isn't more than 0"
+                }
+            );
+        }
+
+        assert_eq!(
+            parse(b"sCAL", b"\x031\x001").unwrap_err(),
+            ChunkError::InvalidValue {
+                field: "unit specifier",
+                value: 3
+            }
+        );
+    }
+}
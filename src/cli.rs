@@ -2,16 +2,32 @@ use std::path::Path;
 
 use std::path::PathBuf;
 
+use color::{CrcCheck, DisplayOptions, ParseOptions};
+
 #[derive(Debug)]
 pub struct Cli {
     pub file_path: Option<PathBuf>,
     pub display_options: DisplayOptions,
+    pub parse_options: ParseOptions,
+    // Write the iCCP profile out as a .icc file
+    pub extract_icc: bool,
+    // Where to write it, next to the PNG if not given
+    pub icc_path: Option<PathBuf>,
+    // Only report problems with the chunk layout, instead of printing the chunks
+    pub validate: bool,
+    // Write each APNG frame out as its own PNG
+    pub extract_frames: Option<FrameExtraction>,
+    // Which directory to write them to, next to the PNG if not given
+    pub frames_path: Option<PathBuf>,
 }
 
-#[derive(Debug)]
-pub struct DisplayOptions {
-    // If description should be added to the stdout messages.
-    pub descriptive: Option<bool>,
+// What each extracted APNG frame holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameExtraction {
+    // Just the frame's own pixels, at its own size
+    Raw,
+    // The whole canvas after the frame is drawn onto it
+    Canvas,
 }
 
 trait IsPng {
@@ -46,7 +62,7 @@ impl CountDashes for String {
             }
             dash_num += 1;
         }
-        return dash_num;
+        dash_num
     }
 }
 
@@ -55,12 +71,18 @@ impl From<std::env::Args> for Cli {
     fn from(args: std::env::Args) -> Self {
         let mut cli = Cli {
             file_path: None,
-            display_options: DisplayOptions { descriptive: None },
+            display_options: DisplayOptions::default(),
+            parse_options: ParseOptions::default(),
+            extract_icc: false,
+            icc_path: None,
+            validate: false,
+            extract_frames: None,
+            frames_path: None,
         };
 
         // Iterate over the remaining env variables
         for mut argument in args.skip(1) {
-            if argument.chars().nth(0).unwrap() == '-' {
+            if argument.starts_with('-') {
                 let dash_num = argument.count_dashes();
 
                 for _ in 0..dash_num {
@@ -74,14 +96,29 @@ impl From<std::env::Args> for Cli {
                     for chars in argument.chars() {
                         match chars {
                             'd' => {
-                                if cli.display_options.descriptive.is_some() {
+                                if cli.display_options.descriptive {
                                     println!(
                                         "Don't assign multiple discription options. Ignoring '{}'.",
                                         chars
                                     );
                                 }
 
-                                cli.display_options.descriptive = Some(true);
+                                cli.display_options.descriptive = true;
+                            }
+                            'l' => {
+                                cli.parse_options.crc_check = CrcCheck::Lenient;
+                            }
+                            'i' => {
+                                cli.extract_icc = true;
+                            }
+                            'c' => {
+                                cli.validate = true;
+                            }
+                            'f' => {
+                                cli.extract_frames = Some(FrameExtraction::Raw);
+                            }
+                            'a' => {
+                                cli.extract_frames = Some(FrameExtraction::Canvas);
                             }
                             _ => {
                                 println!("Unknown short flag: '{}'", chars);
@@ -94,15 +131,53 @@ impl From<std::env::Args> for Cli {
                 // https://www.gnu.org/software/tar/manual/html_node/Long-Options.html
                 // It's a more human readable but bigger option method.
                 if dash_num >= 2 {
+                    // --extract-icc=<path> picks where the profile is written
+                    if let Some(path) = argument.strip_prefix("extract-icc=") {
+                        cli.extract_icc = true;
+                        cli.icc_path = Some(PathBuf::from(path));
+                        continue;
+                    }
+                    // --extract-frames=<dir> & --extract-canvases=<dir> pick where the frames go
+                    if let Some(path) = argument.strip_prefix("extract-frames=") {
+                        cli.extract_frames = Some(FrameExtraction::Raw);
+                        cli.frames_path = Some(PathBuf::from(path));
+                        continue;
+                    }
+                    if let Some(path) = argument.strip_prefix("extract-canvases=") {
+                        cli.extract_frames = Some(FrameExtraction::Canvas);
+                        cli.frames_path = Some(PathBuf::from(path));
+                        continue;
+                    }
+
                     match argument.as_str() {
                         "description" | "descriptive" => {
-                            if cli.display_options.descriptive.is_some() {
+                            if cli.display_options.descriptive {
                                 println!(
                                     "Don't assign multiple description options. Ignoring {} option.",
                                     argument
                                 );
                             }
-                            cli.display_options.descriptive = Some(true);
+                            cli.display_options.descriptive = true;
+                        }
+                        // Keep going past chunks with a bad CRC, showing the mismatch instead
+                        "lenient" => {
+                            cli.parse_options.crc_check = CrcCheck::Lenient;
+                        }
+                        // Write the embedded ICC profile next to the PNG
+                        "extract-icc" => {
+                            cli.extract_icc = true;
+                        }
+                        // Check the chunk order & layout, like pngcheck
+                        "validate" => {
+                            cli.validate = true;
+                        }
+                        // Write each APNG frame as its own PNG
+                        "extract-frames" => {
+                            cli.extract_frames = Some(FrameExtraction::Raw);
+                        }
+                        // Same, but with the whole canvas as it's shown for each frame
+                        "extract-canvases" => {
+                            cli.extract_frames = Some(FrameExtraction::Canvas);
                         }
                         _ => {
                             println!("Unknown long flag: '{}'", argument)
@@ -116,7 +191,7 @@ impl From<std::env::Args> for Cli {
             }
 
             // If there's no dashes, assume it's meant to be the path
-            if cli.file_path != None {
+            if cli.file_path.is_some() {
                 println!("Don't assign multiple paths. Ignoring '{}'", argument);
                 continue;
             }
@@ -134,18 +209,13 @@ impl From<std::env::Args> for Cli {
                 }
             };
 
-            if path.is_png() == false {
+            if !path.is_png() {
                 panic!("Path supplied '{:?}' is not a PNG", &argument);
             }
 
             cli.file_path = Some(path);
         }
 
-        // Default Values
-        if cli.display_options.descriptive == None {
-            cli.display_options.descriptive = Some(false);
-        }
-
         cli
     }
 }
@@ -0,0 +1,273 @@
+// The color math behind cHRM (and later the ICC profile): chromaticities,
+// RGB to XYZ matrices and the well known gamuts.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11cHRM
+
+// A CIE 1931 chromaticity, (x, y)
+pub type Chromaticity = (f64, f64);
+
+// Rows are X, Y & Z, columns are red, green & blue
+pub type Matrix = [[f64; 3]; 3];
+
+// A set of primaries and the white point they're balanced to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gamut {
+    pub name: &'static str,
+    pub white: Chromaticity,
+    pub red: Chromaticity,
+    pub green: Chromaticity,
+    pub blue: Chromaticity,
+}
+
+const D65: Chromaticity = (0.3127, 0.3290);
+
+// cHRM stores 5 decimal places, but encoders round the published values differently
+pub const GAMUT_TOLERANCE: f64 = 0.001;
+
+pub const GAMUTS: [Gamut; 3] = [
+    // https://www.itu.int/rec/R-REC-BT.709
+    Gamut {
+        name: "sRGB / Rec.709",
+        white: D65,
+        red: (0.64, 0.33),
+        green: (0.30, 0.60),
+        blue: (0.15, 0.06),
+    },
+    Gamut {
+        name: "Display P3",
+        white: D65,
+        red: (0.680, 0.320),
+        green: (0.265, 0.690),
+        blue: (0.150, 0.060),
+    },
+    // https://www.itu.int/rec/R-REC-BT.2020
+    Gamut {
+        name: "Rec.2020",
+        white: D65,
+        red: (0.708, 0.292),
+        green: (0.170, 0.797),
+        blue: (0.131, 0.046),
+    },
+];
+
+impl Gamut {
+    // Whether every chromaticity is within `tolerance` of the other gamut's
+    pub fn matches(&self, other: &Gamut, tolerance: f64) -> bool {
+        let close = |a: Chromaticity, b: Chromaticity| {
+            (a.0 - b.0).abs() <= tolerance && (a.1 - b.1).abs() <= tolerance
+        };
+
+        close(self.white, other.white)
+            && close(self.red, other.red)
+            && close(self.green, other.green)
+            && close(self.blue, other.blue)
+    }
+
+    // The first of `GAMUTS` this one matches
+    pub fn known(&self) -> Option<&'static Gamut> {
+        GAMUTS
+            .iter()
+            .find(|gamut| self.matches(gamut, GAMUT_TOLERANCE))
+    }
+
+    // The matrix turning linear RGB into XYZ, scaled so white has Y = 1.
+    // None if the chromaticities can't make one (a y of 0, or primaries in a line).
+    // http://www.brucelindbloom.com/index.html?Eqn_RGB_XYZ_Matrix.html
+    pub fn rgb_to_xyz(&self) -> Option<Matrix> {
+        let primaries = [to_xyz(self.red)?, to_xyz(self.green)?, to_xyz(self.blue)?];
+        let unscaled = [0, 1, 2].map(|row| primaries.map(|primary| primary[row]));
+
+        // Scale each primary so together they add up to the white point
+        let scale = multiply(&invert(&unscaled)?, to_xyz(self.white)?);
+
+        Some(unscaled.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]]))
+    }
+}
+
+// XYZ of a chromaticity, with Y = 1
+pub fn to_xyz((x, y): Chromaticity) -> Option<[f64; 3]> {
+    if y == 0.0 {
+        return None;
+    }
+
+    Some([x / y, 1.0, (1.0 - x - y) / y])
+}
+
+pub fn multiply(matrix: &Matrix, vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+// None if the matrix is singular
+pub fn invert(m: &Matrix) -> Option<Matrix> {
+    let determinant = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    // The adjugate, divided by the determinant
+    let cofactor = |row: usize, column: usize| {
+        let rows = [(row + 1) % 3, (row + 2) % 3];
+        let columns = [(column + 1) % 3, (column + 2) % 3];
+        m[rows[0]][columns[0]] * m[rows[1]][columns[1]]
+            - m[rows[0]][columns[1]] * m[rows[1]][columns[0]]
+    };
+
+    Some([0, 1, 2].map(|row| [0, 1, 2].map(|column| cofactor(column, row) / determinant)))
+}
+
+pub fn multiply_matrices(a: &Matrix, b: &Matrix) -> Matrix {
+    [0, 1, 2].map(|row| [0, 1, 2].map(|column| (0..3).map(|i| a[row][i] * b[i][column]).sum()))
+}
+
+// The ICC profile connection space is always balanced to D50
+pub const D50_XYZ: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+const BRADFORD: Matrix = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+// The Bradford chromatic adaptation from one white (as XYZ) to another
+// http://www.brucelindbloom.com/index.html?Eqn_ChromAdapt.html
+pub fn bradford(from: [f64; 3], to: [f64; 3]) -> Option<Matrix> {
+    let from = multiply(&BRADFORD, from);
+    let to = multiply(&BRADFORD, to);
+    if from.contains(&0.0) {
+        return None;
+    }
+
+    let scale = [
+        [to[0] / from[0], 0.0, 0.0],
+        [0.0, to[1] / from[1], 0.0],
+        [0.0, 0.0, to[2] / from[2]],
+    ];
+    Some(multiply_matrices(
+        &invert(&BRADFORD)?,
+        &multiply_matrices(&scale, &BRADFORD),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    fn assert_close(a: &Matrix, b: &Matrix, tolerance: f64) {
+        for row in 0..3 {
+            for column in 0..3 {
+                assert!(
+                    (a[row][column] - b[row][column]).abs() < tolerance,
+                    "{:?} != {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_to_xyz() {
+        // The matrix published in IEC 61966-2-1, to 4 decimal places
+        let published = [
+            [0.4124, 0.3576, 0.1805],
+            [0.2126, 0.7152, 0.0722],
+            [0.0193, 0.1192, 0.9505],
+        ];
+        assert_close(&GAMUTS[0].rgb_to_xyz().unwrap(), &published, 0.00005);
+    }
+
+    #[test]
+    fn white_has_y_of_one() {
+        for gamut in GAMUTS {
+            let white = multiply(&gamut.rgb_to_xyz().unwrap(), [1.0, 1.0, 1.0]);
+            let expected = to_xyz(gamut.white).unwrap();
+            for (a, b) in white.iter().zip(expected) {
+                assert!((a - b).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn unusable_chromaticities() {
+        assert_eq!(to_xyz((0.3, 0.0)), None);
+
+        let in_a_line = Gamut {
+            green: (0.4, 0.2),
+            blue: (0.2, 0.1),
+            red: (0.6, 0.3),
+            ..GAMUTS[0]
+        };
+        assert_eq!(in_a_line.rgb_to_xyz(), None);
+    }
+
+    #[test]
+    fn invert_round_trips() {
+        for gamut in GAMUTS {
+            let matrix = gamut.rgb_to_xyz().unwrap();
+            let inverse = invert(&matrix).unwrap();
+            assert_close(&multiply_matrices(&matrix, &inverse), &IDENTITY, 1e-12);
+            assert_close(&multiply_matrices(&inverse, &matrix), &IDENTITY, 1e-12);
+        }
+        assert_close(&invert(&IDENTITY).unwrap(), &IDENTITY, f64::EPSILON);
+
+        let singular = [[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 0.0]];
+        assert_eq!(invert(&singular), None);
+    }
+
+    #[test]
+    fn known_gamuts() {
+        let names: Vec<_> = GAMUTS
+            .iter()
+            .map(|gamut| gamut.known().map(|known| known.name))
+            .collect();
+        assert_eq!(
+            names,
+            [Some("sRGB / Rec.709"), Some("Display P3"), Some("Rec.2020")]
+        );
+    }
+
+    #[test]
+    fn gamut_tolerance() {
+        for gamut in GAMUTS {
+            let nudged = |offset: f64| Gamut {
+                green: (gamut.green.0 + offset, gamut.green.1 - offset),
+                ..gamut
+            };
+            assert_eq!(
+                nudged(0.0009).known().map(|known| known.name),
+                Some(gamut.name)
+            );
+            assert_eq!(
+                nudged(-0.0009).known().map(|known| known.name),
+                Some(gamut.name)
+            );
+            assert_eq!(nudged(0.0011).known(), None);
+            assert_eq!(nudged(-0.0011).known(), None);
+        }
+    }
+
+    #[test]
+    fn bradford_d65_to_d50() {
+        // http://www.brucelindbloom.com/index.html?Eqn_ChromAdapt.html
+        let d65 = [0.95047, 1.0, 1.08883];
+        let d50 = [0.96422, 1.0, 0.82521];
+        let published = [
+            [1.0478112, 0.0228866, -0.0501270],
+            [0.0295424, 0.9904844, -0.0170491],
+            [-0.0092345, 0.0150436, 0.7521316],
+        ];
+        let adaptation = bradford(d65, d50).unwrap();
+        assert_close(&adaptation, &published, 1e-6);
+
+        // The white point itself is carried over exactly
+        let adapted = multiply(&adaptation, d65);
+        for (a, b) in adapted.iter().zip(d50) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        assert_close(&bradford(d50, d50).unwrap(), &IDENTITY, 1e-12);
+    }
+}
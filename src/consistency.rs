@@ -0,0 +1,333 @@
+// Checks that the color chunks (gAMA, cHRM, sRGB & iCCP) don't contradict each other.
+// A viewer picks one of them to trust, and different viewers pick differently,
+// which is how an image ends up looking washed out in only some of them.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11srgb
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11iCCP
+
+use std::fmt;
+
+use crate::chunks::{cHRM, gAMA, iCCP, ParsedChunk};
+use crate::colorimetry::{self, GAMUTS, GAMUT_TOLERANCE};
+use crate::icc::IccProfile;
+use crate::parser::Png;
+
+// How far (as a fraction) gAMA's exponent can be from the profile curve's
+const GAMMA_TOLERANCE: f64 = 0.05;
+
+// How far each colorant's XYZ can be from the one cHRM describes
+const COLORANT_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorWarning {
+    // sRGB means a gAMA of 45455
+    SrgbGamma {
+        image_gama: u32,
+    },
+    // sRGB means the sRGB primaries & D65 white point
+    SrgbChromaticities,
+    // sRGB & iCCP shouldn't both be present
+    SrgbWithIccp,
+    // gAMA is far from the profile's tone curve
+    IccpGamma {
+        display_exponent: f64,
+        profile_exponent: f64,
+    },
+    // cHRM describes different primaries to the profile's colorants
+    IccpChromaticities {
+        largest_difference: f64,
+    },
+}
+
+impl fmt::Display for ColorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorWarning::SrgbGamma { image_gama } => write!(
+                f,
+                "sRGB is present, but gAMA is {} instead of {}",
+                image_gama,
+                gAMA::SRGB
+            ),
+            ColorWarning::SrgbChromaticities => write!(
+                f,
+                "sRGB is present, but cHRM doesn't hold the sRGB chromaticities"
+            ),
+            ColorWarning::SrgbWithIccp => write!(f, "sRGB and iCCP shouldn't both be present"),
+            ColorWarning::IccpGamma {
+                display_exponent,
+                profile_exponent,
+            } => write!(
+                f,
+                "gAMA's display exponent is {:.2}, but the iCCP profile's curve is about {:.2}",
+                display_exponent, profile_exponent
+            ),
+            ColorWarning::IccpChromaticities { largest_difference } => write!(
+                f,
+                "cHRM doesn't match the iCCP profile's colorants, they're up to {:.4} apart",
+                largest_difference
+            ),
+        }
+    }
+}
+
+// Every contradiction between the color chunks
+pub fn check(png: &Png) -> Vec<ColorWarning> {
+    let gama = png.parsed_chunks().find_map(|parsed| match parsed {
+        ParsedChunk::gAMA(chunk) => Some(chunk),
+        _ => None,
+    });
+    let chrm = png.parsed_chunks().find_map(|parsed| match parsed {
+        ParsedChunk::cHRM(chunk) => Some(chunk),
+        _ => None,
+    });
+    let srgb = png.parsed_chunks().find_map(|parsed| match parsed {
+        ParsedChunk::sRGB(chunk) => Some(chunk),
+        _ => None,
+    });
+    let iccp = png.iccp();
+
+    let mut warnings = Vec::new();
+    if srgb.is_some() {
+        check_srgb(gama, chrm, &mut warnings);
+        if iccp.is_some() {
+            warnings.push(ColorWarning::SrgbWithIccp);
+        }
+    }
+    if let Some(iccp) = iccp {
+        check_iccp(iccp, gama, chrm, &mut warnings);
+    }
+    warnings
+}
+
+// sRGB stands in for a specific gAMA & cHRM, so any others disagree with it
+fn check_srgb(gama: Option<&gAMA>, chrm: Option<&cHRM>, warnings: &mut Vec<ColorWarning>) {
+    if let Some(gama) = gama {
+        if gama.image_gama != gAMA::SRGB {
+            warnings.push(ColorWarning::SrgbGamma {
+                image_gama: gama.image_gama,
+            });
+        }
+    }
+
+    if let Some(chrm) = chrm {
+        if !chrm.gamut().matches(&GAMUTS[0], GAMUT_TOLERANCE) {
+            warnings.push(ColorWarning::SrgbChromaticities);
+        }
+    }
+}
+
+// The profile is only compared where it can be read, a broken profile is reported by iCCP itself
+fn check_iccp(
+    iccp: &iCCP,
+    gama: Option<&gAMA>,
+    chrm: Option<&cHRM>,
+    warnings: &mut Vec<ColorWarning>,
+) {
+    let profile = match iccp.profile().map(|profile| IccProfile::parse(&profile)) {
+        Ok(Ok(x)) => x,
+        _ => return,
+    };
+
+    if let Some(gama) = gama {
+        let profile_exponent = profile
+            .first_curve()
+            .and_then(|curve| curve.effective_gamma());
+        if let Some(profile_exponent) = profile_exponent {
+            let display_exponent = gama.display_exponent();
+            if (display_exponent - profile_exponent).abs() > profile_exponent * GAMMA_TOLERANCE {
+                warnings.push(ColorWarning::IccpGamma {
+                    display_exponent,
+                    profile_exponent,
+                });
+            }
+        }
+    }
+
+    if let Some(chrm) = chrm {
+        // The profile's colorants are adapted to D50, so cHRM's are too before comparing
+        let gamut = chrm.gamut();
+        let expected = gamut.rgb_to_xyz().and_then(|matrix| {
+            let white = colorimetry::to_xyz(gamut.white)?;
+            let adaptation = colorimetry::bradford(white, colorimetry::D50_XYZ)?;
+            Some(colorimetry::multiply_matrices(&adaptation, &matrix))
+        });
+
+        if let (Some(expected), Some(colorants)) = (expected, profile.colorants()) {
+            let largest_difference = (0..3)
+                .flat_map(|row| (0..3).map(move |column| (row, column)))
+                .map(|(row, column)| (expected[row][column] - colorants[row][column]).abs())
+                .fold(0.0, f64::max);
+
+            if largest_difference > COLORANT_TOLERANCE {
+                warnings.push(ColorWarning::IccpChromaticities { largest_difference });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colorimetry::{Chromaticity, Gamut, Matrix};
+    use crate::testing;
+    use crate::writer;
+
+    const SRGB: &[u8] = &[0];
+
+    fn gama(display_exponent: f64) -> Vec<u8> {
+        let image_gama = (100000.0 / display_exponent).round() as u32;
+        image_gama.to_be_bytes().to_vec()
+    }
+
+    fn chrm(gamut: &Gamut) -> Vec<u8> {
+        [gamut.white, gamut.red, gamut.green, gamut.blue]
+            .iter()
+            .flat_map(|(x, y)| [x, y])
+            .flat_map(|value| ((value * 100000.0).round() as u32).to_be_bytes())
+            .collect()
+    }
+
+    fn srgb_with(red: Chromaticity) -> Gamut {
+        Gamut { red, ..GAMUTS[0] }
+    }
+
+    // An iCCP chunk with a gamma curve & colorants
+    fn iccp(gamma: f64, colorants: &Matrix) -> Vec<u8> {
+        let s15_fixed16 = |value: f64| ((value * 65536.0).round() as i32).to_be_bytes();
+        let xyz = |column: usize| {
+            let values: Vec<u8> = (0..3)
+                .flat_map(|row| s15_fixed16(colorants[row][column]))
+                .collect();
+            testing::icc_tag(b"XYZ ", &values)
+        };
+        let curve = ((gamma * 256.0).round() as u16).to_be_bytes();
+        let profile = testing::icc_profile(&[
+            (
+                b"rTRC",
+                testing::icc_tag(b"curv", &[0, 0, 0, 1, curve[0], curve[1]]),
+            ),
+            (b"rXYZ", xyz(0)),
+            (b"gXYZ", xyz(1)),
+            (b"bXYZ", xyz(2)),
+        ]);
+        [b"test\0\0".as_slice(), &writer::zlib_stored(&profile)].concat()
+    }
+
+    // What the profile's colorants are when they match the sRGB cHRM
+    fn srgb_colorants() -> Matrix {
+        let matrix = GAMUTS[0].rgb_to_xyz().unwrap();
+        let white = colorimetry::to_xyz(GAMUTS[0].white).unwrap();
+        let adaptation = colorimetry::bradford(white, colorimetry::D50_XYZ).unwrap();
+        colorimetry::multiply_matrices(&adaptation, &matrix)
+    }
+
+    fn warnings(chunks: &[(&[u8; 4], &[u8])]) -> Vec<ColorWarning> {
+        let ihdr = testing::ihdr(1, 1, 8, 2);
+        let mut all = vec![(b"IHDR", &ihdr[..])];
+        all.extend_from_slice(chunks);
+        all.push((b"IEND", &[]));
+        check(&Png::from_bytes(&testing::png(&all)).unwrap())
+    }
+
+    #[test]
+    fn srgb_gamma() {
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"gAMA", &45455u32.to_be_bytes())]),
+            []
+        );
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"gAMA", &45454u32.to_be_bytes())]),
+            [ColorWarning::SrgbGamma { image_gama: 45454 }]
+        );
+    }
+
+    #[test]
+    fn srgb_chromaticities() {
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"cHRM", &chrm(&GAMUTS[0]))]),
+            []
+        );
+
+        // cHRM rounds to 5 decimal places, so these are 0.0009 & 0.0011 away
+        let inside = chrm(&srgb_with((0.6409, 0.33)));
+        assert_eq!(warnings(&[(b"sRGB", SRGB), (b"cHRM", &inside)]), []);
+        let outside = chrm(&srgb_with((0.6411, 0.33)));
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"cHRM", &outside)]),
+            [ColorWarning::SrgbChromaticities]
+        );
+
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"cHRM", &chrm(&GAMUTS[1]))]),
+            [ColorWarning::SrgbChromaticities]
+        );
+    }
+
+    #[test]
+    fn srgb_with_iccp() {
+        let iccp = iccp(2.2, &srgb_colorants());
+        assert_eq!(
+            warnings(&[(b"sRGB", SRGB), (b"iCCP", &iccp)]),
+            [ColorWarning::SrgbWithIccp]
+        );
+    }
+
+    #[test]
+    fn iccp_gamma() {
+        let iccp = iccp(2.0, &srgb_colorants());
+
+        // Within 5% of the curve's 2.0 either way
+        for display_exponent in [2.0, 2.09, 1.91] {
+            assert_eq!(
+                warnings(&[(b"gAMA", &gama(display_exponent)), (b"iCCP", &iccp)]),
+                []
+            );
+        }
+        for display_exponent in [2.11, 1.89] {
+            let found = warnings(&[(b"gAMA", &gama(display_exponent)), (b"iCCP", &iccp)]);
+            assert!(matches!(
+                found[..],
+                [ColorWarning::IccpGamma { display_exponent: d, profile_exponent: p }]
+                    if (d - display_exponent).abs() < 0.001 && p == 2.0
+            ));
+        }
+    }
+
+    #[test]
+    fn iccp_chromaticities() {
+        let chrm = chrm(&GAMUTS[0]);
+        let iccp_chunk = iccp(2.2, &srgb_colorants());
+        assert_eq!(warnings(&[(b"cHRM", &chrm), (b"iCCP", &iccp_chunk)]), []);
+
+        // Moving one colorant by just under & just over 0.01
+        let mut colorants = srgb_colorants();
+        colorants[0][1] += 0.0099;
+        let iccp_chunk = iccp(2.2, &colorants);
+        assert_eq!(warnings(&[(b"cHRM", &chrm), (b"iCCP", &iccp_chunk)]), []);
+
+        let mut colorants = srgb_colorants();
+        colorants[0][1] += 0.0101;
+        let iccp_chunk = iccp(2.2, &colorants);
+        assert!(matches!(
+            warnings(&[(b"cHRM", &chrm), (b"iCCP", &iccp_chunk)])[..],
+            [ColorWarning::IccpChromaticities { largest_difference }]
+                if (largest_difference - 0.0101).abs() < 0.0001
+        ));
+    }
+
+    #[test]
+    fn broken_profiles_are_skipped() {
+        let iccp = [
+            b"test\0\0".as_slice(),
+            &writer::zlib_stored(b"not a profile"),
+        ]
+        .concat();
+        assert_eq!(
+            warnings(&[
+                (b"gAMA", &gama(1.0)),
+                (b"cHRM", &chrm(&GAMUTS[2])),
+                (b"iCCP", &iccp)
+            ]),
+            []
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// CRC-32 as used by PNG chunks
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5CRC-algorithm
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#D-CRCAppendix
+
+// Table of CRCs of all 8-bit messages, built at compile time
+const CRC_TABLE: [u32; 256] = make_crc_table();
+
+const fn make_crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+
+        let mut k = 0;
+        while k < 8 {
+            if c & 1 == 1 {
+                c = 0xedb88320 ^ (c >> 1);
+            } else {
+                c >>= 1;
+            }
+            k += 1;
+        }
+
+        table[n] = c;
+        n += 1;
+    }
+
+    table
+}
+
+// Running CRC, for when the bytes being checked aren't in one slice (like a chunk's type & data).
+pub struct Crc {
+    crc: u32,
+}
+
+impl Crc {
+    pub fn new() -> Self {
+        Crc { crc: 0xffffffff }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.crc = CRC_TABLE[((self.crc ^ *byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        self.crc ^ 0xffffffff
+    }
+}
+
+impl Default for Crc {
+    fn default() -> Self {
+        Crc::new()
+    }
+}
+
+// The CRC of a chunk, calculated over the chunk type and chunk data (but not the length).
+pub fn chunk_crc(chunk_type: &[u8; 4], chunk_data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(chunk_data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iend_crc() {
+        assert_eq!(chunk_crc(b"IEND", &[]), 0xAE426082);
+    }
+
+    #[test]
+    fn check_value() {
+        // The standard CRC-32 check value, over the ASCII digits 1 to 9
+        let mut crc = Crc::new();
+        crc.update(b"12345");
+        crc.update(b"6789");
+        assert_eq!(crc.finish(), 0xCBF43926);
+    }
+}
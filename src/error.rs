@@ -0,0 +1,683 @@
+use std::fmt;
+
+use crate::exif::Ifd;
+use crate::parser::ChunkType;
+
+// Everything that can go wrong while decoding a PNG datastream.
+//
+// Offsets are counted in bytes from the start of the datastream (the first byte
+// of the signature is offset 0), and chunk indexes start at 0 for the chunk
+// directly after the signature.
+#[derive(Debug)]
+pub enum PngError {
+    // The first 8 bytes weren't the PNG signature. Holds whatever was read
+    // instead, which may be shorter than 8 bytes.
+    InvalidSignature {
+        found: Vec<u8>,
+    },
+
+    // The datastream ended part way through a chunk's length or type field.
+    TruncatedChunkHeader {
+        offset: u64,
+        chunk_index: usize,
+    },
+
+    // The datastream ended part way through a chunk's data or CRC field.
+    // `needed` counts the data and CRC bytes together.
+    TruncatedChunkData {
+        offset: u64,
+        chunk_index: usize,
+        chunk_type: [u8; 4],
+        needed: usize,
+        available: usize,
+    },
+
+    // The CRC stored after a chunk doesn't match the one computed over its
+    // type and data.
+    CrcMismatch {
+        offset: u64,
+        chunk_index: usize,
+        chunk_type: [u8; 4],
+        expected: u32,
+        actual: u32,
+    },
+
+    // A chunk length is larger than the 2^31 - 1 bytes the spec allows.
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#5Chunk-layout
+    OversizedLength {
+        offset: u64,
+        chunk_index: usize,
+        length: u32,
+    },
+
+    // A critical chunk (uppercase first letter) that this parser doesn't know how
+    // to handle. The spec doesn't allow decoding to carry on past it.
+    UnknownCriticalChunk {
+        offset: u64,
+        chunk_index: usize,
+        chunk_type: [u8; 4],
+    },
+
+    // A critical chunk's data couldn't be decoded. Problems in ancillary chunks
+    // don't stop decoding, they're kept on the chunk as `ParsedChunk::Invalid`.
+    InvalidChunk {
+        offset: u64,
+        chunk_index: usize,
+        chunk_type: [u8; 4],
+        error: ChunkError,
+    },
+
+    // Every PNG needs an IHDR chunk, but none was found.
+    MissingIhdr,
+
+    // The datastream ended cleanly between two chunks, but no IEND was seen.
+    UnexpectedEof {
+        offset: u64,
+        chunk_index: usize,
+    },
+
+    // The underlying reader failed.
+    Io {
+        offset: u64,
+        source: std::io::Error,
+    },
+}
+
+impl PngError {
+    // Where in the datastream the problem was found.
+    pub fn offset(&self) -> u64 {
+        match self {
+            PngError::InvalidSignature { .. } | PngError::MissingIhdr => 0,
+            PngError::TruncatedChunkHeader { offset, .. }
+            | PngError::TruncatedChunkData { offset, .. }
+            | PngError::CrcMismatch { offset, .. }
+            | PngError::OversizedLength { offset, .. }
+            | PngError::UnknownCriticalChunk { offset, .. }
+            | PngError::InvalidChunk { offset, .. }
+            | PngError::UnexpectedEof { offset, .. }
+            | PngError::Io { offset, .. } => *offset,
+        }
+    }
+
+    // The index of the chunk being read when the problem was found, if the
+    // problem happened inside the chunk stream.
+    pub fn chunk_index(&self) -> Option<usize> {
+        match self {
+            PngError::InvalidSignature { .. } | PngError::MissingIhdr | PngError::Io { .. } => None,
+            PngError::TruncatedChunkHeader { chunk_index, .. }
+            | PngError::TruncatedChunkData { chunk_index, .. }
+            | PngError::CrcMismatch { chunk_index, .. }
+            | PngError::OversizedLength { chunk_index, .. }
+            | PngError::UnknownCriticalChunk { chunk_index, .. }
+            | PngError::InvalidChunk { chunk_index, .. }
+            | PngError::UnexpectedEof { chunk_index, .. } => Some(*chunk_index),
+        }
+    }
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PngError::InvalidSignature { found } => {
+                write!(f, "the PNG file signature is incorrect (found {:?})", found)
+            }
+            PngError::TruncatedChunkHeader {
+                offset,
+                chunk_index,
+            } => write!(
+                f,
+                "chunk {} at byte {} is truncated, its length and type fields are incomplete",
+                chunk_index, offset
+            ),
+            PngError::TruncatedChunkData {
+                offset,
+                chunk_index,
+                chunk_type,
+                needed,
+                available,
+            } => write!(
+                f,
+                "{} chunk {} at byte {} is truncated, it needs {} more bytes of data and CRC but only {} are left",
+                ChunkType::from(*chunk_type),
+                chunk_index,
+                offset,
+                needed,
+                available
+            ),
+            PngError::CrcMismatch {
+                offset,
+                chunk_index,
+                chunk_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} chunk {} at byte {} has a bad CRC, expected {:#010x} but the chunk stores {:#010x}",
+                ChunkType::from(*chunk_type),
+                chunk_index,
+                offset,
+                expected,
+                actual
+            ),
+            PngError::OversizedLength {
+                offset,
+                chunk_index,
+                length,
+            } => write!(
+                f,
+                "chunk {} at byte {} has a length of {}, more than the 2^31 - 1 bytes allowed",
+                chunk_index, offset, length
+            ),
+            PngError::UnknownCriticalChunk {
+                offset,
+                chunk_index,
+                chunk_type,
+            } => write!(
+                f,
+                "{} chunk {} at byte {} is critical but isn't a known chunk type, so the image can't be decoded",
+                ChunkType::from(*chunk_type),
+                chunk_index,
+                offset
+            ),
+            PngError::InvalidChunk {
+                offset,
+                chunk_index,
+                chunk_type,
+                error,
+            } => write!(
+                f,
+                "{} chunk {} at byte {} is invalid: {}",
+                ChunkType::from(*chunk_type),
+                chunk_index,
+                offset,
+                error
+            ),
+            PngError::MissingIhdr => write!(f, "the datastream has no IHDR chunk"),
+            PngError::UnexpectedEof {
+                offset,
+                chunk_index,
+            } => write!(
+                f,
+                "the datastream ended at byte {} after {} chunks without an IEND chunk",
+                offset, chunk_index
+            ),
+            PngError::Io { offset, source } => {
+                write!(f, "error reading the PNG at byte {}: {}", offset, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PngError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PngError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Why a single chunk's data couldn't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkError {
+    // The data ended (at `offset`) before every field was read
+    UnexpectedEnd {
+        offset: usize,
+    },
+    // The data length isn't one this chunk type can have
+    InvalidLength {
+        length: usize,
+    },
+    // A keyword wasn't followed by the null byte separating it from the rest of the data
+    MissingNullSeparator,
+    // Keywords have to be 1-79 bytes long
+    InvalidKeywordLength {
+        length: usize,
+    },
+    // Keywords can only hold printable Latin-1, with no leading, trailing or repeated spaces
+    InvalidKeyword {
+        keyword: String,
+        reason: &'static str,
+    },
+    // iTXt language tags are ASCII letters, digits & hyphens
+    InvalidLanguageTag {
+        tag: String,
+    },
+    // Text that has to be UTF-8 (like iTXt's) isn't
+    InvalidUtf8 {
+        field: &'static str,
+    },
+    // tEXt's text can't hold null bytes, the only one is the keyword's separator
+    NullInText,
+    // The chunk depends on IHDR, which hasn't been seen yet
+    MissingIhdr,
+    // IHDR's color type isn't one of 0, 2, 3, 4 or 6
+    InvalidColorType {
+        color_type: u8,
+    },
+    // The chunk can't be used with IHDR's color type
+    NotAllowedForColorType {
+        color_type: u8,
+    },
+    // The chunk depends on PLTE, which hasn't been seen yet
+    MissingPlte,
+    // There are more entries than the chunk is allowed to have
+    TooManyEntries {
+        entries: usize,
+        max: usize,
+    },
+    // There has to be exactly one entry per pallet entry
+    EntryCountMismatch {
+        entries: usize,
+        expected: usize,
+    },
+    // The bit depth isn't allowed for IHDR's color type
+    InvalidBitDepth {
+        color_type: u8,
+        bit_depth: u8,
+    },
+    // An APNG frame doesn't fit inside the image
+    FrameOutsideImage {
+        x_offset: u32,
+        y_offset: u32,
+        width: u32,
+        height: u32,
+    },
+    // A number written out as text (like pCAL's parameters) isn't valid
+    InvalidNumber {
+        field: &'static str,
+        text: String,
+        reason: &'static str,
+    },
+    // eXIf data has to start with a TIFF header ("II" or "MM", then 42)
+    MissingTiffHeader,
+    // A field holds a value the spec doesn't allow
+    InvalidValue {
+        field: &'static str,
+        value: u32,
+    },
+    // Compressed data inside the chunk couldn't be decompressed
+    Decompress(DecompressError),
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkError::UnexpectedEnd { offset } => {
+                write!(f, "the data ended early, after {} bytes", offset)
+            }
+            ChunkError::InvalidLength { length } => {
+                write!(f, "a length of {} isn't allowed", length)
+            }
+            ChunkError::MissingNullSeparator => {
+                write!(f, "the keyword isn't followed by a null separator")
+            }
+            ChunkError::InvalidKeywordLength { length } => write!(
+                f,
+                "keywords have to be 1-79 bytes long, but this one is {}",
+                length
+            ),
+            ChunkError::InvalidKeyword { keyword, reason } => {
+                write!(f, "the keyword {:?} {}", keyword, reason)
+            }
+            ChunkError::InvalidLanguageTag { tag } => {
+                write!(
+                    f,
+                    "the language tag {:?} has characters that aren't allowed",
+                    tag
+                )
+            }
+            ChunkError::InvalidUtf8 { field } => write!(f, "the {} isn't valid UTF-8", field),
+            ChunkError::NullInText => write!(f, "the text has a null byte in it"),
+            ChunkError::MissingIhdr => write!(f, "it came before the IHDR chunk"),
+            ChunkError::InvalidColorType { color_type } => {
+                write!(f, "color type {} isn't defined", color_type)
+            }
+            ChunkError::NotAllowedForColorType { color_type } => {
+                write!(f, "it isn't allowed for color type {}", color_type)
+            }
+            ChunkError::MissingPlte => write!(f, "it came before the PLTE chunk"),
+            ChunkError::TooManyEntries { entries, max } => write!(
+                f,
+                "it has {} entries, but there can only be {}",
+                entries, max
+            ),
+            ChunkError::EntryCountMismatch { entries, expected } => write!(
+                f,
+                "it has {} entries, but it needs one for each of the {} pallet entries",
+                entries, expected
+            ),
+            ChunkError::InvalidBitDepth {
+                color_type,
+                bit_depth,
+            } => write!(
+                f,
+                "a bit depth of {} isn't allowed for color type {}",
+                bit_depth, color_type
+            ),
+            ChunkError::FrameOutsideImage {
+                x_offset,
+                y_offset,
+                width,
+                height,
+            } => write!(
+                f,
+                "a {} x {} frame at ({}, {}) doesn't fit inside the image",
+                width, height, x_offset, y_offset
+            ),
+            ChunkError::InvalidNumber {
+                field,
+                text,
+                reason,
+            } => write!(f, "{} {:?} {}", field, text, reason),
+            ChunkError::MissingTiffHeader => {
+                write!(f, "the data doesn't start with a TIFF header")
+            }
+            ChunkError::InvalidValue { field, value } => {
+                write!(f, "{} can't be {}", field, value)
+            }
+            ChunkError::Decompress(error) => write!(f, "couldn't decompress: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChunkError::Decompress(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecompressError> for ChunkError {
+    fn from(error: DecompressError) -> Self {
+        ChunkError::Decompress(error)
+    }
+}
+
+// Why an ICC profile couldn't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IccError {
+    // Too short for the 128 byte header & tag count
+    TooShort { length: usize },
+    // Bytes 36-39 aren't "acsp", so it's not an ICC profile
+    MissingSignature,
+    // The header says the profile is longer than it is
+    Truncated { declared: u32, actual: usize },
+    // The tag table runs past the end of the profile
+    TagTableOutOfBounds { count: u32 },
+}
+
+impl fmt::Display for IccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IccError::TooShort { length } => {
+                write!(f, "it's {} bytes, too short for the profile header", length)
+            }
+            IccError::MissingSignature => write!(f, "it doesn't have the \"acsp\" signature"),
+            IccError::Truncated { declared, actual } => write!(
+                f,
+                "the header says it's {} bytes, but there are only {}",
+                declared, actual
+            ),
+            IccError::TagTableOutOfBounds { count } => write!(
+                f,
+                "the table of {} tags runs past the end of the profile",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IccError {}
+
+// Why an eXIf chunk's TIFF structure couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExifError {
+    // The data doesn't start with "II" or "MM", then 42 & the offset to IFD0
+    MissingTiffHeader,
+    // An IFD, or its table of entries, runs past the end of the data
+    IfdOutOfBounds { ifd: Ifd, offset: u32 },
+}
+
+impl fmt::Display for ExifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExifError::MissingTiffHeader => write!(f, "it doesn't start with a TIFF header"),
+            ExifError::IfdOutOfBounds { ifd, offset } => write!(
+                f,
+                "the {} at byte {} runs past the end of the data",
+                ifd, offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExifError {}
+
+// Why zlib/DEFLATE data couldn't be decompressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressError {
+    // The compressed data stopped part way through
+    UnexpectedEnd,
+    // The zlib header check bits are wrong
+    InvalidHeader,
+    // The zlib header asks for something other than DEFLATE with a window of up to 32K
+    UnsupportedMethod { cmf: u8 },
+    // PNG doesn't allow zlib streams that need a preset dictionary
+    PresetDictionary,
+    // Block type 3 is reserved
+    InvalidBlockType,
+    // A stored block's length doesn't match its ones' complement
+    StoredLengthMismatch,
+    // A dynamic block's Huffman code can't be built, or a code that isn't in it was read
+    InvalidHuffmanCode,
+    // A literal/length or distance symbol outside the ones DEFLATE defines
+    InvalidSymbol,
+    // A back reference points to before the start of the output
+    DistanceTooFar,
+    // The Adler-32 after the data doesn't match the decompressed bytes
+    ChecksumMismatch { expected: u32, actual: u32 },
+    // The data decompresses to more than it should
+    TooLarge { limit: usize },
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressError::UnexpectedEnd => write!(f, "the compressed data ended early"),
+            DecompressError::InvalidHeader => write!(f, "the zlib header is invalid"),
+            DecompressError::UnsupportedMethod { cmf } => {
+                write!(
+                    f,
+                    "zlib compression method byte {:#04x} isn't supported",
+                    cmf
+                )
+            }
+            DecompressError::PresetDictionary => {
+                write!(f, "the zlib stream needs a preset dictionary")
+            }
+            DecompressError::InvalidBlockType => write!(f, "a block uses the reserved type 3"),
+            DecompressError::StoredLengthMismatch => {
+                write!(f, "a stored block's length doesn't match its complement")
+            }
+            DecompressError::InvalidHuffmanCode => write!(f, "a Huffman code is invalid"),
+            DecompressError::InvalidSymbol => write!(f, "an invalid symbol was decoded"),
+            DecompressError::DistanceTooFar => {
+                write!(f, "a back reference goes past the start of the data")
+            }
+            DecompressError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "the Adler-32 checksum is wrong, expected {:#010x} but the stream stores {:#010x}",
+                expected, actual
+            ),
+            DecompressError::TooLarge { limit } => {
+                write!(
+                    f,
+                    "it decompresses to more than the {} bytes allowed",
+                    limit
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
+
+// Why the image data couldn't be turned into pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    // The IDAT data couldn't be decompressed
+    Decompress(DecompressError),
+    // There's less data than the image's size needs
+    NotEnoughData {
+        expected: usize,
+        actual: usize,
+    },
+    // Filter types go from 0 to 4
+    InvalidFilterType {
+        row: usize,
+        filter_type: u8,
+    },
+    // Same as above, for a row of one of the Adam7 passes
+    InvalidPassFilterType {
+        pass: u8,
+        row: usize,
+        filter_type: u8,
+    },
+    // The image is indexed but has no PLTE chunk
+    MissingPalette,
+    // A pixel points to a pallet entry that doesn't exist
+    PaletteIndexOutOfRange {
+        index: u8,
+        pallet_length: usize,
+    },
+    // The image's size doesn't fit in memory at all
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Decompress(error) => write!(f, "couldn't decompress: {}", error),
+            ImageError::NotEnoughData { expected, actual } => write!(
+                f,
+                "the image needs {} bytes of data, but there are only {}",
+                expected, actual
+            ),
+            ImageError::InvalidFilterType { row, filter_type } => {
+                write!(
+                    f,
+                    "row {} uses filter type {}, which isn't defined",
+                    row, filter_type
+                )
+            }
+            ImageError::InvalidPassFilterType {
+                pass,
+                row,
+                filter_type,
+            } => write!(
+                f,
+                "row {} of Adam7 pass {} uses filter type {}, which isn't defined",
+                row, pass, filter_type
+            ),
+            ImageError::MissingPalette => write!(f, "the image is indexed but has no PLTE chunk"),
+            ImageError::PaletteIndexOutOfRange {
+                index,
+                pallet_length,
+            } => write!(
+                f,
+                "a pixel uses pallet index {}, but the pallet only has {} entries",
+                index, pallet_length
+            ),
+            ImageError::TooLarge { width, height } => {
+                write!(f, "a {} x {} image is too big to decode", width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Decompress(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecompressError> for ImageError {
+    fn from(error: DecompressError) -> Self {
+        ImageError::Decompress(error)
+    }
+}
+
+// Why an APNG's frames couldn't be put together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationError {
+    // There's no acTL chunk
+    NotAnimated,
+    // fcTL & fdAT sequence numbers have to count up from 0 without gaps
+    SequenceNumber {
+        expected: u32,
+        found: u32,
+    },
+    // An fcTL or fdAT chunk's data isn't valid
+    InvalidChunk {
+        chunk_type: ChunkType,
+        error: ChunkError,
+    },
+    // An fdAT chunk that doesn't belong to a frame, either before the first fcTL
+    // or after the fcTL for the IDAT image
+    UnexpectedFrameData {
+        sequence_number: u32,
+    },
+    // A frame's fcTL isn't followed by any image data
+    MissingFrameData {
+        frame: usize,
+    },
+    // A frame's image data couldn't be turned into pixels
+    Image {
+        frame: usize,
+        error: ImageError,
+    },
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::NotAnimated => write!(f, "the image isn't animated, it has no acTL"),
+            AnimationError::SequenceNumber { expected, found } => write!(
+                f,
+                "expected sequence number {}, but found {}",
+                expected, found
+            ),
+            AnimationError::InvalidChunk { chunk_type, error } => {
+                write!(f, "{} is invalid: {}", chunk_type, error)
+            }
+            AnimationError::UnexpectedFrameData { sequence_number } => write!(
+                f,
+                "the fdAT with sequence number {} doesn't belong to a frame",
+                sequence_number
+            ),
+            AnimationError::MissingFrameData { frame } => {
+                write!(f, "frame {} has no image data", frame)
+            }
+            AnimationError::Image { frame, error } => write!(f, "frame {}: {}", frame, error),
+        }
+    }
+}
+
+impl std::error::Error for AnimationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AnimationError::InvalidChunk { error, .. } => Some(error),
+            AnimationError::Image { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,831 @@
+// Decodes the TIFF structure eXIf holds: the byte order, IFD0 (the main image's
+// tags) and the Exif & GPS IFDs it points to. Only the common tags get names, the
+// rest are still listed by number.
+// TIFF: https://www.itu.int/itudoc/itu-t/com16/tiff-fx/docs/tiff6.pdf
+// Exif: https://www.cipa.jp/std/documents/download_e.html?CIPA_DC-008-2023-E
+
+use std::fmt;
+
+use crate::error::ExifError;
+
+// Tags in IFD0 that point to the other IFDs
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const GPS_IFD_POINTER: u16 = 0x8825;
+
+pub const MAKE: u16 = 0x010f;
+pub const MODEL: u16 = 0x0110;
+pub const ORIENTATION: u16 = 0x0112;
+pub const DATE_TIME: u16 = 0x0132;
+pub const EXPOSURE_TIME: u16 = 0x829a;
+pub const DATE_TIME_ORIGINAL: u16 = 0x9003;
+pub const GPS_LATITUDE_REF: u16 = 0x0001;
+pub const GPS_LATITUDE: u16 = 0x0002;
+pub const GPS_LONGITUDE_REF: u16 = 0x0003;
+pub const GPS_LONGITUDE: u16 = 0x0004;
+pub const GPS_ALTITUDE_REF: u16 = 0x0005;
+pub const GPS_ALTITUDE: u16 = 0x0006;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    // "II", Intel
+    LittleEndian,
+    // "MM", Motorola
+    BigEndian,
+}
+
+// Which IFD a field is in, tag numbers only mean something within their IFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ifd {
+    Primary,
+    Exif,
+    Gps,
+}
+
+// A field's values, by TIFF type. Every type can hold a list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(Vec<u8>),
+    // Null terminated, the terminator isn't kept
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    // Numerator, denominator
+    Rational(Vec<(u32, u32)>),
+    SByte(Vec<i8>),
+    Undefined(Vec<u8>),
+    SShort(Vec<i16>),
+    SLong(Vec<i32>),
+    SRational(Vec<(i32, i32)>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub ifd: Ifd,
+    pub tag: u16,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exif {
+    pub byte_order: ByteOrder,
+    // In the order they're stored, IFD0 first
+    pub fields: Vec<Field>,
+}
+
+// Reads numbers in the data's byte order, None when they're past the end
+struct TiffReader<'a> {
+    data: &'a [u8],
+    byte_order: ByteOrder,
+}
+
+impl TiffReader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data
+            .get(offset..offset.checked_add(N)?)?
+            .try_into()
+            .ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes(offset)?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes(offset)?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let bytes = self.bytes(offset)?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u64::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u64::from_be_bytes(bytes),
+        })
+    }
+
+    // `count` values of the type, starting at `offset`
+    fn value(&self, field_type: u16, count: usize, offset: usize) -> Option<Value> {
+        let values = |size: usize| (0..count).map(move |index| offset + index * size);
+
+        Some(match field_type {
+            1 => Value::Byte(self.data.get(offset..offset + count)?.to_vec()),
+            2 => {
+                let bytes = self.data.get(offset..offset + count)?;
+                let text = bytes.split(|byte| *byte == 0).next().unwrap_or_default();
+                Value::Ascii(String::from_utf8_lossy(text).into_owned())
+            }
+            3 => Value::Short(values(2).map(|at| self.u16(at)).collect::<Option<_>>()?),
+            4 => Value::Long(values(4).map(|at| self.u32(at)).collect::<Option<_>>()?),
+            5 => Value::Rational(
+                values(8)
+                    .map(|at| Some((self.u32(at)?, self.u32(at + 4)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            6 => Value::SByte(
+                self.data
+                    .get(offset..offset + count)?
+                    .iter()
+                    .map(|byte| *byte as i8)
+                    .collect(),
+            ),
+            7 => Value::Undefined(self.data.get(offset..offset + count)?.to_vec()),
+            8 => Value::SShort(
+                values(2)
+                    .map(|at| Some(self.u16(at)? as i16))
+                    .collect::<Option<_>>()?,
+            ),
+            9 => Value::SLong(
+                values(4)
+                    .map(|at| Some(self.u32(at)? as i32))
+                    .collect::<Option<_>>()?,
+            ),
+            10 => Value::SRational(
+                values(8)
+                    .map(|at| Some((self.u32(at)? as i32, self.u32(at + 4)? as i32)))
+                    .collect::<Option<_>>()?,
+            ),
+            11 => Value::Float(
+                values(4)
+                    .map(|at| Some(f32::from_bits(self.u32(at)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            12 => Value::Double(
+                values(8)
+                    .map(|at| Some(f64::from_bits(self.u64(at)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        })
+    }
+}
+
+// Bytes per value for each TIFF type
+fn type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+impl Exif {
+    pub fn parse(data: &[u8]) -> Result<Exif, ExifError> {
+        let byte_order = match data.get(..4) {
+            Some(b"II\x2a\x00") => ByteOrder::LittleEndian,
+            Some(b"MM\x00\x2a") => ByteOrder::BigEndian,
+            _ => return Err(ExifError::MissingTiffHeader),
+        };
+        let reader = TiffReader { data, byte_order };
+        let mut exif = Exif {
+            byte_order,
+            fields: Vec::new(),
+        };
+
+        let ifd0_offset = reader.u32(4).ok_or(ExifError::MissingTiffHeader)?;
+        exif.read_ifd(&reader, Ifd::Primary, ifd0_offset)?;
+
+        // Only followed from IFD0, so a bad pointer can't send us round in circles
+        let pointers = [(EXIF_IFD_POINTER, Ifd::Exif), (GPS_IFD_POINTER, Ifd::Gps)];
+        for (tag, ifd) in pointers {
+            if let Some(Value::Long(offset)) = exif.field(Ifd::Primary, tag) {
+                if let Some(offset) = offset.first() {
+                    exif.read_ifd(&reader, ifd, *offset)?;
+                }
+            }
+        }
+
+        Ok(exif)
+    }
+
+    // A 2 byte entry count, then 12 bytes per entry: tag, type, count & the value
+    // (or the offset to it, when it doesn't fit in 4 bytes)
+    fn read_ifd(&mut self, reader: &TiffReader, ifd: Ifd, offset: u32) -> Result<(), ExifError> {
+        let out_of_bounds = ExifError::IfdOutOfBounds { ifd, offset };
+        let start = offset as usize;
+        let count = reader.u16(start).ok_or(out_of_bounds.clone())? as usize;
+        if start + 2 + count * 12 > reader.data.len() {
+            return Err(out_of_bounds);
+        }
+
+        for index in 0..count {
+            let entry = start + 2 + index * 12;
+            let tag = reader.u16(entry).ok_or(out_of_bounds.clone())?;
+            let field_type = reader.u16(entry + 2).ok_or(out_of_bounds.clone())?;
+            let value_count = reader.u32(entry + 4).ok_or(out_of_bounds.clone())? as usize;
+
+            // Unknown types & values past the end of the data are skipped, camera
+            // maker notes often have broken offsets
+            let Some(size) = type_size(field_type).and_then(|size| size.checked_mul(value_count))
+            else {
+                continue;
+            };
+            let value_offset = match size {
+                0..=4 => entry + 8,
+                _ => reader.u32(entry + 8).ok_or(out_of_bounds.clone())? as usize,
+            };
+            if value_offset
+                .checked_add(size)
+                .is_none_or(|end| end > reader.data.len())
+            {
+                continue;
+            }
+
+            if let Some(value) = reader.value(field_type, value_count, value_offset) {
+                self.fields.push(Field { ifd, tag, value });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn field(&self, ifd: Ifd, tag: u16) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|field| field.ifd == ifd && field.tag == tag)
+            .map(|field| &field.value)
+    }
+
+    fn text(&self, ifd: Ifd, tag: u16) -> Option<&str> {
+        match self.field(ifd, tag)? {
+            Value::Ascii(text) => Some(text.trim()),
+            _ => None,
+        }
+    }
+
+    // The first rational of a field, as a fraction
+    fn rational(&self, ifd: Ifd, tag: u16) -> Option<(u32, u32)> {
+        match self.field(ifd, tag)? {
+            Value::Rational(values) => values.first().copied(),
+            _ => None,
+        }
+    }
+
+    pub fn make(&self) -> Option<&str> {
+        self.text(Ifd::Primary, MAKE)
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.text(Ifd::Primary, MODEL)
+    }
+
+    // When the file was last changed, like "2024:05:01 13:45:00" (local time)
+    pub fn date_time(&self) -> Option<&str> {
+        self.text(Ifd::Primary, DATE_TIME)
+    }
+
+    // When the photo was taken
+    pub fn date_time_original(&self) -> Option<&str> {
+        self.text(Ifd::Exif, DATE_TIME_ORIGINAL)
+    }
+
+    // 1 to 8, how the image has to be rotated & flipped to display it the right way up
+    pub fn orientation(&self) -> Option<u16> {
+        match self.field(Ifd::Primary, ORIENTATION)? {
+            Value::Short(values) => values.first().copied(),
+            _ => None,
+        }
+    }
+
+    // In seconds, as a fraction
+    pub fn exposure_time(&self) -> Option<(u32, u32)> {
+        self.rational(Ifd::Exif, EXPOSURE_TIME)
+    }
+
+    // Latitude & longitude in degrees, negative for south & west
+    pub fn gps_position(&self) -> Option<(f64, f64)> {
+        let coordinate = |tag, reference_tag, negative: &str| {
+            let degrees = match self.field(Ifd::Gps, tag)? {
+                Value::Rational(values) if values.len() == 3 => values
+                    .iter()
+                    .zip([1.0, 60.0, 3600.0])
+                    .map(|(&(numerator, denominator), scale)| match denominator {
+                        0 => None,
+                        _ => Some(numerator as f64 / denominator as f64 / scale),
+                    })
+                    .sum::<Option<f64>>()?,
+                _ => return None,
+            };
+            match self.text(Ifd::Gps, reference_tag) {
+                Some(reference) if reference == negative => Some(-degrees),
+                _ => Some(degrees),
+            }
+        };
+
+        Some((
+            coordinate(GPS_LATITUDE, GPS_LATITUDE_REF, "S")?,
+            coordinate(GPS_LONGITUDE, GPS_LONGITUDE_REF, "W")?,
+        ))
+    }
+
+    // In metres, negative when below sea level
+    pub fn gps_altitude(&self) -> Option<f64> {
+        let (numerator, denominator) = self.rational(Ifd::Gps, GPS_ALTITUDE)?;
+        if denominator == 0 {
+            return None;
+        }
+        let altitude = numerator as f64 / denominator as f64;
+
+        match self.field(Ifd::Gps, GPS_ALTITUDE_REF) {
+            Some(Value::Byte(reference)) if reference.first() == Some(&1) => Some(-altitude),
+            _ => Some(altitude),
+        }
+    }
+
+    // What each orientation value means, as the transform to display the image
+    pub fn orientation_name(orientation: u16) -> &'static str {
+        match orientation {
+            1 => "Normal",
+            2 => "Mirror horizontal",
+            3 => "Rotate 180°",
+            4 => "Mirror vertical",
+            5 => "Mirror horizontal & rotate 270° clockwise",
+            6 => "Rotate 90° clockwise",
+            7 => "Mirror horizontal & rotate 90° clockwise",
+            8 => "Rotate 270° clockwise",
+            _ => "Unknown",
+        }
+    }
+}
+
+// Names for the common tags, per IFD
+pub fn tag_name(ifd: Ifd, tag: u16) -> Option<&'static str> {
+    Some(match (ifd, tag) {
+        (Ifd::Primary | Ifd::Exif, tag) => match tag {
+            0x010e => "ImageDescription",
+            MAKE => "Make",
+            MODEL => "Model",
+            ORIENTATION => "Orientation",
+            0x011a => "XResolution",
+            0x011b => "YResolution",
+            0x0128 => "ResolutionUnit",
+            0x0131 => "Software",
+            DATE_TIME => "DateTime",
+            0x013b => "Artist",
+            0x0213 => "YCbCrPositioning",
+            0x8298 => "Copyright",
+            EXIF_IFD_POINTER => "ExifIFDPointer",
+            GPS_IFD_POINTER => "GPSInfoIFDPointer",
+            EXPOSURE_TIME => "ExposureTime",
+            0x829d => "FNumber",
+            0x8822 => "ExposureProgram",
+            0x8827 => "PhotographicSensitivity",
+            0x9000 => "ExifVersion",
+            DATE_TIME_ORIGINAL => "DateTimeOriginal",
+            0x9004 => "DateTimeDigitized",
+            0x9010 => "OffsetTime",
+            0x9011 => "OffsetTimeOriginal",
+            0x9201 => "ShutterSpeedValue",
+            0x9202 => "ApertureValue",
+            0x9204 => "ExposureBiasValue",
+            0x9207 => "MeteringMode",
+            0x9209 => "Flash",
+            0x920a => "FocalLength",
+            0x927c => "MakerNote",
+            0x9286 => "UserComment",
+            0xa000 => "FlashpixVersion",
+            0xa001 => "ColorSpace",
+            0xa002 => "PixelXDimension",
+            0xa003 => "PixelYDimension",
+            0xa005 => "InteroperabilityIFDPointer",
+            0xa402 => "ExposureMode",
+            0xa403 => "WhiteBalance",
+            0xa405 => "FocalLengthIn35mmFilm",
+            0xa431 => "BodySerialNumber",
+            0xa433 => "LensMake",
+            0xa434 => "LensModel",
+            _ => return None,
+        },
+        (Ifd::Gps, tag) => match tag {
+            0x0000 => "GPSVersionID",
+            GPS_LATITUDE_REF => "GPSLatitudeRef",
+            GPS_LATITUDE => "GPSLatitude",
+            GPS_LONGITUDE_REF => "GPSLongitudeRef",
+            GPS_LONGITUDE => "GPSLongitude",
+            GPS_ALTITUDE_REF => "GPSAltitudeRef",
+            GPS_ALTITUDE => "GPSAltitude",
+            0x0007 => "GPSTimeStamp",
+            0x0010 => "GPSImgDirectionRef",
+            0x0011 => "GPSImgDirection",
+            0x0012 => "GPSMapDatum",
+            0x001d => "GPSDateStamp",
+            _ => return None,
+        },
+    })
+}
+
+impl fmt::Display for Ifd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ifd::Primary => write!(f, "IFD0"),
+            Ifd::Exif => write!(f, "Exif IFD"),
+            Ifd::Gps => write!(f, "GPS IFD"),
+        }
+    }
+}
+
+// Lists are comma separated, long byte strings are only counted
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T>(
+            f: &mut fmt::Formatter<'_>,
+            values: &[T],
+            write_value: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write_value(f, value)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Value::Ascii(text) => write!(f, "{:?}", text),
+            Value::Byte(bytes) | Value::Undefined(bytes) => {
+                // Version fields like ExifVersion are ASCII digits stored as undefined
+                if bytes.len() <= 8 && bytes.iter().all(|byte| byte.is_ascii_graphic()) {
+                    write!(f, "{:?}", String::from_utf8_lossy(bytes))
+                } else if bytes.len() <= 8 {
+                    list(f, bytes, |f, byte| write!(f, "{}", byte))
+                } else {
+                    write!(f, "{} bytes", bytes.len())
+                }
+            }
+            Value::Short(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::Long(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::Rational(values) => list(f, values, |f, (numerator, denominator)| {
+                write!(f, "{}/{}", numerator, denominator)
+            }),
+            Value::SByte(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::SShort(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::SLong(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::SRational(values) => list(f, values, |f, (numerator, denominator)| {
+                write!(f, "{}/{}", numerator, denominator)
+            }),
+            Value::Float(values) => list(f, values, |f, value| write!(f, "{}", value)),
+            Value::Double(values) => list(f, values, |f, value| write!(f, "{}", value)),
+        }
+    }
+}
+
+// The common tags, then (with `{:#}`) every field. Nested under the eXIf chunk.
+impl fmt::Display for Exif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texts = [
+            ("make", self.make()),
+            ("model", self.model()),
+            ("date/time", self.date_time()),
+            ("date/time original", self.date_time_original()),
+        ];
+        for (name, text) in texts {
+            if let Some(text) = text {
+                writeln!(f, "  {}: {}", name, text)?;
+            }
+        }
+
+        if let Some(orientation) = self.orientation() {
+            if f.alternate() {
+                writeln!(
+                    f,
+                    "  orientation: {} ({}) | How to turn the image so it's the right way up",
+                    Exif::orientation_name(orientation),
+                    orientation
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "  orientation: {} ({})",
+                    Exif::orientation_name(orientation),
+                    orientation
+                )?;
+            }
+        }
+
+        if let Some((numerator, denominator)) = self.exposure_time() {
+            writeln!(f, "  exposure time: {}/{} s", numerator, denominator)?;
+        }
+
+        if let Some((latitude, longitude)) = self.gps_position() {
+            let north_south = if latitude < 0.0 { "S" } else { "N" };
+            let east_west = if longitude < 0.0 { "W" } else { "E" };
+            if f.alternate() {
+                writeln!(
+                    f,
+                    "  GPS position: {:.6}° {}, {:.6}° {} | Where the image was made",
+                    latitude.abs(),
+                    north_south,
+                    longitude.abs(),
+                    east_west
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "  GPS position: {:.6}° {}, {:.6}° {}",
+                    latitude.abs(),
+                    north_south,
+                    longitude.abs(),
+                    east_west
+                )?;
+            }
+        }
+        if let Some(altitude) = self.gps_altitude() {
+            writeln!(f, "  GPS altitude: {:.1} m", altitude)?;
+        }
+
+        if f.alternate() {
+            writeln!(f, "  fields: {}", self.fields.len())?;
+            for field in &self.fields {
+                write!(f, "   {} {:#06x}", field.ifd, field.tag)?;
+                if let Some(name) = tag_name(field.ifd, field.tag) {
+                    write!(f, " {}", name)?;
+                }
+                writeln!(f, ": {}", field.value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: u16 = 2;
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const RATIONAL: u16 = 5;
+
+    // (tag, type, count, the value's bytes)
+    type Entry = (u16, u16, u32, Vec<u8>);
+
+    // Builds TIFF data in either byte order, IFD by IFD
+    struct Tiff {
+        byte_order: ByteOrder,
+        data: Vec<u8>,
+    }
+
+    impl Tiff {
+        // The header, with IFD0's offset filled in by `finish`
+        fn new(byte_order: ByteOrder) -> Tiff {
+            let header = match byte_order {
+                ByteOrder::LittleEndian => b"II\x2a\x00",
+                ByteOrder::BigEndian => b"MM\x00\x2a",
+            };
+            Tiff {
+                byte_order,
+                data: [&header[..], &[0; 4]].concat(),
+            }
+        }
+
+        fn u16s(&self, values: &[u16]) -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| match self.byte_order {
+                    ByteOrder::LittleEndian => value.to_le_bytes(),
+                    ByteOrder::BigEndian => value.to_be_bytes(),
+                })
+                .collect()
+        }
+
+        fn u32s(&self, values: &[u32]) -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| match self.byte_order {
+                    ByteOrder::LittleEndian => value.to_le_bytes(),
+                    ByteOrder::BigEndian => value.to_be_bytes(),
+                })
+                .collect()
+        }
+
+        fn rationals(&self, values: &[(u32, u32)]) -> Vec<u8> {
+            let flat: Vec<u32> = values.iter().flat_map(|(n, d)| [*n, *d]).collect();
+            self.u32s(&flat)
+        }
+
+        // Adds an IFD with the values that don't fit in 4 bytes straight after it,
+        // returning where it starts
+        fn ifd(&mut self, entries: &[Entry]) -> u32 {
+            let start = self.data.len();
+            let mut values: Vec<u8> = Vec::new();
+            let values_start = start + 2 + entries.len() * 12 + 4;
+
+            self.data.extend(self.u16s(&[entries.len() as u16]));
+            for (tag, field_type, count, value) in entries {
+                self.data.extend(self.u16s(&[*tag, *field_type]));
+                self.data.extend(self.u32s(&[*count]));
+                if value.len() <= 4 {
+                    let mut inline = value.clone();
+                    inline.resize(4, 0);
+                    self.data.extend(inline);
+                } else {
+                    self.data
+                        .extend(self.u32s(&[(values_start + values.len()) as u32]));
+                    values.extend(value);
+                }
+            }
+            // No next IFD
+            self.data.extend([0; 4]);
+            self.data.extend(values);
+
+            start as u32
+        }
+
+        fn finish(mut self, ifd0: u32) -> Vec<u8> {
+            let offset = self.u32s(&[ifd0]);
+            self.data[4..8].copy_from_slice(&offset);
+            self.data
+        }
+    }
+
+    // A photo with IFD0, the Exif IFD & the GPS IFD, the last two before IFD0
+    fn photo(byte_order: ByteOrder) -> Vec<u8> {
+        let mut tiff = Tiff::new(byte_order);
+
+        let exif_entries = [
+            (EXPOSURE_TIME, RATIONAL, 1, tiff.rationals(&[(1, 250)])),
+            (
+                DATE_TIME_ORIGINAL,
+                ASCII,
+                20,
+                b"2024:05:01 13:45:00\0".to_vec(),
+            ),
+        ];
+        let exif_ifd = tiff.ifd(&exif_entries);
+
+        let gps_entries = [
+            (GPS_LATITUDE_REF, ASCII, 2, b"N\0".to_vec()),
+            (
+                GPS_LATITUDE,
+                RATIONAL,
+                3,
+                tiff.rationals(&[(51, 1), (30, 1), (0, 1)]),
+            ),
+            (GPS_LONGITUDE_REF, ASCII, 2, b"W\0".to_vec()),
+            (
+                GPS_LONGITUDE,
+                RATIONAL,
+                3,
+                tiff.rationals(&[(0, 1), (7, 1), (3900, 100)]),
+            ),
+            (GPS_ALTITUDE_REF, 1, 1, vec![1]),
+            (GPS_ALTITUDE, RATIONAL, 1, tiff.rationals(&[(255, 10)])),
+        ];
+        let gps_ifd = tiff.ifd(&gps_entries);
+
+        let ifd0_entries = [
+            (MAKE, ASCII, 6, b"Canon\0".to_vec()),
+            (ORIENTATION, SHORT, 1, tiff.u16s(&[6])),
+            (EXIF_IFD_POINTER, LONG, 1, tiff.u32s(&[exif_ifd])),
+            (GPS_IFD_POINTER, LONG, 1, tiff.u32s(&[gps_ifd])),
+        ];
+        let ifd0 = tiff.ifd(&ifd0_entries);
+
+        tiff.finish(ifd0)
+    }
+
+    #[test]
+    fn both_byte_orders() {
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let exif = Exif::parse(&photo(byte_order)).unwrap();
+
+            assert_eq!(exif.byte_order, byte_order);
+            assert_eq!(exif.make(), Some("Canon"));
+            assert_eq!(exif.orientation(), Some(6));
+            assert_eq!(exif.exposure_time(), Some((1, 250)));
+            assert_eq!(exif.date_time_original(), Some("2024:05:01 13:45:00"));
+            assert_eq!(exif.fields.len(), 12);
+            assert_eq!(exif.fields[0].ifd, Ifd::Primary);
+        }
+    }
+
+    #[test]
+    fn missing_header() {
+        assert_eq!(
+            Exif::parse(b"II\x00\x2a\x08\x00\x00\x00"),
+            Err(ExifError::MissingTiffHeader)
+        );
+        // The header's there, but not IFD0's offset
+        assert_eq!(
+            Exif::parse(b"MM\x00\x2a\x00"),
+            Err(ExifError::MissingTiffHeader)
+        );
+    }
+
+    #[test]
+    fn inline_and_offset_values() {
+        let mut tiff = Tiff::new(ByteOrder::BigEndian);
+        let entries = [
+            // 4 bytes or less are stored in the entry itself
+            (0x0131, ASCII, 4, b"abc\0".to_vec()),
+            (0x0001, SHORT, 2, tiff.u16s(&[1, 2])),
+            (0x0002, LONG, 1, tiff.u32s(&[0xdead_beef])),
+            // Anything longer is at an offset
+            (0x0003, SHORT, 3, tiff.u16s(&[3, 4, 5])),
+            (0x0004, ASCII, 6, b"abcde\0".to_vec()),
+            (0x0005, 10, 1, tiff.u32s(&[-3i32 as u32, 4])),
+        ];
+        let ifd0 = tiff.ifd(&entries);
+        let exif = Exif::parse(&tiff.finish(ifd0)).unwrap();
+
+        let value = |tag| exif.field(Ifd::Primary, tag).unwrap().clone();
+        assert_eq!(value(0x0131), Value::Ascii("abc".to_string()));
+        assert_eq!(value(0x0001), Value::Short(vec![1, 2]));
+        assert_eq!(value(0x0002), Value::Long(vec![0xdead_beef]));
+        assert_eq!(value(0x0003), Value::Short(vec![3, 4, 5]));
+        assert_eq!(value(0x0004), Value::Ascii("abcde".to_string()));
+        assert_eq!(value(0x0005), Value::SRational(vec![(-3, 4)]));
+    }
+
+    #[test]
+    fn gps_ifd() {
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let exif = Exif::parse(&photo(byte_order)).unwrap();
+            let (latitude, longitude) = exif.gps_position().unwrap();
+
+            assert_eq!(latitude, 51.5);
+            assert!((longitude + 0.1275).abs() < 1e-9);
+            // Reference 1 is below sea level
+            assert_eq!(exif.gps_altitude(), Some(-25.5));
+
+            // GPS tag numbers overlap IFD0's, so they're kept apart
+            assert_eq!(exif.field(Ifd::Primary, GPS_LATITUDE), None);
+            assert_eq!(tag_name(Ifd::Gps, GPS_LATITUDE), Some("GPSLatitude"));
+        }
+    }
+
+    #[test]
+    fn ifd_offsets_out_of_range() {
+        let tiff = Tiff::new(ByteOrder::LittleEndian);
+        assert_eq!(
+            Exif::parse(&tiff.finish(1000)),
+            Err(ExifError::IfdOutOfBounds {
+                ifd: Ifd::Primary,
+                offset: 1000
+            })
+        );
+
+        let mut tiff = Tiff::new(ByteOrder::LittleEndian);
+        let entries = [(GPS_IFD_POINTER, LONG, 1, tiff.u32s(&[u32::MAX]))];
+        let ifd0 = tiff.ifd(&entries);
+        assert_eq!(
+            Exif::parse(&tiff.finish(ifd0)),
+            Err(ExifError::IfdOutOfBounds {
+                ifd: Ifd::Gps,
+                offset: u32::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn ifd_entries_past_the_end() {
+        let mut tiff = Tiff::new(ByteOrder::BigEndian);
+        let entries = [(ORIENTATION, SHORT, 1, tiff.u16s(&[1]))];
+        let ifd0 = tiff.ifd(&entries);
+        let mut data = tiff.finish(ifd0);
+        // Says there are 2 entries, but only 1 (& the next IFD offset) is there
+        data[9] = 2;
+        data.truncate(data.len() - 4);
+
+        assert_eq!(
+            Exif::parse(&data),
+            Err(ExifError::IfdOutOfBounds {
+                ifd: Ifd::Primary,
+                offset: 8
+            })
+        );
+    }
+
+    #[test]
+    fn bad_values_are_skipped() {
+        let mut tiff = Tiff::new(ByteOrder::LittleEndian);
+        let entries = [
+            // An offset past the end
+            (MAKE, ASCII, 100, tiff.u32s(&[5000])),
+            // More values than there could be room for
+            (MODEL, LONG, u32::MAX, tiff.u32s(&[8])),
+            // Type 13 isn't defined
+            (0x0131, 13, 1, vec![0; 4]),
+            (ORIENTATION, SHORT, 1, tiff.u16s(&[3])),
+        ];
+        let ifd0 = tiff.ifd(&entries);
+        let exif = Exif::parse(&tiff.finish(ifd0)).unwrap();
+
+        assert_eq!(exif.fields.len(), 1);
+        assert_eq!(exif.make(), None);
+        assert_eq!(exif.model(), None);
+        assert_eq!(exif.orientation(), Some(3));
+    }
+}
@@ -0,0 +1,801 @@
+// Decodes an embedded ICC profile (from iCCP): the header, the tag table and the
+// tags most profiles share, enough to tell which profile it is.
+// https://www.color.org/specification/ICC.1-2022-05.pdf
+
+use std::fmt;
+
+use crate::chunks::sRGB;
+use crate::colorimetry::Matrix;
+use crate::error::IccError;
+
+// The profile header is 128 bytes, followed by the tag count & table
+const HEADER_LENGTH: usize = 128;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+// A signed 15.16 fixed point number
+fn read_s15_fixed16(data: &[u8], offset: usize) -> Option<f64> {
+    Some(read_u32(data, offset)? as i32 as f64 / 65536.0)
+}
+
+// An XYZType tag's first value
+// ICC.1 10.31 XYZType
+pub fn read_xyz(tag: &[u8]) -> Option<[f64; 3]> {
+    if tag.get(..4)? != b"XYZ " {
+        return None;
+    }
+
+    Some([
+        read_s15_fixed16(tag, 8)?,
+        read_s15_fixed16(tag, 12)?,
+        read_s15_fixed16(tag, 16)?,
+    ])
+}
+
+// A tone reproduction curve, turning encoded values into linear light
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    // y = x ^ gamma
+    Gamma(f64),
+    // Evenly spaced samples, linearly interpolated
+    Table(Vec<u16>),
+    // One of the five parametric functions, with its parameters
+    Parametric {
+        function_type: u16,
+        parameters: Vec<f64>,
+    },
+}
+
+impl Curve {
+    // A curveType or parametricCurveType tag
+    // ICC.1 10.6 curveType & 10.18 parametricCurveType
+    pub fn parse(tag: &[u8]) -> Option<Curve> {
+        match tag.get(..4)? {
+            b"curv" => {
+                let count = read_u32(tag, 8)? as usize;
+                match count {
+                    0 => Some(Curve::Gamma(1.0)),
+                    // A u8Fixed8Number
+                    1 => Some(Curve::Gamma(read_u16(tag, 12)? as f64 / 256.0)),
+                    _ => (0..count)
+                        .map(|index| read_u16(tag, 12 + index * 2))
+                        .collect::<Option<_>>()
+                        .map(Curve::Table),
+                }
+            }
+            b"para" => {
+                let function_type = read_u16(tag, 8)?;
+                let parameter_count = match function_type {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+                let parameters = (0..parameter_count)
+                    .map(|index| read_s15_fixed16(tag, 12 + index * 4))
+                    .collect::<Option<_>>()?;
+
+                Some(Curve::Parametric {
+                    function_type,
+                    parameters,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // The curve at x, from 0 to 1
+    pub fn evaluate(&self, x: f64) -> f64 {
+        match self {
+            Curve::Gamma(gamma) => x.powf(*gamma),
+            Curve::Table(table) => {
+                let position = x * (table.len() - 1) as f64;
+                let below = (position.floor() as usize).min(table.len() - 1);
+                let above = (below + 1).min(table.len() - 1);
+                let fraction = position - below as f64;
+                (table[below] as f64 * (1.0 - fraction) + table[above] as f64 * fraction) / 65535.0
+            }
+            Curve::Parametric {
+                function_type,
+                parameters: p,
+            } => {
+                let power = |a: f64, b: f64| (a * x + b).max(0.0).powf(p[0]);
+                match function_type {
+                    0 => x.powf(p[0]),
+                    1 if x >= -p[2] / p[1] => power(p[1], p[2]),
+                    1 => 0.0,
+                    2 if x >= -p[2] / p[1] => power(p[1], p[2]) + p[3],
+                    2 => p[3],
+                    3 if x >= p[4] => power(p[1], p[2]),
+                    3 => p[3] * x,
+                    _ if x >= p[4] => power(p[1], p[2]) + p[5],
+                    _ => p[3] * x + p[6],
+                }
+            }
+        }
+    }
+
+    // The single exponent that best stands in for the curve, measured at its midpoint.
+    // The sRGB curve comes out at about 2.2.
+    pub fn effective_gamma(&self) -> Option<f64> {
+        let y = self.evaluate(0.5);
+        if y <= 0.0 || y >= 1.0 {
+            return None;
+        }
+
+        Some(y.ln() / 0.5f64.ln())
+    }
+}
+
+// ICC.1 7.2 Profile header
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccHeader {
+    // The size the profile says it is
+    pub size: u32,
+    // The preferred color management module
+    pub cmm: [u8; 4],
+    // Major, minor & bug fix
+    pub version: (u8, u8, u8),
+    pub device_class: [u8; 4],
+    // The color space of the data, like "RGB " or "GRAY"
+    pub color_space: [u8; 4],
+    // The profile connection space, "XYZ " or "Lab "
+    pub pcs: [u8; 4],
+    // Year, month, day, hour, minute, second
+    pub created: [u16; 6],
+    pub platform: [u8; 4],
+    pub flags: u32,
+    pub manufacturer: [u8; 4],
+    pub model: u32,
+    pub rendering_intent: u32,
+    // The PCS white point, always D50
+    pub illuminant: [f64; 3],
+    pub creator: [u8; 4],
+    // An MD5 of the profile, all zeros if it wasn't calculated
+    pub profile_id: [u8; 16],
+}
+
+// One entry of the tag table, pointing at the tag's data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagEntry {
+    pub signature: [u8; 4],
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    pub header: IccHeader,
+    pub tags: Vec<TagEntry>,
+    data: Vec<u8>,
+}
+
+impl IccProfile {
+    pub fn parse(data: &[u8]) -> Result<IccProfile, IccError> {
+        if data.len() < HEADER_LENGTH + 4 {
+            return Err(IccError::TooShort { length: data.len() });
+        }
+        if &data[36..40] != b"acsp" {
+            return Err(IccError::MissingSignature);
+        }
+
+        // Checked above, so every header field is there
+        let u32_at = |offset| read_u32(data, offset).expect("inside the header");
+        let array_at = |offset: usize| -> [u8; 4] {
+            data[offset..offset + 4]
+                .try_into()
+                .expect("inside the header")
+        };
+
+        let size = u32_at(0);
+        if size as usize > data.len() {
+            return Err(IccError::Truncated {
+                declared: size,
+                actual: data.len(),
+            });
+        }
+
+        let header = IccHeader {
+            size,
+            cmm: array_at(4),
+            version: (data[8], data[9] >> 4, data[9] & 0xf),
+            device_class: array_at(12),
+            color_space: array_at(16),
+            pcs: array_at(20),
+            created: [0, 1, 2, 3, 4, 5]
+                .map(|index| read_u16(data, 24 + index * 2).expect("inside the header")),
+            platform: array_at(40),
+            flags: u32_at(44),
+            manufacturer: array_at(48),
+            model: u32_at(52),
+            rendering_intent: u32_at(64),
+            illuminant: [68, 72, 76]
+                .map(|offset| read_s15_fixed16(data, offset).expect("inside the header")),
+            creator: array_at(80),
+            profile_id: data[84..100].try_into().expect("inside the header"),
+        };
+
+        // ICC.1 7.3 Tag table
+        let count = u32_at(HEADER_LENGTH);
+        let table_end = (count as u64) * 12 + HEADER_LENGTH as u64 + 4;
+        if table_end > data.len() as u64 {
+            return Err(IccError::TagTableOutOfBounds { count });
+        }
+        let tags = (0..count as usize)
+            .map(|index| {
+                let entry = HEADER_LENGTH + 4 + index * 12;
+                TagEntry {
+                    signature: data[entry..entry + 4].try_into().expect("inside the table"),
+                    offset: u32_at(entry + 4),
+                    size: u32_at(entry + 8),
+                }
+            })
+            .collect();
+
+        Ok(IccProfile {
+            header,
+            tags,
+            data: data.to_vec(),
+        })
+    }
+
+    // The data of the tag with this signature, like b"rXYZ".
+    // None if there's no such tag or it points outside the profile.
+    pub fn tag(&self, signature: &[u8; 4]) -> Option<&[u8]> {
+        let entry = self.tags.iter().find(|tag| tag.signature == *signature)?;
+        self.entry_data(entry)
+    }
+
+    fn entry_data(&self, entry: &TagEntry) -> Option<&[u8]> {
+        let start = entry.offset as usize;
+        self.data
+            .get(start..start.checked_add(entry.size as usize)?)
+    }
+
+    // A text tag, whichever of the three text types it's stored as
+    pub fn text(&self, signature: &[u8; 4]) -> Option<String> {
+        read_text(self.tag(signature)?)
+    }
+
+    pub fn xyz(&self, signature: &[u8; 4]) -> Option<[f64; 3]> {
+        read_xyz(self.tag(signature)?)
+    }
+
+    pub fn curve(&self, signature: &[u8; 4]) -> Option<Curve> {
+        Curve::parse(self.tag(signature)?)
+    }
+
+    // The name a profile is known by, like "sRGB IEC61966-2.1"
+    pub fn description(&self) -> Option<String> {
+        self.text(b"desc")
+    }
+
+    pub fn copyright(&self) -> Option<String> {
+        self.text(b"cprt")
+    }
+
+    // The curve for the first channel, the red one for RGB & the only one for greyscale
+    pub fn first_curve(&self) -> Option<Curve> {
+        self.curve(b"rTRC").or_else(|| self.curve(b"kTRC"))
+    }
+
+    // The red, green & blue colorants as the columns of a matrix, adapted to D50
+    pub fn colorants(&self) -> Option<Matrix> {
+        let red = self.xyz(b"rXYZ")?;
+        let green = self.xyz(b"gXYZ")?;
+        let blue = self.xyz(b"bXYZ")?;
+
+        Some([0, 1, 2].map(|row| [red[row], green[row], blue[row]]))
+    }
+}
+
+// textType, textDescriptionType (version 2) or multiLocalizedUnicodeType (version 4).
+// For the multi language type the US English text is used, or the first one if there isn't any.
+// ICC.1 10.24 textType & 10.15 multiLocalizedUnicodeType
+fn read_text(tag: &[u8]) -> Option<String> {
+    let ascii = |bytes: &[u8]| {
+        let text: String = bytes.iter().map(|byte| *byte as char).collect();
+        text.trim_end_matches('\0').to_string()
+    };
+
+    match tag.get(..4)? {
+        b"text" => Some(ascii(tag.get(8..)?)),
+        b"desc" => {
+            let length = read_u32(tag, 8)? as usize;
+            Some(ascii(tag.get(12..12 + length)?))
+        }
+        b"mluc" => {
+            let count = read_u32(tag, 8)? as usize;
+            // A record is the language & country codes, then the text's length & offset
+            let record_size = read_u32(tag, 12)? as usize;
+            if record_size < 12 {
+                return None;
+            }
+
+            // The count isn't trusted, only the records that fit in the tag are read
+            let count = count.min((tag.len() - 16) / record_size);
+            let records = || {
+                (0..count).filter_map(move |index| index.checked_mul(record_size)?.checked_add(16))
+            };
+            let record = records()
+                .find(|record| tag.get(*record..*record + 4) == Some(b"enUS"))
+                .or_else(|| records().next())?;
+
+            let length = read_u32(tag, record + 4)? as usize;
+            let offset = read_u32(tag, record + 8)? as usize;
+            let units: Vec<u16> = tag
+                .get(offset..offset.checked_add(length)?)?
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            Some(
+                String::from_utf16_lossy(&units)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            )
+        }
+        _ => None,
+    }
+}
+
+// Signatures are 4 ASCII characters, often padded with spaces
+pub fn signature_name(signature: &[u8; 4]) -> String {
+    if signature
+        .iter()
+        .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+    {
+        signature
+            .iter()
+            .map(|byte| *byte as char)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    } else {
+        format!("{:#010x}", u32::from_be_bytes(*signature))
+    }
+}
+
+// ICC.1 7.2.5 Profile/device class field
+fn device_class_name(device_class: &[u8; 4]) -> &'static str {
+    match device_class {
+        b"scnr" => "Input device",
+        b"mntr" => "Display device",
+        b"prtr" => "Output device",
+        b"link" => "Device link",
+        b"spac" => "Color space",
+        b"abst" => "Abstract",
+        b"nmcl" => "Named color",
+        _ => "Unknown",
+    }
+}
+
+// What the common tags hold
+// ICC.1 9.2 Tag listing
+fn tag_name(signature: &[u8; 4]) -> Option<&'static str> {
+    Some(match signature {
+        b"desc" => "description",
+        b"cprt" => "copyright",
+        b"wtpt" => "media white point",
+        b"bkpt" => "media black point",
+        b"rXYZ" => "red colorant",
+        b"gXYZ" => "green colorant",
+        b"bXYZ" => "blue colorant",
+        b"rTRC" => "red tone curve",
+        b"gTRC" => "green tone curve",
+        b"bTRC" => "blue tone curve",
+        b"kTRC" => "grey tone curve",
+        b"chad" => "chromatic adaptation",
+        b"lumi" => "luminance",
+        b"meas" => "measurement",
+        b"tech" => "technology",
+        b"view" => "viewing conditions",
+        b"vued" => "viewing conditions description",
+        b"dmnd" => "device manufacturer description",
+        b"dmdd" => "device model description",
+        b"A2B0" | b"A2B1" | b"A2B2" => "device to PCS lookup",
+        b"B2A0" | b"B2A1" | b"B2A2" => "PCS to device lookup",
+        b"gamt" => "gamut",
+        b"chrm" => "chromaticity",
+        _ => return None,
+    })
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // A pure gamma curve is its own effective gamma
+            Curve::Gamma(gamma) => return write!(f, "gamma {:.2}", gamma),
+            Curve::Table(table) => write!(f, "curve of {} points", table.len())?,
+            Curve::Parametric { function_type, .. } => {
+                write!(f, "parametric function {}", function_type)?
+            }
+        }
+
+        match self.effective_gamma() {
+            Some(gamma) => write!(f, " (about gamma {:.2})", gamma),
+            None => Ok(()),
+        }
+    }
+}
+
+// Written to sit inside the iCCP chunk's section, so it's indented a level further
+impl fmt::Display for IccProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+        let text = |text: Option<String>| text.unwrap_or_else(|| "none".to_string());
+
+        writeln!(f, "  description: {}", text(self.description()))?;
+        writeln!(f, "  copyright: {}", text(self.copyright()))?;
+        writeln!(
+            f,
+            "  version: {}.{}.{}",
+            header.version.0, header.version.1, header.version.2
+        )?;
+        writeln!(
+            f,
+            "  device class: {} | {}",
+            signature_name(&header.device_class),
+            device_class_name(&header.device_class)
+        )?;
+        writeln!(
+            f,
+            "  color space: {} | The color space of the image data",
+            signature_name(&header.color_space)
+        )?;
+        writeln!(
+            f,
+            "  PCS: {} | The profile connection space colors are converted through",
+            signature_name(&header.pcs)
+        )?;
+        writeln!(
+            f,
+            "  rendering intent: {} ({})",
+            sRGB::intent_name(header.rendering_intent.min(u8::MAX as u32) as u8),
+            header.rendering_intent
+        )?;
+        let [year, month, day, hour, minute, second] = header.created;
+        writeln!(
+            f,
+            "  created: {:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        )?;
+        writeln!(
+            f,
+            "  creator: {} | CMM: {}, platform: {}, manufacturer: {}",
+            signature_name(&header.creator),
+            signature_name(&header.cmm),
+            signature_name(&header.platform),
+            signature_name(&header.manufacturer)
+        )?;
+        writeln!(
+            f,
+            "  illuminant: ({:.4}, {:.4}, {:.4}) | The PCS white point",
+            header.illuminant[0], header.illuminant[1], header.illuminant[2]
+        )?;
+
+        writeln!(f, "  tags: {}", self.tags.len())?;
+        for entry in &self.tags {
+            let data = self.entry_data(entry);
+            let tag_type = data
+                .and_then(|data| data.get(..4))
+                .and_then(|tag_type| tag_type.try_into().ok())
+                .map_or("out of bounds".to_string(), |tag_type| {
+                    signature_name(tag_type)
+                });
+
+            write!(
+                f,
+                "   {}: {}, {} bytes at {}",
+                signature_name(&entry.signature),
+                tag_type,
+                entry.size,
+                entry.offset
+            )?;
+            match tag_name(&entry.signature) {
+                Some(name) => writeln!(f, " | {}", name)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        let xyz_tags = [
+            (b"wtpt", "white point"),
+            (b"rXYZ", "red colorant"),
+            (b"gXYZ", "green colorant"),
+            (b"bXYZ", "blue colorant"),
+        ];
+        for (signature, name) in xyz_tags {
+            if let Some([x, y, z]) = self.xyz(signature) {
+                writeln!(f, "  {}: ({:.4}, {:.4}, {:.4})", name, x, y, z)?;
+            }
+        }
+
+        let curve_tags = [
+            (b"rTRC", "red curve"),
+            (b"gTRC", "green curve"),
+            (b"bTRC", "blue curve"),
+            (b"kTRC", "grey curve"),
+        ];
+        for (signature, name) in curve_tags {
+            if let Some(curve) = self.curve(signature) {
+                writeln!(f, "  {}: {}", name, curve)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn mluc(records: &[(&[u8; 4], &str)]) -> Vec<u8> {
+        let mut data = u32s(&[records.len() as u32, 12]);
+        let mut strings = Vec::new();
+        let strings_start = 16 + records.len() * 12;
+        for (language, text) in records {
+            let units: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            data.extend_from_slice(*language);
+            data.extend(u32s(&[
+                units.len() as u32,
+                (strings_start + strings.len()) as u32,
+            ]));
+            strings.extend(units);
+        }
+        data.extend(strings);
+        testing::icc_tag(b"mluc", &data)
+    }
+
+    #[test]
+    fn header() {
+        let profile = IccProfile::parse(&testing::icc_profile(&[])).unwrap();
+        let header = &profile.header;
+
+        assert_eq!(header.size, 132);
+        assert_eq!(header.version, (4, 3, 0));
+        assert_eq!(&header.device_class, b"mntr");
+        assert_eq!(&header.color_space, b"RGB ");
+        assert_eq!(&header.pcs, b"XYZ ");
+        assert_eq!(header.created, [2024, 5, 6, 7, 8, 9]);
+        assert!((header.illuminant[0] - 0.9642).abs() < 0.0001);
+        assert_eq!(header.illuminant[1], 1.0);
+        assert!(profile.tags.is_empty());
+    }
+
+    #[test]
+    fn header_errors() {
+        assert_eq!(
+            IccProfile::parse(&[0; 100]),
+            Err(IccError::TooShort { length: 100 })
+        );
+
+        let mut data = testing::icc_profile(&[]);
+        data[36] = b'x';
+        assert_eq!(IccProfile::parse(&data), Err(IccError::MissingSignature));
+
+        let data = testing::icc_profile(&[]);
+        assert_eq!(
+            IccProfile::parse(&data[..131]),
+            Err(IccError::TooShort { length: 131 })
+        );
+
+        let mut data = testing::icc_profile(&[]);
+        data[..4].copy_from_slice(&200u32.to_be_bytes());
+        assert_eq!(
+            IccProfile::parse(&data),
+            Err(IccError::Truncated {
+                declared: 200,
+                actual: 132
+            })
+        );
+    }
+
+    #[test]
+    fn tag_table() {
+        let data = testing::icc_profile(&[
+            (b"desc", testing::icc_tag(b"text", b"One\0")),
+            (b"cprt", testing::icc_tag(b"text", b"Two\0")),
+        ]);
+        let profile = IccProfile::parse(&data).unwrap();
+
+        assert_eq!(
+            profile.tags,
+            [
+                TagEntry {
+                    signature: *b"desc",
+                    offset: 156,
+                    size: 12
+                },
+                TagEntry {
+                    signature: *b"cprt",
+                    offset: 168,
+                    size: 12
+                },
+            ]
+        );
+        assert_eq!(profile.tag(b"desc"), Some(&data[156..168]));
+        assert_eq!(profile.tag(b"wtpt"), None);
+        assert_eq!(profile.description().as_deref(), Some("One"));
+        assert_eq!(profile.copyright().as_deref(), Some("Two"));
+    }
+
+    #[test]
+    fn tag_table_out_of_bounds() {
+        let mut data = testing::icc_profile(&[]);
+        data[128..132].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            IccProfile::parse(&data),
+            Err(IccError::TagTableOutOfBounds { count: u32::MAX })
+        );
+    }
+
+    #[test]
+    fn tags_outside_the_profile() {
+        let mut data = testing::icc_profile(&[
+            (b"desc", testing::icc_tag(b"text", b"One\0")),
+            (b"cprt", testing::icc_tag(b"text", b"Two\0")),
+        ]);
+        // The first runs a byte past the end, the second starts near u32::MAX
+        let past_end = data.len() as u32 - 11;
+        data[140..144].copy_from_slice(&past_end.to_be_bytes());
+        data[148..152].copy_from_slice(&(u32::MAX - 4).to_be_bytes());
+        let profile = IccProfile::parse(&data).unwrap();
+
+        assert_eq!(profile.tag(b"desc"), None);
+        assert_eq!(profile.tag(b"cprt"), None);
+        assert_eq!(profile.description(), None);
+        assert!(profile.to_string().contains("out of bounds"));
+    }
+
+    #[test]
+    fn gamma_curves() {
+        assert_eq!(
+            Curve::parse(&testing::icc_tag(b"curv", &u32s(&[0]))),
+            Some(Curve::Gamma(1.0))
+        );
+
+        // 2.2 as a u8Fixed8Number is 0x0233
+        let curve = Curve::parse(&testing::icc_tag(b"curv", &[0, 0, 0, 1, 0x02, 0x33])).unwrap();
+        assert_eq!(curve, Curve::Gamma(563.0 / 256.0));
+        assert!((curve.effective_gamma().unwrap() - 2.199).abs() < 0.001);
+    }
+
+    #[test]
+    fn table_curves() {
+        let curve = Curve::parse(&testing::icc_tag(
+            b"curv",
+            &[0, 0, 0, 3, 0x00, 0x00, 0x40, 0x00, 0xff, 0xff],
+        ))
+        .unwrap();
+
+        assert_eq!(curve, Curve::Table(vec![0, 0x4000, 0xffff]));
+        assert_eq!(curve.evaluate(0.0), 0.0);
+        assert!((curve.evaluate(0.5) - 0x4000 as f64 / 65535.0).abs() < 1e-9);
+        assert_eq!(curve.evaluate(1.0), 1.0);
+
+        // Says 3 entries but only has 2
+        assert_eq!(
+            Curve::parse(&testing::icc_tag(b"curv", &[0, 0, 0, 3, 0, 0, 0xff, 0xff])),
+            None
+        );
+    }
+
+    #[test]
+    fn parametric_curves() {
+        // Type 0, gamma 2.4
+        let curve =
+            Curve::parse(&testing::icc_tag(b"para", &[0, 0, 0, 0, 0, 2, 0x66, 0x66])).unwrap();
+        match &curve {
+            Curve::Parametric {
+                function_type: 0,
+                parameters,
+            } => assert!((parameters[0] - 2.4).abs() < 0.0001),
+            curve => panic!("{:?}", curve),
+        }
+
+        // Type 3, the sRGB curve
+        let parameters = [
+            0x0002_6666,
+            0x0000_f2a7,
+            0x0000_0d59,
+            0x0000_13d0,
+            0x0000_0a5b,
+        ];
+        let mut data = vec![0, 3, 0, 0];
+        data.extend(u32s(&parameters));
+        let curve = Curve::parse(&testing::icc_tag(b"para", &data)).unwrap();
+        assert!((curve.evaluate(0.5) - 0.2140).abs() < 0.001);
+        // The linear part near black
+        assert!((curve.evaluate(0.02) - 0.02 / 12.92).abs() < 0.0001);
+
+        // Type 5 isn't defined, and type 3 needs 5 parameters
+        assert_eq!(
+            Curve::parse(&testing::icc_tag(b"para", &[0, 5, 0, 0])),
+            None
+        );
+        assert_eq!(Curve::parse(&testing::icc_tag(b"para", &data[..16])), None);
+    }
+
+    #[test]
+    fn curve_tags() {
+        let data = testing::icc_profile(&[(b"kTRC", testing::icc_tag(b"curv", &u32s(&[0])))]);
+        let profile = IccProfile::parse(&data).unwrap();
+        assert_eq!(profile.first_curve(), Some(Curve::Gamma(1.0)));
+        assert_eq!(profile.curve(b"rTRC"), None);
+    }
+
+    #[test]
+    fn xyz_tags() {
+        let data = testing::icc_profile(&[(
+            b"wtpt",
+            testing::icc_tag(b"XYZ ", &u32s(&[0x0000_f6d6, 0x0001_0000, 0x0000_d32d])),
+        )]);
+        let profile = IccProfile::parse(&data).unwrap();
+        let [x, y, z] = profile.xyz(b"wtpt").unwrap();
+
+        assert!((x - 0.9642).abs() < 0.0001);
+        assert_eq!(y, 1.0);
+        assert!((z - 0.8249).abs() < 0.0001);
+        assert_eq!(read_xyz(&testing::icc_tag(b"XYZ ", &[0; 8])), None);
+    }
+
+    #[test]
+    fn text_types() {
+        assert_eq!(
+            read_text(&testing::icc_tag(
+                b"desc",
+                &[0, 0, 0, 4, b'a', b'b', b'c', 0]
+            ))
+            .as_deref(),
+            Some("abc")
+        );
+        // The length goes past the end of the tag
+        assert_eq!(
+            read_text(&testing::icc_tag(b"desc", &[0, 0, 0, 9, b'a'])),
+            None
+        );
+
+        // US English is picked over the first record
+        let tag = mluc(&[(b"deDE", "Farbe"), (b"enUS", "Color")]);
+        assert_eq!(read_text(&tag).as_deref(), Some("Color"));
+        let tag = mluc(&[(b"deDE", "Farbe"), (b"frFR", "Couleur")]);
+        assert_eq!(read_text(&tag).as_deref(), Some("Farbe"));
+    }
+
+    #[test]
+    fn mluc_counts_arent_trusted() {
+        let mut tag = mluc(&[(b"enUS", "Hi")]);
+        tag[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(read_text(&tag).as_deref(), Some("Hi"));
+
+        // A huge record size leaves no records
+        let mut huge = tag.clone();
+        huge[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(read_text(&huge), None);
+
+        // Records are at least 12 bytes
+        let mut small = tag.clone();
+        small[12..16].copy_from_slice(&4u32.to_be_bytes());
+        assert_eq!(read_text(&small), None);
+    }
+}
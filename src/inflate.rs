@@ -0,0 +1,631 @@
+// zlib & DEFLATE decompression, used for IDAT, zTXt, iTXt and iCCP.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#10Compression
+// zlib: https://www.rfc-editor.org/rfc/rfc1950
+// DEFLATE: https://www.rfc-editor.org/rfc/rfc1951
+
+use crate::error::DecompressError;
+
+// Decompresses a zlib stream: a 2 byte header, DEFLATE data, then the Adler-32 of the output.
+// Stops with an error once the output would go over `limit` bytes, so a small stream
+// can't expand into gigabytes (a decompression bomb).
+pub fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, DecompressError> {
+    let inflated = zlib_decompress_prefix(data, limit)?;
+    if inflated.excess {
+        return Err(DecompressError::TooLarge { limit });
+    }
+    Ok(inflated.data)
+}
+
+// The first `limit` bytes a zlib stream decompresses to, and whether it has more after them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inflated {
+    pub data: Vec<u8>,
+    pub excess: bool,
+}
+
+// Like `zlib_decompress`, but a stream that goes over `limit` bytes is cut short instead
+// of being an error. Image data can have more than the image needs, which decoders are
+// meant to ignore. The Adler-32 covers the whole stream, so it isn't checked when cut short.
+pub fn zlib_decompress_prefix(data: &[u8], limit: usize) -> Result<Inflated, DecompressError> {
+    if data.len() < 2 {
+        return Err(DecompressError::UnexpectedEnd);
+    }
+
+    let cmf = data[0];
+    let flg = data[1];
+
+    // The header, read as a big-endian u16, has to be a multiple of 31
+    if !(((cmf as u16) << 8) | flg as u16).is_multiple_of(31) {
+        return Err(DecompressError::InvalidHeader);
+    }
+
+    // Method 8 is DEFLATE with a window of at most 32K, the only one PNG allows
+    let method = cmf & 0x0f;
+    let window_bits = (cmf >> 4) + 8;
+    if method != 8 || window_bits > 15 {
+        return Err(DecompressError::UnsupportedMethod { cmf });
+    }
+
+    if flg & 0x20 != 0 {
+        return Err(DecompressError::PresetDictionary);
+    }
+
+    let mut bits = BitReader::new(&data[2..]);
+    let mut output = Output {
+        bytes: Vec::new(),
+        limit,
+    };
+    match inflate_into(&mut bits, &mut output) {
+        Ok(()) => (),
+        Err(DecompressError::TooLarge { .. }) => {
+            return Ok(Inflated {
+                data: output.bytes,
+                excess: true,
+            })
+        }
+        Err(error) => return Err(error),
+    }
+
+    // The Adler-32 starts at the next byte boundary after the last block
+    let checksum_start = 2 + bits.bytes_used();
+    let checksum = match data.get(checksum_start..checksum_start + 4) {
+        Some(x) => u32::from_be_bytes([x[0], x[1], x[2], x[3]]),
+        None => return Err(DecompressError::UnexpectedEnd),
+    };
+
+    let computed = adler32(&output.bytes);
+    if computed != checksum {
+        return Err(DecompressError::ChecksumMismatch {
+            expected: computed,
+            actual: checksum,
+        });
+    }
+
+    Ok(Inflated {
+        data: output.bytes,
+        excess: false,
+    })
+}
+
+// Decompresses raw DEFLATE data, without the zlib wrapper.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut output = Output {
+        bytes: Vec::new(),
+        limit,
+    };
+    inflate_into(&mut BitReader::new(data), &mut output)?;
+    Ok(output.bytes)
+}
+
+// https://www.rfc-editor.org/rfc/rfc1950#section-8.2
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // Largest number of bytes that can be summed before b could overflow a u32
+    const BLOCK_SIZE: usize = 5552;
+
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for block in data.chunks(BLOCK_SIZE) {
+        for byte in block {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+fn inflate_into(bits: &mut BitReader, output: &mut Output) -> Result<(), DecompressError> {
+    loop {
+        let last_block = bits.read_bits(1)? == 1;
+
+        match bits.read_bits(2)? {
+            0 => stored_block(bits, output)?,
+            1 => {
+                let (literals, distances) = fixed_tables();
+                huffman_block(bits, output, &literals, &distances)?
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(bits)?;
+                huffman_block(bits, output, &literals, &distances)?
+            }
+            _ => return Err(DecompressError::InvalidBlockType),
+        }
+
+        if last_block {
+            return Ok(());
+        }
+    }
+}
+
+// The decompressed bytes so far, which can't grow past the limit. Bytes are added up
+// to the limit before it's an error, so what fits can still be used.
+struct Output {
+    bytes: Vec<u8>,
+    limit: usize,
+}
+
+impl Output {
+    fn push(&mut self, byte: u8) -> Result<(), DecompressError> {
+        if self.bytes.len() == self.limit {
+            return Err(DecompressError::TooLarge { limit: self.limit });
+        }
+        self.bytes.push(byte);
+        Ok(())
+    }
+
+    fn extend(&mut self, bytes: &[u8]) -> Result<(), DecompressError> {
+        let room = self.limit - self.bytes.len();
+        self.bytes
+            .extend_from_slice(&bytes[..bytes.len().min(room)]);
+        if bytes.len() > room {
+            return Err(DecompressError::TooLarge { limit: self.limit });
+        }
+        Ok(())
+    }
+}
+
+// Reads bits starting from the least significant bit of each byte, as DEFLATE packs them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            position: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32, DecompressError> {
+        while self.bit_count < count {
+            let byte = match self.data.get(self.position) {
+                Some(x) => *x,
+                None => return Err(DecompressError::UnexpectedEnd),
+            };
+            self.position += 1;
+            self.bit_buffer |= (byte as u64) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        let value = (self.bit_buffer & ((1 << count) - 1)) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    // Throws away what's left of the current byte
+    fn align_to_byte(&mut self) {
+        let partial = self.bit_count % 8;
+        self.bit_buffer >>= partial;
+        self.bit_count -= partial;
+    }
+
+    // Bytes fully or partially read so far
+    fn bytes_used(&self) -> usize {
+        self.position - (self.bit_count / 8) as usize
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], DecompressError> {
+        // Stored blocks are byte aligned, so whole bytes left in the buffer go back to the data
+        let start = self.bytes_used();
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+
+        match self.data.get(start..start + length) {
+            Some(bytes) => {
+                self.position = start + length;
+                Ok(bytes)
+            }
+            None => Err(DecompressError::UnexpectedEnd),
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.4
+fn stored_block(bits: &mut BitReader, output: &mut Output) -> Result<(), DecompressError> {
+    bits.align_to_byte();
+
+    let header = bits.read_bytes(4)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let length_complement = u16::from_le_bytes([header[2], header[3]]);
+
+    if length != !length_complement {
+        return Err(DecompressError::StoredLengthMismatch);
+    }
+
+    output.extend(bits.read_bytes(length as usize)?)
+}
+
+const MAX_CODE_LENGTH: usize = 15;
+
+// A canonical Huffman code, stored as how many codes there are of each length
+// and the symbols in code order. Decoding walks the lengths one bit at a time.
+struct Huffman {
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, DecompressError> {
+        let mut counts = [0; MAX_CODE_LENGTH + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Too many codes of a length leave no room for longer ones (over-subscribed).
+        // Incomplete codes are fine, as long as the missing codes are never used.
+        let mut left: i32 = 1;
+        for count in &counts[1..] {
+            left <<= 1;
+            left -= *count as i32;
+            if left < 0 {
+                return Err(DecompressError::InvalidHuffmanCode);
+            }
+        }
+
+        // Where each length's symbols start in `symbols`
+        let mut offsets = [0; MAX_CODE_LENGTH + 2];
+        for length in 1..=MAX_CODE_LENGTH {
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+        }
+
+        let mut symbols = vec![0; offsets[MAX_CODE_LENGTH + 1]];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize]] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, DecompressError> {
+        // First code of the current length, and where its symbols start
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for length in 1..=MAX_CODE_LENGTH {
+            code |= bits.read_bits(1)? as i32;
+            let count = self.counts[length] as i32;
+
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err(DecompressError::InvalidHuffmanCode)
+    }
+}
+
+// Base lengths & extra bits for length symbols 257-285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+// Base distances & extra bits for distance symbols 0-29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.5
+fn huffman_block(
+    bits: &mut BitReader,
+    output: &mut Output,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), DecompressError> {
+    loop {
+        let symbol = literals.decode(bits)?;
+
+        match symbol {
+            0..=255 => {
+                output.push(symbol as u8)?;
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = (symbol - 257) as usize;
+                let length = LENGTH_BASE[index] as usize
+                    + bits.read_bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distances.decode(bits)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(DecompressError::InvalidSymbol);
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + bits.read_bits(DISTANCE_EXTRA[index] as u32)? as usize;
+
+                if distance > output.bytes.len() {
+                    return Err(DecompressError::DistanceTooFar);
+                }
+                // Byte by byte, as the copy can overlap what it's writing
+                let start = output.bytes.len() - distance;
+                for i in 0..length {
+                    output.push(output.bytes[start + i])?;
+                }
+            }
+            _ => return Err(DecompressError::InvalidSymbol),
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.6
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    // Both are complete codes, so can't fail
+    let literals = Huffman::new(&lengths).expect("fixed literal code is valid");
+    let distances = Huffman::new(&[5; 30]).expect("fixed distance code is valid");
+
+    (literals, distances)
+}
+
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.7
+fn dynamic_tables(bits: &mut BitReader) -> Result<(Huffman, Huffman), DecompressError> {
+    // The order code length code lengths are stored in
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let literal_count = bits.read_bits(5)? as usize + 257;
+    let distance_count = bits.read_bits(5)? as usize + 1;
+    let code_length_count = bits.read_bits(4)? as usize + 4;
+
+    if literal_count > 286 || distance_count > 30 {
+        return Err(DecompressError::InvalidHuffmanCode);
+    }
+
+    let mut code_length_lengths = [0; 19];
+    for position in &ORDER[..code_length_count] {
+        code_length_lengths[*position] = bits.read_bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths)?;
+
+    // Literal & distance lengths are read as one run, repeats can cross between them
+    let mut lengths = vec![0; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_lengths.decode(bits)?;
+
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            // Repeat the previous length 3-6 times
+            16 => {
+                if index == 0 {
+                    return Err(DecompressError::InvalidHuffmanCode);
+                }
+                (lengths[index - 1], 3 + bits.read_bits(2)? as usize)
+            }
+            // Repeat a length of zero 3-10 times, or 11-138 times
+            17 => (0, 3 + bits.read_bits(3)? as usize),
+            18 => (0, 11 + bits.read_bits(7)? as usize),
+            _ => return Err(DecompressError::InvalidSymbol),
+        };
+
+        if index + repeat > lengths.len() {
+            return Err(DecompressError::InvalidHuffmanCode);
+        }
+        lengths[index..index + repeat].fill(length);
+        index += repeat;
+    }
+
+    // Without an end of block code the block could never finish
+    if lengths[256] == 0 {
+        return Err(DecompressError::InvalidHuffmanCode);
+    }
+
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+
+    Ok((literals, distances))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer;
+
+    // zlib.compress(b"hello hello hello", 9), a single fixed Huffman block
+    const FIXED: [u8; 16] = [
+        0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e, 0x06,
+        0x7d,
+    ];
+
+    // zlib.compress(DYNAMIC_TEXT, 9), a single dynamic Huffman block
+    const DYNAMIC_TEXT: &[u8] = b"baaaddbdacadcacaaabaabdbdbbdaa";
+    const DYNAMIC: [u8; 32] = [
+        0x78, 0xda, 0x0d, 0xc8, 0x41, 0x0d, 0x00, 0x00, 0x0c, 0x83, 0x40, 0xad, 0x30, 0xfc, 0x6b,
+        0x58, 0x13, 0xc2, 0xe3, 0x04, 0xca, 0x38, 0xba, 0x0d, 0x5c, 0x03, 0x67, 0x3c, 0xb2, 0x62,
+        0x0b, 0x81,
+    ];
+
+    #[test]
+    fn adler32_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"hello hello hello"), 0x3a2e067d);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn stored_blocks_round_trip() {
+        // Empty, one block, and over 64K so it's split into several blocks
+        for length in [0, 1000, 200_000] {
+            let data: Vec<u8> = (0..length).map(|i| (i * 7 % 251) as u8).collect();
+            let compressed = writer::zlib_stored(&data);
+            assert_eq!(zlib_decompress(&compressed, length).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn fixed_block() {
+        assert_eq!(zlib_decompress(&FIXED, 100).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_block() {
+        assert_eq!(zlib_decompress(&DYNAMIC, 100).unwrap(), DYNAMIC_TEXT);
+    }
+
+    #[test]
+    fn raw_deflate() {
+        assert_eq!(inflate(&FIXED[2..], 100).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut data = FIXED;
+        data[15] ^= 1;
+        assert_eq!(
+            zlib_decompress(&data, 100),
+            Err(DecompressError::ChecksumMismatch {
+                expected: 0x3a2e067d,
+                actual: 0x3a2e067c,
+            })
+        );
+    }
+
+    #[test]
+    fn missing_checksum() {
+        assert_eq!(
+            zlib_decompress(&FIXED[..13], 100),
+            Err(DecompressError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn truncated_data() {
+        for length in [0, 1, 2, 3, 8] {
+            assert_eq!(
+                zlib_decompress(&DYNAMIC[..length], 100),
+                Err(DecompressError::UnexpectedEnd)
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert_eq!(
+            zlib_decompress(&[0x78, 0xdb, 0x03, 0x00], 100),
+            Err(DecompressError::InvalidHeader)
+        );
+        // Method 15, with the check bits right
+        assert_eq!(
+            zlib_decompress(&[0x7f, 0x07, 0x03, 0x00], 100),
+            Err(DecompressError::UnsupportedMethod { cmf: 0x7f })
+        );
+        assert_eq!(
+            zlib_decompress(&[0x78, 0xbb, 0x03, 0x00], 100),
+            Err(DecompressError::PresetDictionary)
+        );
+    }
+
+    #[test]
+    fn reserved_block_type() {
+        assert_eq!(
+            inflate(&[0x07], 100),
+            Err(DecompressError::InvalidBlockType)
+        );
+    }
+
+    #[test]
+    fn stored_length_mismatch() {
+        assert_eq!(
+            inflate(&[0x01, 0x05, 0x00, 0xfa, 0xfe], 100),
+            Err(DecompressError::StoredLengthMismatch)
+        );
+    }
+
+    #[test]
+    fn oversubscribed_code_lengths() {
+        // A dynamic block whose four code length codes are all 1 bit long
+        assert_eq!(
+            inflate(&[0x05, 0x00, 0x92, 0x04], 100),
+            Err(DecompressError::InvalidHuffmanCode)
+        );
+    }
+
+    #[test]
+    fn output_limit() {
+        assert_eq!(
+            zlib_decompress(&FIXED, 16),
+            Err(DecompressError::TooLarge { limit: 16 })
+        );
+        assert_eq!(zlib_decompress(&FIXED, 17).unwrap().len(), 17);
+
+        let stored = writer::zlib_stored(&[0; 1000]);
+        assert_eq!(
+            zlib_decompress(&stored, 999),
+            Err(DecompressError::TooLarge { limit: 999 })
+        );
+    }
+
+    #[test]
+    fn prefix_of_the_output() {
+        assert_eq!(
+            zlib_decompress_prefix(&FIXED, 5),
+            Ok(Inflated {
+                data: b"hello".to_vec(),
+                excess: true,
+            })
+        );
+        assert_eq!(
+            zlib_decompress_prefix(&FIXED, 17),
+            Ok(Inflated {
+                data: b"hello hello hello".to_vec(),
+                excess: false,
+            })
+        );
+
+        // Cut short part way through a stored block
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let inflated = zlib_decompress_prefix(&writer::zlib_stored(&data), 600).unwrap();
+        assert_eq!(inflated.data, data[..600]);
+        assert!(inflated.excess);
+    }
+
+    #[test]
+    fn prefix_still_checks_the_stream() {
+        // The checksum is only checked when the whole stream is decompressed
+        let mut data = FIXED;
+        data[15] ^= 1;
+        assert!(zlib_decompress_prefix(&data, 5).unwrap().excess);
+        assert!(matches!(
+            zlib_decompress_prefix(&data, 17),
+            Err(DecompressError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(
+            zlib_decompress_prefix(&[0x78, 0xdb, 0x03, 0x00], 100),
+            Err(DecompressError::InvalidHeader)
+        );
+    }
+}
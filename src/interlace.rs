@@ -0,0 +1,163 @@
+// Adam7 interlacing splits the image into seven smaller images (passes), each
+// stored one after the other in the image data, filtered on its own.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#8Interlace
+
+use crate::chunks::IHDR;
+
+// (x start, y start, x step, y step) for each of the seven passes
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+// One of the seven reduced images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adam7Pass {
+    // 1 to 7
+    pub number: u8,
+    pub x_start: u32,
+    pub y_start: u32,
+    pub x_step: u32,
+    pub y_step: u32,
+    // Size of the reduced image, either can be 0 for small images
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Adam7Pass {
+    // A pass with no pixels has no scanlines at all, not even filter type bytes
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    // Bytes of filtered image data the pass takes up, including the filter type bytes.
+    // None when that doesn't fit in a usize.
+    pub fn byte_size(&self, header: &IHDR) -> Option<usize> {
+        if self.is_empty() {
+            return Some(0);
+        }
+
+        (header.scanline_length(self.width) + 1).checked_mul(self.height as usize)
+    }
+
+    // Position of the pass's pixel (x, y) in the full image
+    pub fn to_image(&self, x: u32, y: u32) -> (u32, u32) {
+        (
+            self.x_start + x * self.x_step,
+            self.y_start + y * self.y_step,
+        )
+    }
+}
+
+// The seven passes for an image of the header's size, in the order they're stored
+pub fn adam7_passes(header: &IHDR) -> [Adam7Pass; 7] {
+    let mut number = 0;
+    ADAM7.map(|(x_start, y_start, x_step, y_step)| {
+        number += 1;
+        Adam7Pass {
+            number,
+            x_start,
+            y_start,
+            x_step,
+            y_step,
+            width: (header.width + x_step - 1 - x_start.min(header.width)) / x_step,
+            height: (header.height + y_step - 1 - y_start.min(header.height)) / y_step,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32) -> IHDR {
+        IHDR {
+            width,
+            height,
+            bit_depth: 8,
+            color_type: 0,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 1,
+        }
+    }
+
+    fn sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+        adam7_passes(&header(width, height))
+            .iter()
+            .map(|pass| (pass.width, pass.height))
+            .collect()
+    }
+
+    #[test]
+    fn tiny_images_have_empty_passes() {
+        assert_eq!(
+            sizes(1, 1),
+            [(1, 1), (0, 1), (1, 0), (0, 1), (1, 0), (0, 1), (1, 0)]
+        );
+        assert_eq!(
+            sizes(2, 2),
+            [(1, 1), (0, 1), (1, 0), (0, 1), (1, 0), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            sizes(3, 3),
+            [(1, 1), (0, 1), (1, 0), (1, 1), (2, 1), (1, 2), (3, 1)]
+        );
+    }
+
+    #[test]
+    fn full_block_passes() {
+        assert_eq!(
+            sizes(8, 8),
+            [(1, 1), (1, 1), (2, 1), (2, 2), (4, 2), (4, 4), (8, 4)]
+        );
+        // Every pixel is in exactly one pass
+        let total: u32 = sizes(13, 7).iter().map(|(w, h)| w * h).sum();
+        assert_eq!(total, 13 * 7);
+    }
+
+    #[test]
+    fn empty_passes_take_no_bytes() {
+        let header = header(1, 1);
+        let passes = adam7_passes(&header);
+
+        assert!(passes[1].is_empty());
+        assert_eq!(passes[1].byte_size(&header), Some(0));
+        // One filter type byte & one sample
+        assert_eq!(passes[0].byte_size(&header), Some(2));
+        assert_eq!(header.image_data_size(), Some(2));
+    }
+
+    #[test]
+    fn passes_are_numbered_in_order() {
+        let numbers: Vec<u8> = adam7_passes(&header(8, 8))
+            .iter()
+            .map(|pass| pass.number)
+            .collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn pass_pixels_map_to_the_image() {
+        let passes = adam7_passes(&header(8, 8));
+        assert_eq!(passes[0].to_image(0, 0), (0, 0));
+        assert_eq!(passes[1].to_image(0, 0), (4, 0));
+        assert_eq!(passes[3].to_image(1, 1), (6, 4));
+        assert_eq!(passes[6].to_image(3, 2), (3, 5));
+    }
+
+    #[test]
+    fn huge_sizes_dont_overflow() {
+        let header = IHDR {
+            bit_depth: 16,
+            color_type: 6,
+            ..header(0x7fff_ffff, 0x7fff_ffff)
+        };
+        assert_eq!(header.image_data_size(), None);
+    }
+}
@@ -0,0 +1,39 @@
+// A small PNG parser.
+//
+// Decode a datastream with `Png::from_reader` or `Png::from_bytes` (or their
+// `_with_options` versions to pick how strictly it's checked), then print it
+// through `Png::display`. Each chunk is also decoded into its own struct (see
+// `chunks`), so values can be read directly, like `png.ihdr().width`.
+
+pub mod animation;
+pub mod chunks;
+pub mod colorimetry;
+pub mod consistency;
+pub mod crc;
+pub mod error;
+pub mod exif;
+pub mod icc;
+pub mod inflate;
+pub mod interlace;
+pub mod parser;
+pub mod pixels;
+pub mod reader;
+#[cfg(test)]
+mod testing;
+pub mod trailing;
+pub mod validate;
+pub mod writer;
+
+pub use animation::{Canvases, Frame};
+pub use chunks::ParsedChunk;
+pub use consistency::ColorWarning;
+pub use error::{
+    AnimationError, ChunkError, DecompressError, ExifError, IccError, ImageError, PngError,
+};
+pub use exif::Exif;
+pub use icc::IccProfile;
+pub use interlace::Adam7Pass;
+pub use parser::{CrcCheck, DisplayOptions, ParseOptions, Png};
+pub use pixels::{Pixel, PixelBuffer};
+pub use trailing::{TrailingData, TrailingKind};
+pub use validate::{Finding, Problem, Severity};
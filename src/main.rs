@@ -1,15 +1,172 @@
 use std::env::args;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::process::exit;
 
-use crate::cli::Cli;
-use crate::parser::Png;
+use color::animation::canvas_to_png;
+use color::{CrcCheck, Png, Severity};
+
+use crate::cli::{Cli, FrameExtraction};
 
 mod cli;
-mod parser;
 
 fn main() {
-    let cli = Cli::from(args());
+    let mut cli = Cli::from(args());
+
+    // Validating reports bad CRCs along with everything else, instead of stopping at them
+    if cli.validate {
+        cli.parse_options.crc_check = CrcCheck::Lenient;
+    }
+
+    let path = match &cli.file_path {
+        Some(file_path) => file_path,
+        None => {
+            eprintln!("Error, no PNG path was supplied");
+            exit(1);
+        }
+    };
+
+    let file = match File::open(path) {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("Error, can't read file {:?}: {}", path, error);
+            exit(1);
+        }
+    };
+
+    let png = match Png::from_reader_with_options(BufReader::new(file), &cli.parse_options) {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("Error, {}", error);
+            exit(1);
+        }
+    };
+
+    if cli.validate {
+        validate(&png);
+    } else {
+        println!("{}", png.display(&cli.display_options));
+    }
+
+    if cli.extract_icc {
+        let icc_path = match &cli.icc_path {
+            Some(icc_path) => icc_path.clone(),
+            None => path.with_extension("icc"),
+        };
+        extract_icc(&png, &icc_path);
+    }
+
+    if let Some(extraction) = cli.extract_frames {
+        let frames_path = match &cli.frames_path {
+            Some(frames_path) => frames_path.clone(),
+            None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        };
+        extract_frames(&png, path, &frames_path, extraction);
+    }
+}
+
+// Prints every problem found, exiting with 1 if any of them are errors
+fn validate(png: &Png) {
+    let findings = png.validate();
+    for finding in &findings {
+        println!("{}", finding);
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity() == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    println!("{} errors, {} warnings", errors, warnings);
+
+    if errors > 0 {
+        exit(1);
+    }
+}
+
+// Writes the decompressed iCCP profile to its own file
+fn extract_icc(png: &Png, icc_path: &Path) {
+    let iccp = match png.iccp() {
+        Some(x) => x,
+        None => {
+            eprintln!("Error, the PNG has no (valid) iCCP chunk to extract");
+            exit(1);
+        }
+    };
+
+    let profile = match iccp.profile() {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("Error, can't decompress the ICC profile: {}", error);
+            exit(1);
+        }
+    };
+
+    if let Err(error) = fs::write(icc_path, &profile) {
+        eprintln!("Error, can't write {:?}: {}", icc_path, error);
+        exit(1);
+    }
+
+    println!(
+        "Wrote the {} byte ICC profile \"{}\" to {:?}",
+        profile.len(),
+        iccp.profile_name,
+        icc_path
+    );
+}
+
+// Writes each APNG frame to its own PNG, named after the image, the frame & its delay
+fn extract_frames(png: &Png, path: &Path, frames_path: &Path, extraction: FrameExtraction) {
+    let frames = match png.frames() {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("Error, can't get the APNG's frames: {}", error);
+            exit(1);
+        }
+    };
+
+    let mut canvases = match extraction {
+        FrameExtraction::Raw => None,
+        FrameExtraction::Canvas => match png.canvases() {
+            Ok(x) => Some(x),
+            Err(error) => {
+                eprintln!("Error, can't draw the APNG's frames: {}", error);
+                exit(1);
+            }
+        },
+    };
+
+    if let Err(error) = fs::create_dir_all(frames_path) {
+        eprintln!("Error, can't create {:?}: {}", frames_path, error);
+        exit(1);
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let digits = frames.len().to_string().len();
+
+    for frame in &frames {
+        let delay = (frame.control.delay() * 1000.0).round();
+        let frame_path = frames_path.join(format!(
+            "{}-frame{:0digits$}-{}ms.png",
+            stem, frame.number, delay
+        ));
+
+        // Each canvas is written before the next one is drawn
+        let data = match canvases.as_mut().and_then(Iterator::next) {
+            Some(Ok(canvas)) => canvas_to_png(png, &canvas),
+            Some(Err(error)) => {
+                eprintln!("Error, can't draw the APNG's frames: {}", error);
+                exit(1);
+            }
+            None => frame.to_png(png),
+        };
 
-    let png = Png::from(cli);
+        if let Err(error) = fs::write(&frame_path, data) {
+            eprintln!("Error, can't write {:?}: {}", frame_path, error);
+            exit(1);
+        }
+    }
 
-    println!("{}", png);
+    println!("Wrote {} frames to {:?}", frames.len(), frames_path);
 }
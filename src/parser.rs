@@ -1,9 +1,26 @@
-// TODO: Might make sense to make structs for each chunk type, allowing for me to more easily share & display the data when parsing
-
-use std::fs::File;
-use std::io::Read;
-
-use crate::cli::Cli;
+use std::cell::OnceCell;
+use std::io::{ErrorKind, Read};
+
+use crate::animation::{self, Canvases, Frame};
+use crate::chunks::{
+    acTL, cICP, eXIf, fcTL, iCCP, pCAL, tIME, tRNS, ParseContext, ParsedChunk, IHDR, PLTE,
+};
+use crate::consistency::{self, ColorWarning};
+use crate::crc;
+use crate::error::{AnimationError, ChunkError, DecompressError, ImageError, PngError};
+use crate::inflate::{self, Inflated};
+use crate::interlace::{self, Adam7Pass};
+use crate::pixels::PixelBuffer;
+use crate::trailing::TrailingData;
+use crate::validate::{self, Finding};
+
+// The 8 byte signature every PNG datastream starts with
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5PNG-file-signature
+pub const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+// Chunk lengths are limited to 2^31 - 1 bytes
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5Chunk-layout
+const MAX_CHUNK_LENGTH: u32 = i32::MAX as u32;
 
 // http://www.libpng.org/pub/png/spec/1.2/PNG-Structure.html
 // https://www.w3.org/TR/2003/REC-PNG-20031110/#11Chunks
@@ -11,298 +28,288 @@ use crate::cli::Cli;
 pub struct Png {
     metadata: [u8; 8],
     chunks: Vec<Chunk>,
-    cli: Cli,
+    // Anything after IEND
+    trailing_data: Option<TrailingData>,
+    // Decompressing & unfiltering the image is slow, so each is only done the first time
+    // it's asked for
+    image_data: OnceCell<Result<Inflated, DecompressError>>,
+    pixels: OnceCell<Result<PixelBuffer, ImageError>>,
+}
+
+// How the PNG should be written out when displayed.
+#[derive(Debug, Default)]
+pub struct DisplayOptions {
+    // If description should be added to the stdout messages.
+    pub descriptive: bool,
+}
+
+// How the datastream should be checked while it's being decoded.
+#[derive(Debug, Default)]
+pub struct ParseOptions {
+    pub crc_check: CrcCheck,
+}
+
+// What to do when a chunk's stored CRC doesn't match its contents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CrcCheck {
+    // Stop decoding with `PngError::CrcMismatch`
+    #[default]
+    Strict,
+    // Keep the chunk, recording the mismatch on it so it can be shown later
+    Lenient,
+}
+
+// Borrows a Png together with the options it should be displayed with.
+// Created through `Png::display`.
+pub struct PngDisplay<'a> {
+    png: &'a Png,
+    options: &'a DisplayOptions,
 }
 
 // http://www.libpng.org/pub/png/spec/1.2/PNG-Structure.html#Chunk-layout
 #[derive(Debug)]
-struct Chunk {
+pub struct Chunk {
     chunk_type: ChunkType,
+    // Where the chunk's length field starts in the datastream
+    chunk_offset: u64,
     chunk_length: usize,
     chunk_data: Vec<u8>,
     chunk_crc: [u8; 4],
+    // CRC calculated over the chunk type & data while decoding
+    computed_crc: u32,
+    // The chunk's data decoded into its struct
+    parsed: ParsedChunk,
 }
 
-// Gonna be a disgusting mess imo
-impl std::fmt::Display for Png {
+impl std::fmt::Display for PngDisplay<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        // What to parse before hand: We must know what the colordata in the IHDR chunk is to allow us to understand what the IDAT Chunk actually holds as data.
-        // Otherwise, we don't know if it's gresycale, alpha, 3 channel, etc.
-        // Also tells us if it's pallet or not, so we don't need to go looking beforehand.
-
-        let mut color_type: [u8; 1] = [0; 1];
-        let mut bit_depth: [u8; 1] = [0; 1];
-
-        for chunk in &self.chunks {
-            match chunk.chunk_type {
-                ChunkType::IHDR => {
-                    let mut width: [u8; 4] = [0; 4];
-                    let mut height: [u8; 4] = [0; 4];
-                    let mut compression_method: [u8; 1] = [0; 1];
-                    let mut filter_method: [u8; 1] = [0; 1];
-                    let mut interlace_method: [u8; 1] = [0; 1];
-
-                    chunk.chunk_data.as_slice().read_exact(&mut width).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut height).unwrap();
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut bit_depth)
-                        .unwrap();
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut color_type)
-                        .unwrap();
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut compression_method)
-                        .unwrap();
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut filter_method)
-                        .unwrap();
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut interlace_method)
-                        .unwrap();
-
-                    if self.cli.display_options.descriptive == Some(true) {
-                        write!(f, "IHDR Chunk:\n width: {:?}\n height: {:?}\n compression method: {:?}\n filter_method {:?}\n interlace method: {:?}\n",
-                            u32::from_be_bytes(width), u32::from_be_bytes(height), u8::from_be_bytes(compression_method), u8::from_be_bytes(filter_method), u8::from_be_bytes(interlace_method)).unwrap();
-                    }
-                }
-                ChunkType::IEND => {
-                    if self.cli.display_options.descriptive == Some(true) {
-                        write!(f, "IEND Chunk:\n (no data)").unwrap();
-                    }
-                }
-                ChunkType::PLTE => {
-                    // Scheme: Index, Red, Green, Blue
-                    let mut pallet: Vec<(u8, u8, u8)> = Vec::new();
-
-                    for _ in chunk.chunk_data.iter() {
-                        let mut colors: [u8; 3] = [0, 0, 0];
-
-                        // Break once the Pallet Chunk ends
-                        match chunk.chunk_data.as_slice().read_exact(&mut colors) {
-                            Ok(..) => {}
-                            Err(..) => {
-                                break;
-                            }
-                        }
+        for chunk in &self.png.chunks {
+            if self.options.descriptive {
+                write!(f, "{:#}", chunk)?;
+            } else {
+                write!(f, "{}", chunk)?;
+            }
+        }
 
-                        pallet.push((colors[0], colors[1], colors[2]));
-                    }
+        if let Some(trailing_data) = &self.png.trailing_data {
+            writeln!(f, "Trailing Data:")?;
+            if self.options.descriptive {
+                write!(f, "{:#}", trailing_data)?;
+            } else {
+                write!(f, "{}", trailing_data)?;
+            }
+        }
 
-                    if self.cli.display_options.descriptive == Some(true) {
-                        write!(f, "PLTE Chunk:\n").unwrap();
-                        for (index, colors) in pallet.iter().enumerate() {
-                            write!(f, " {}: ", index).unwrap();
-                            write!(f, "{:?}\n", colors).unwrap();
-                        }
-                    }
-                }
-                ChunkType::gAMA => {
-                    let mut image_gama: [u8; 4] = [0; 4];
-
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut image_gama)
-                        .unwrap();
-
-                    if self.cli.display_options.descriptive == Some(true) {
-                        write!(f, "gAMA Chunk:\n gama: {}", u32::from_be_bytes(image_gama))
-                            .unwrap();
-                    }
+        // What all the IDAT chunks hold once they're put together
+        let idat_count = self
+            .png
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type == ChunkType::IDAT)
+            .count();
+        let compressed = self.png.compressed_image_data();
+
+        writeln!(f, "Image Data:")?;
+        writeln!(f, " IDAT chunks: {}", idat_count)?;
+        writeln!(f, " compressed: {} bytes", compressed.len())?;
+        match self.png.image_data() {
+            Ok(inflated) if inflated.excess => writeln!(
+                f,
+                " decompressed: {} bytes, then extra data that's ignored",
+                inflated.data.len()
+            )?,
+            Ok(inflated) => writeln!(f, " decompressed: {} bytes", inflated.data.len())?,
+            Err(error) => writeln!(f, " decompressed: error, {}", error)?,
+        }
+
+        // The pixels are only decoded for the descriptive view, which needs them for
+        // the transparency counts
+        if self.options.descriptive {
+            let pixels = self.png.pixels();
+            match &pixels {
+                Ok(pixels) => writeln!(
+                    f,
+                    " pixels: {} x {}, {} channels at {} bits",
+                    pixels.width(),
+                    pixels.height(),
+                    pixels.channels(),
+                    pixels.bit_depth()
+                )?,
+                Err(error) => writeln!(f, " pixels: error, {}", error)?,
+            }
+
+            // Where transparency comes from, and how much of the image it covers
+            let has_trns = self
+                .png
+                .chunks
+                .iter()
+                .any(|chunk| chunk.chunk_type == ChunkType::tRNS);
+            let source = match (self.png.ihdr().color_type, self.png.transparency()) {
+                (4 | 6, _) => "alpha channel",
+                (_, Some(tRNS::Indexed { .. })) => "tRNS alpha for each pallet entry",
+                (_, Some(_)) => "tRNS key color",
+                (_, None) if has_trns => "tRNS is invalid, so every pixel is opaque",
+                (_, None) => "no tRNS chunk, so every pixel is opaque",
+            };
+
+            // Counted a pixel at a time, rather than converting the whole image
+            let counts = pixels.and_then(|pixels| {
+                pixels
+                    .pixels()
+                    .try_fold((0, 0), |(transparent, partial), pixel| {
+                        Ok(match pixels.rgba8(pixel)?[3] {
+                            0 => (transparent + 1, partial),
+                            255 => (transparent, partial),
+                            _ => (transparent, partial + 1),
+                        })
+                    })
+            });
+            match counts {
+                Ok((transparent, partial)) => writeln!(
+                    f,
+                    " transparency: {} fully & {} partly transparent pixels | {}",
+                    transparent, partial, source
+                )?,
+                Err(error) => writeln!(f, " transparency: error, {} | {}", error, source)?,
+            }
+        }
+
+        // Where each pass's pixels are taken from, and how much of the data it uses
+        if self.options.descriptive {
+            if let Some(passes) = self.png.interlace_passes() {
+                let header = self.png.ihdr();
+                writeln!(f, " Adam7 passes:")?;
+                for pass in passes {
+                    writeln!(
+                        f,
+                        "  pass {}: {} x {}, {} bytes | every {} x {} pixels from ({}, {})",
+                        pass.number,
+                        pass.width,
+                        pass.height,
+                        pass.byte_size(header).unwrap_or(usize::MAX),
+                        pass.x_step,
+                        pass.y_step,
+                        pass.x_start,
+                        pass.y_start
+                    )?;
                 }
-                ChunkType::sRGB => {
-                    let mut rendering_intent: [u8; 1] = [0];
+            }
+        }
 
-                    chunk
-                        .chunk_data
-                        .as_slice()
-                        .read_exact(&mut rendering_intent)
-                        .unwrap();
+        if let Some(actl) = self.png.animation_control() {
+            writeln!(f, "Animation:")?;
+            match self.png.frames() {
+                Ok(frames) => {
+                    let duration: f64 = frames.iter().map(|frame| frame.control.delay()).sum();
+                    writeln!(f, " frames: {}", frames.len())?;
+                    match actl.num_plays {
+                        0 => writeln!(f, " plays: forever")?,
+                        plays => writeln!(f, " plays: {}", plays)?,
+                    }
+                    writeln!(f, " duration: {:.3}s", duration)?;
 
-                    if self.cli.display_options.descriptive == Some(true) {
+                    for frame in frames {
+                        let control = &frame.control;
                         write!(
                             f,
-                            "sRGB Chunk:\n rendering intent: {:?}",
-                            u8::from_be_bytes(rendering_intent)
-                        )
-                        .unwrap();
-                    }
-                }
-                ChunkType::tIME => {
-                    let mut year: [u8; 2] = [0; 2];
-                    let mut month: [u8; 1] = [0; 1];
-                    let mut day: [u8; 1] = [0; 1];
-                    let mut hour: [u8; 1] = [0; 1];
-                    let mut minute: [u8; 1] = [0; 1];
-                    let mut second: [u8; 1] = [0; 1];
-                    chunk.chunk_data.as_slice().read_exact(&mut year).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut month).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut day).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut hour).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut minute).unwrap();
-                    chunk.chunk_data.as_slice().read_exact(&mut second).unwrap();
-
-                    if self.cli.display_options.descriptive == Some(true) {
-                        write!(f,
-                            "tIME Chunk:\n Year: {}\n Month: {}\n Day: {}\n Hour: {}\n Minute: {}\n Second: {}", 
-                            u16::from_be_bytes(year), u8::from_be_bytes(month), u8::from_be_bytes(day), u8::from_be_bytes(hour), u8::from_be_bytes(minute), u8::from_be_bytes(second)
-                        )
-                        .unwrap();
+                            "  frame {}: {} x {} at ({}, {}), {:.3}s, dispose {}, blend {}",
+                            frame.number,
+                            control.width,
+                            control.height,
+                            control.x_offset,
+                            control.y_offset,
+                            control.delay(),
+                            fcTL::dispose_op_name(control.dispose_op),
+                            fcTL::blend_op_name(control.blend_op)
+                        )?;
+                        if frame.is_default_image {
+                            write!(f, " (IDAT image)")?;
+                        }
+                        writeln!(f)?;
                     }
                 }
-                _ => {
-                    println!("{:?}", &chunk);
-                }
+                Err(error) => writeln!(f, " frames: error, {}", error)?,
             }
         }
+
+        let warnings = self.png.color_warnings();
+        if !warnings.is_empty() {
+            writeln!(f, "Color Warnings:")?;
+            for warning in warnings {
+                writeln!(f, " {}", warning)?;
+            }
+        }
+
         Ok(())
     }
 }
 
-trait Parse {
-    fn parse(chunk: Chunk) -> Self;
-}
+// One section per chunk. The alternate flag (`{:#}`) adds the descriptions.
+impl std::fmt::Display for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self.chunk_type {
+            ChunkType::Unknown(..) => writeln!(f, "{} Chunk (unknown):", self.chunk_type)?,
+            _ => writeln!(f, "{} Chunk:", self.chunk_type)?,
+        }
+
+        if !self.crc_matches() {
+            writeln!(
+                f,
+                " CRC mismatch at byte {}: expected {:#010x}, actual {:#010x}",
+                self.chunk_offset,
+                self.computed_crc,
+                u32::from_be_bytes(self.chunk_crc)
+            )?;
+        }
 
-// struct IHDR {
-//     width: u32,
-//     height: u32,
-//     bit_depth: u8,
-//     color_type: u8,
-//     compression_method: u8,
-//     filter_method: u8,
-//     interlace_method: u8,
-// }
-
-// struct PLTE {
-//     // R,G,B
-//     pallet: Vec<(u8, u8, u8)>,
-// }
-
-// struct IDAT {
-//     // Depends
-// }
-
-// // No Data
-// struct IEND {}
-
-// struct tRNS {
-//     //Color type 0:
-//     grey_sample_value: u16,
-
-//     // Type 2:
-//     red_sample_value: u16,
-//     blue_sample_value: u16,
-//     green_sample_value: u16,
-
-//     // Type 3
-//     pallet: Vec<u8>, // Type 4 & 6 are ignored as alpha is saved in IDAT
-// }
-
-// struct cHRM {
-//     white_x: u32,
-//     white_y: u32,
-
-//     red_x: u32,
-//     red_y: u32,
-
-//     green_x: u32,
-//     green_y: u32,
-
-//     blue_x: u32,
-//     blue_y: u32,
-// }
-
-// struct gAMA {
-//     image_gama: u32,
-// }
-
-// struct iCCP {
-//     profile_name: String, // Bytes 1-79
-
-//     //null_separator: u8,
-//     compression_method: u8,
-//     compressed_profile: Vec<u8>,
-// }
-
-// struct sBIT {
-//     // Type 0
-//     significant_greyscale_bit: u8,
-
-//     // Type 2 & 3
-//     significant_red_bits: u8,
-//     significant_green_bits: u8,
-//     significant_blue_bits: u8,
-
-//     significant_greyscale_bits: u8,
-//     significant_alpha_bits: u8,
-
-//     significant_red_bits: u8,
-//     significant_green_bits: u8,
-//     significant_blue_bits: u8,
-//     significant_alpha_bits: u8,
-// }
-
-// struct sRGB {
-//     rendering_intent: u8,
-// }
-
-// struct bKGD {
-//     // Type 0
-//     greyscale: u16,
-//     // Type 2 & 6
-//     red: u16,
-//     green: u16,
-//     blue: u16,
-//     // Type 3
-//     palette_index: u8,
-// }
-
-// struct hIST {
-//     histogram: Vec<u16>,
-// }
-
-// struct pHYs {
-//     pixels_per_x: u32,
-//     pixels_per_y: u32,
-//     unit_specifier: u8,
-// }
-
-// struct sPLT {
-//     palette_name: String, // First 1-79 bytes
-//     // null_seperator: u8,
-//     sample_depth: u8,
-//     red: u8, // Can be 16?
-//     green: u8,
-//     blue: u8,
-//     alpha: u8,
-//     frequency: u8,
-// }
-
-// struct tIME {
-//     year: u16,
-//     month: u8,
-//     day: u8,
-//     hour: u8,
-//     minute: u8,
-//     second: u8,
-// }
+        match self.parsed {
+            ParsedChunk::IDAT => writeln!(f, " length: {}", self.chunk_length)?,
+            ParsedChunk::Raw => {
+                writeln!(f, " length: {}", self.chunk_length)?;
+                writeln!(f, " data: {:?}", self.chunk_data)?;
+            }
+            _ => self.parsed.fmt(f)?,
+        }
+
+        if f.alternate() {
+            let chunk_type = &self.chunk_type;
+
+            writeln!(
+                f,
+                " properties: {}, {}, {}, {}",
+                if chunk_type.is_ancillary() {
+                    "ancillary"
+                } else {
+                    "critical"
+                },
+                if chunk_type.is_private() {
+                    "private"
+                } else {
+                    "public"
+                },
+                if chunk_type.is_reserved() {
+                    "reserved bit set"
+                } else {
+                    "reserved bit clear"
+                },
+                if chunk_type.is_safe_to_copy() {
+                    "safe to copy"
+                } else {
+                    "unsafe to copy"
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
 
 // Chunk Types
 // http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html
 // Used to tell the parser what the data is used for.
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
-enum ChunkType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkType {
     // Critical chunks
     // https://www.w3.org/TR/2003/REC-PNG-20031110/#11Critical-chunks
     IHDR, // 73, 72, 68, 62
@@ -328,86 +335,508 @@ enum ChunkType {
     sPLT, // 115, 80, 76, 84
     hIST, // 104, 73, 83, 84
     tIME, // 116, 73, 77, 69
+
+    // Animation chunks
+    // https://www.w3.org/TR/png-3/#apng-chunks
+    acTL, // 97, 99, 84, 76
+    fcTL, // 102, 99, 84, 76
+    fdAT, // 102, 100, 65, 84
+
+    // Chunks added in the third edition
+    // https://www.w3.org/TR/png-3/#11Ancillary-chunks
+    cICP, // 99, 73, 67, 80
+    mDCV, // 109, 68, 67, 86
+    cLLI, // 99, 76, 76, 73
+    eXIf, // 101, 88, 73, 102
+
+    // Registered extension chunks
+    // https://ftp-osl.osuosl.org/pub/libpng/documents/pngext-1.5.0.html
+    oFFs, // 111, 70, 70, 115
+    pCAL, // 112, 67, 65, 76
+    sCAL, // 115, 67, 65, 76
+    sTER, // 115, 84, 69, 82
+    gIFg, // 103, 73, 70, 103
+    gIFx, // 103, 73, 70, 120
+
+    // Anything not listed above, such as private & vendor chunks (Apple's iDOT).
+    // Holds the chunk type exactly as it was in the datastream.
+    Unknown([u8; 4]),
 }
 
-impl From<Cli> for Png {
-    // Iterate over the whole file, emmiting PNG at end.
-    fn from(cli: Cli) -> Png {
-        let path = match cli.file_path.clone() {
-            Some(file_path) => file_path,
-            None => {
-                panic!("Error, can't read path from CLI");
-            }
-        };
+impl Png {
+    // Iterate over the whole datastream, emmiting PNG at end.
+    // Decodes with the default (strict) options.
+    pub fn from_reader(reader: impl Read) -> Result<Png, PngError> {
+        Png::from_reader_with_options(reader, &ParseOptions::default())
+    }
 
-        let mut file = match File::open(path) {
-            Ok(x) => x,
-            Err(..) => {
-                panic!("Error, can't read file");
-            }
-        };
+    pub fn from_reader_with_options(
+        reader: impl Read,
+        options: &ParseOptions,
+    ) -> Result<Png, PngError> {
+        let mut stream = StreamReader::new(reader);
 
         let mut png_metadata: [u8; 8] = [0; 8];
-        match file.read_exact(&mut png_metadata) {
-            Ok(x) => x,
-            Err(..) => {
-                panic!("Error, PNG signature not found")
-            }
-        }
+        let signature_length = stream.fill(&mut png_metadata)?;
 
-        if !(png_metadata == [137, 80, 78, 71, 13, 10, 26, 10]) {
-            panic!("Error, the PNG File Signature is incorrect")
+        if png_metadata != PNG_SIGNATURE {
+            return Err(PngError::InvalidSignature {
+                found: png_metadata[..signature_length].to_vec(),
+            });
         }
 
-        println!("{:?}", png_metadata);
-
         let mut png = Png {
             metadata: png_metadata,
             chunks: Vec::new(),
-            cli,
+            trailing_data: None,
+            image_data: OnceCell::new(),
+            pixels: OnceCell::new(),
         };
 
-        let mut chunk_type: [u8; 4] = [0; 4];
-        let mut chunk_length: [u8; 4] = [0; 4];
-        // let mut chunk_data: Vec<u8> = Vec::new(); - Length created at run-time, so we put it in the loop
+        // Length & Type, read together so a short read can be told apart from the end of the datastream
+        let mut chunk_header: [u8; 8] = [0; 8];
         let mut chunk_crc: [u8; 4] = [0; 4];
+        let mut seen_iend = false;
 
-        // TODO: Better Error Management
         loop {
-            match file.read_exact(&mut chunk_length) {
-                Err(..) => (),
-                _ => (),
+            let chunk_offset = stream.offset;
+            let chunk_index = png.chunks.len();
+
+            // Running out of data between chunks marks the end of the datastream
+            match stream.fill(&mut chunk_header)? {
+                0 => break,
+                8 => (),
+                _ => {
+                    return Err(PngError::TruncatedChunkHeader {
+                        offset: chunk_offset,
+                        chunk_index,
+                    })
+                }
             }
 
             // PNG Uses Big-Edian
             // https://www.w3.org/TR/2003/REC-PNG-20031110/#7Integers-and-byte-order
-            let chunk_length_usize = u32::from_be_bytes(chunk_length).try_into().unwrap();
-
-            match file.read_exact(&mut chunk_type) {
-                Err(..) => break,
-                _ => (),
+            let chunk_length = u32::from_be_bytes([
+                chunk_header[0],
+                chunk_header[1],
+                chunk_header[2],
+                chunk_header[3],
+            ]);
+            let chunk_type = [
+                chunk_header[4],
+                chunk_header[5],
+                chunk_header[6],
+                chunk_header[7],
+            ];
+
+            if chunk_length > MAX_CHUNK_LENGTH {
+                return Err(PngError::OversizedLength {
+                    offset: chunk_offset,
+                    chunk_index,
+                    length: chunk_length,
+                });
+            }
+            let chunk_length_usize = chunk_length as usize;
+
+            let chunk_data = stream.fill_vec(chunk_length_usize)?;
+            let crc_length = stream.fill(&mut chunk_crc)?;
+
+            if chunk_data.len() < chunk_length_usize || crc_length < chunk_crc.len() {
+                return Err(PngError::TruncatedChunkData {
+                    offset: chunk_offset,
+                    chunk_index,
+                    chunk_type,
+                    needed: chunk_length_usize + chunk_crc.len(),
+                    available: chunk_data.len() + crc_length,
+                });
             }
-            let mut chunk_data = vec![0; chunk_length_usize];
 
-            match file.read_exact(&mut chunk_data) {
-                Err(..) => (),
-                _ => (),
+            // Checked first, as a damaged chunk type can't be trusted to say what the chunk is
+            let computed_crc = crc::chunk_crc(&chunk_type, &chunk_data);
+            let stored_crc = u32::from_be_bytes(chunk_crc);
+            let crc_matches = computed_crc == stored_crc;
+
+            if !crc_matches && options.crc_check == CrcCheck::Strict {
+                return Err(PngError::CrcMismatch {
+                    offset: chunk_offset,
+                    chunk_index,
+                    chunk_type,
+                    expected: computed_crc,
+                    actual: stored_crc,
+                });
             }
 
-            match file.read_exact(&mut chunk_crc) {
-                Err(..) => (),
-                _ => (),
+            // Decoders have to stop at critical chunks they don't understand, as the image can't be shown without them.
+            // A chunk with a bad CRC is kept when decoding leniently, as its type may just be damaged.
+            // https://www.w3.org/TR/2003/REC-PNG-20031110/#13Chunk-processing
+            let parsed_chunk_type = ChunkType::from(chunk_type);
+            if matches!(parsed_chunk_type, ChunkType::Unknown(..))
+                && parsed_chunk_type.is_critical()
+                && crc_matches
+            {
+                return Err(PngError::UnknownCriticalChunk {
+                    offset: chunk_offset,
+                    chunk_index,
+                    chunk_type,
+                });
             }
 
-            png.chunks.push(Chunk {
-                chunk_type: ChunkType::from(chunk_type),
+            let mut chunk = Chunk {
+                chunk_type: parsed_chunk_type,
+                chunk_offset,
                 chunk_length: chunk_length_usize,
                 chunk_data,
                 chunk_crc,
+                computed_crc,
+                parsed: ParsedChunk::Raw,
+            };
+
+            let context = ParseContext {
+                header: png.find_ihdr(),
+                pallet: png.palette(),
+            };
+            chunk.parsed = match ParsedChunk::parse(&chunk, &context) {
+                Ok(parsed) => parsed,
+                // The image can't be understood without its critical chunks
+                Err(error) if parsed_chunk_type.is_critical() => {
+                    return Err(PngError::InvalidChunk {
+                        offset: chunk_offset,
+                        chunk_index,
+                        chunk_type,
+                        error,
+                    })
+                }
+                Err(error) => ParsedChunk::Invalid(error),
+            };
+
+            png.chunks.push(chunk);
+
+            // IEND ends the chunk stream, whatever comes after isn't chunks
+            // https://www.w3.org/TR/2003/REC-PNG-20031110/#11IEND
+            if parsed_chunk_type == ChunkType::IEND {
+                seen_iend = true;
+
+                let offset = stream.offset;
+                let data = stream.read_rest()?;
+                if !data.is_empty() {
+                    png.trailing_data = Some(TrailingData { offset, data });
+                }
+                break;
+            }
+        }
+
+        if !seen_iend {
+            return Err(PngError::UnexpectedEof {
+                offset: stream.offset,
+                chunk_index: png.chunks.len(),
             });
         }
 
-        return png;
+        if png.find_ihdr().is_none() {
+            return Err(PngError::MissingIhdr);
+        }
+
+        Ok(png)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Png, PngError> {
+        Png::from_reader(bytes)
+    }
+
+    pub fn from_bytes_with_options(bytes: &[u8], options: &ParseOptions) -> Result<Png, PngError> {
+        Png::from_reader_with_options(bytes, options)
+    }
+
+    // The 8 byte signature the datastream started with.
+    pub fn signature(&self) -> [u8; 8] {
+        self.metadata
+    }
+
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    // The image header. Decoding fails without one, so this is always there.
+    pub fn ihdr(&self) -> &IHDR {
+        self.find_ihdr()
+            .expect("Png is only created once an IHDR chunk was found")
+    }
+
+    fn find_ihdr(&self) -> Option<&IHDR> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::IHDR(ihdr) => Some(ihdr),
+            _ => None,
+        })
+    }
+
+    // The PLTE chunk, if there is one
+    pub fn palette(&self) -> Option<&PLTE> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::PLTE(plte) => Some(plte),
+            _ => None,
+        })
+    }
+
+    // The seven Adam7 passes, or None if the image isn't interlaced
+    pub fn interlace_passes(&self) -> Option<[Adam7Pass; 7]> {
+        match self.ihdr().interlace_method {
+            1 => Some(interlace::adam7_passes(self.ihdr())),
+            _ => None,
+        }
+    }
+
+    // The bytes after IEND, if there are any
+    pub fn trailing_data(&self) -> Option<&TrailingData> {
+        self.trailing_data.as_ref()
+    }
+
+    // The embedded ICC profile chunk, if there is one and it's valid
+    pub fn iccp(&self) -> Option<&iCCP> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::iCCP(iccp) => Some(iccp),
+            _ => None,
+        })
+    }
+
+    // The color space code points, which take priority over the other color chunks
+    pub fn cicp(&self) -> Option<&cICP> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::cICP(cicp) => Some(cicp),
+            _ => None,
+        })
+    }
+
+    // The Exif metadata chunk, if there is one and it's valid
+    pub fn exif(&self) -> Option<&eXIf> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::eXIf(exif) => Some(exif),
+            _ => None,
+        })
+    }
+
+    // How samples turn into physical values, from pCAL
+    pub fn calibration(&self) -> Option<&pCAL> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::pCAL(calibration) => Some(calibration),
+            _ => None,
+        })
+    }
+
+    // When the image was last changed, from tIME
+    pub fn last_modified(&self) -> Option<&tIME> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::tIME(time) => Some(time),
+            _ => None,
+        })
+    }
+
+    // The transparency chunk, if there is one and it's valid
+    pub fn transparency(&self) -> Option<&tRNS> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::tRNS(trns) => Some(trns),
+            _ => None,
+        })
+    }
+
+    // All IDAT chunks' data, joined back into the one zlib stream it was split from
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#10CompressionOtherUses
+    pub fn compressed_image_data(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type == ChunkType::IDAT)
+            .flat_map(|chunk| chunk.chunk_data.iter().copied())
+            .collect()
+    }
+
+    // The decompressed image data. Still filtered, one filter type byte per scanline.
+    // Anything past what the image's size needs is left off, and noted in `excess`.
+    pub fn image_data(&self) -> Result<&Inflated, DecompressError> {
+        self.image_data
+            .get_or_init(|| {
+                // An image too big for its size to fit in a usize fails when it's unfiltered
+                let limit = self.ihdr().image_data_size().unwrap_or(usize::MAX);
+                inflate::zlib_decompress_prefix(&self.compressed_image_data(), limit)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    // The image's pixels, decompressed & unfiltered
+    pub fn pixels(&self) -> Result<&PixelBuffer, ImageError> {
+        self.pixels
+            .get_or_init(|| {
+                PixelBuffer::from_image_data(
+                    self.ihdr(),
+                    self.palette(),
+                    self.transparency(),
+                    &self.image_data()?.data,
+                )
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    // The animation control chunk, only there for APNGs
+    pub fn animation_control(&self) -> Option<&acTL> {
+        self.chunks.iter().find_map(|chunk| match &chunk.parsed {
+            ParsedChunk::acTL(actl) => Some(actl),
+            _ => None,
+        })
+    }
+
+    // The APNG's frames, in the order they're shown
+    pub fn frames(&self) -> Result<Vec<Frame>, AnimationError> {
+        animation::frames(self)
+    }
+
+    // Every frame drawn onto the full size canvas, as 8 bit RGBA. Each canvas is drawn
+    // as it's asked for.
+    pub fn canvases(&self) -> Result<Canvases<'_>, AnimationError> {
+        Ok(Canvases::new(self, self.frames()?))
+    }
+
+    // Every chunk's decoded form, in the order they appear
+    pub fn parsed_chunks(&self) -> impl Iterator<Item = &ParsedChunk> {
+        self.chunks.iter().map(|chunk| &chunk.parsed)
+    }
+
+    // Every keyword & text pair from the tEXt, zTXt & iTXt chunks, decompressed
+    pub fn text(&self) -> Vec<(&str, Result<String, ChunkError>)> {
+        self.parsed_chunks()
+            .filter_map(|parsed| match parsed {
+                ParsedChunk::tEXt(chunk) => Some((chunk.keyword.as_str(), Ok(chunk.text.clone()))),
+                ParsedChunk::zTXt(chunk) => Some((chunk.keyword.as_str(), chunk.text())),
+                ParsedChunk::iTXt(chunk) => Some((chunk.keyword.as_str(), chunk.text())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Places where gAMA, cHRM, sRGB & iCCP contradict each other
+    pub fn color_warnings(&self) -> Vec<ColorWarning> {
+        consistency::check(self)
+    }
+
+    // Problems with the chunk order, repeated chunks & chunks the color type needs
+    pub fn validate(&self) -> Vec<Finding> {
+        validate::validate(self)
+    }
+
+    pub fn display<'a>(&'a self, options: &'a DisplayOptions) -> PngDisplay<'a> {
+        PngDisplay { png: self, options }
+    }
+}
+
+impl Chunk {
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    // Byte offset of the start of the chunk (its length field) in the datastream
+    pub fn offset(&self) -> u64 {
+        self.chunk_offset
+    }
+
+    // Length of the data field, as stored in the chunk
+    pub fn length(&self) -> usize {
+        self.chunk_length
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.chunk_data
+    }
+
+    // The decoded form of the data
+    pub fn parsed(&self) -> &ParsedChunk {
+        &self.parsed
+    }
+
+    // The CRC stored in the chunk
+    pub fn crc(&self) -> [u8; 4] {
+        self.chunk_crc
+    }
+
+    // The CRC calculated over the chunk type & data
+    pub fn computed_crc(&self) -> u32 {
+        self.computed_crc
+    }
+
+    // Only false when decoding with `CrcCheck::Lenient`, strict decoding stops at the first bad CRC
+    pub fn crc_matches(&self) -> bool {
+        self.computed_crc == u32::from_be_bytes(self.chunk_crc)
+    }
+}
+
+// Wraps the reader handed to `Png::from_reader`, keeping track of how far into
+// the datastream we are so errors can say where they happened.
+struct StreamReader<R> {
+    reader: R,
+    offset: u64,
+}
+
+impl<R: Read> StreamReader<R> {
+    fn new(reader: R) -> Self {
+        StreamReader { reader, offset: 0 }
+    }
+
+    // Reads until `buffer` is full or the datastream ends, returning how many bytes were read.
+    fn fill(&mut self, buffer: &mut [u8]) -> Result<usize, PngError> {
+        let mut filled = 0;
+
+        while filled < buffer.len() {
+            match self.reader.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(read) => {
+                    filled += read;
+                    self.offset += read as u64;
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => (),
+                Err(source) => {
+                    return Err(PngError::Io {
+                        offset: self.offset,
+                        source,
+                    })
+                }
+            }
+        }
+
+        Ok(filled)
+    }
+
+    // Reads everything left in the datastream
+    fn read_rest(&mut self) -> Result<Vec<u8>, PngError> {
+        let mut buffer = Vec::new();
+
+        match self.reader.read_to_end(&mut buffer) {
+            Ok(read) => {
+                self.offset += read as u64;
+                Ok(buffer)
+            }
+            Err(source) => Err(PngError::Io {
+                offset: self.offset + buffer.len() as u64,
+                source,
+            }),
+        }
+    }
+
+    // Reads up to `length` bytes. The buffer grows as data arrives rather than up front,
+    // so a corrupt length on a short file can't make us allocate gigabytes.
+    fn fill_vec(&mut self, length: usize) -> Result<Vec<u8>, PngError> {
+        let mut buffer = Vec::new();
+
+        match (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut buffer)
+        {
+            Ok(read) => {
+                self.offset += read as u64;
+                Ok(buffer)
+            }
+            Err(source) => Err(PngError::Io {
+                offset: self.offset + buffer.len() as u64,
+                source,
+            }),
+        }
     }
 }
 
@@ -437,503 +866,335 @@ impl From<[u8; 4]> for ChunkType {
             [104, 73, 83, 84] => ChunkType::hIST,
             [116, 73, 77, 69] => ChunkType::tIME,
 
-            _ => {
-                println!("Unkown Chunk Type");
-                ChunkType::tIME
-            }
+            [97, 99, 84, 76] => ChunkType::acTL,
+            [102, 99, 84, 76] => ChunkType::fcTL,
+            [102, 100, 65, 84] => ChunkType::fdAT,
+
+            [99, 73, 67, 80] => ChunkType::cICP,
+            [109, 68, 67, 86] => ChunkType::mDCV,
+            [99, 76, 76, 73] => ChunkType::cLLI,
+            [101, 88, 73, 102] => ChunkType::eXIf,
+
+            [111, 70, 70, 115] => ChunkType::oFFs,
+            [112, 67, 65, 76] => ChunkType::pCAL,
+            [115, 67, 65, 76] => ChunkType::sCAL,
+            [115, 84, 69, 82] => ChunkType::sTER,
+            [103, 73, 70, 103] => ChunkType::gIFg,
+            [103, 73, 70, 120] => ChunkType::gIFx,
+
+            _ => ChunkType::Unknown(chunk_identifier),
+        }
+    }
+}
+
+impl From<ChunkType> for [u8; 4] {
+    // The 4 bytes the chunktype is written as
+    fn from(chunk_type: ChunkType) -> [u8; 4] {
+        match chunk_type {
+            ChunkType::IHDR => *b"IHDR",
+            ChunkType::PLTE => *b"PLTE",
+            ChunkType::IDAT => *b"IDAT",
+            ChunkType::IEND => *b"IEND",
+
+            ChunkType::tRNS => *b"tRNS",
+            ChunkType::gAMA => *b"gAMA",
+            ChunkType::cHRM => *b"cHRM",
+            ChunkType::sRGB => *b"sRGB",
+            ChunkType::iCCP => *b"iCCP",
+
+            ChunkType::tEXt => *b"tEXt",
+            ChunkType::zTXt => *b"zTXt",
+            ChunkType::iTXt => *b"iTXt",
+
+            ChunkType::bKGD => *b"bKGD",
+            ChunkType::pHYs => *b"pHYs",
+            ChunkType::sBIT => *b"sBIT",
+            ChunkType::sPLT => *b"sPLT",
+            ChunkType::hIST => *b"hIST",
+            ChunkType::tIME => *b"tIME",
+
+            ChunkType::acTL => *b"acTL",
+            ChunkType::fcTL => *b"fcTL",
+            ChunkType::fdAT => *b"fdAT",
+
+            ChunkType::cICP => *b"cICP",
+            ChunkType::mDCV => *b"mDCV",
+            ChunkType::cLLI => *b"cLLI",
+            ChunkType::eXIf => *b"eXIf",
+
+            ChunkType::oFFs => *b"oFFs",
+            ChunkType::pCAL => *b"pCAL",
+            ChunkType::sCAL => *b"sCAL",
+            ChunkType::sTER => *b"sTER",
+            ChunkType::gIFg => *b"gIFg",
+            ChunkType::gIFx => *b"gIFx",
+
+            ChunkType::Unknown(chunk_identifier) => chunk_identifier,
+        }
+    }
+}
+
+// The case of each letter of a chunk type is a property bit (bit 5 of the byte, lowercase when set).
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5Chunk-naming-conventions
+impl ChunkType {
+    fn property_bit(&self, index: usize) -> bool {
+        <[u8; 4]>::from(*self)[index] & 0x20 != 0
+    }
+
+    // Ancillary chunks can be ignored, critical ones are needed to display the image.
+    pub fn is_ancillary(&self) -> bool {
+        self.property_bit(0)
+    }
+
+    pub fn is_critical(&self) -> bool {
+        !self.is_ancillary()
+    }
+
+    // Private chunks aren't part of the spec or registered with the PNG group.
+    pub fn is_private(&self) -> bool {
+        self.property_bit(1)
+    }
+
+    // Should always be false, the bit is reserved for future use.
+    pub fn is_reserved(&self) -> bool {
+        self.property_bit(2)
+    }
+
+    // Whether an editor that doesn't understand the chunk may still copy it into a modified file.
+    pub fn is_safe_to_copy(&self) -> bool {
+        self.property_bit(3)
+    }
+}
+
+// Writes the 4 letter code, escaping anything that isn't printable ASCII
+// (only possible for unknown chunks, and only in broken files).
+impl std::fmt::Display for ChunkType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in <[u8; 4]>::from(*self) {
+            write!(f, "{}", std::ascii::escape_default(byte))?;
         }
+        Ok(())
     }
 }
 
-// impl Png {
-//     pub fn from_cli(input: Cli) -> () {
-//         let path = match input.path {
-//             Some(input_path) => input_path,
-//             None => {
-//                 panic!("File Path Not Given");
-//             }
-//         };
-
-//         let output = match input.output {
-//             Some(input_output) => input_output,
-//             None => {
-//                 panic!("Output Type Not Given");
-//             }
-//         };
-
-//         let mut f = File::open(path).unwrap();
-
-//         let mut metadata: [u8; 8] = [0; 8];
-//         let mut color_type: u8 = 0;
-
-//         f.read_exact(&mut metadata).expect("error");
-
-//         println!("PNG METADATA: {:?} | Magic Numbers", metadata);
-//         loop {
-//             let mut chunk_length: [u8; 4] = [0; 4];
-//             match f.read_exact(&mut chunk_length) {
-//                 Err(..) => break,
-//                 _ => (),
-//             };
-
-//             let mut chunk_type: [u8; 4] = [0; 4];
-//             f.read_exact(&mut chunk_type).expect("error");
-
-//             let mut chunk_data = vec![0; u32::from_be_bytes(chunk_length).try_into().unwrap()];
-//             f.read_exact(&mut chunk_data).expect("error");
-
-//             let mut crc: [u8; 4] = [0; 4];
-//             f.read_exact(&mut crc).expect("error");
-
-//             match ChunkTypes::from_bytes(chunk_type) {
-//                 ChunkTypes::IEND => {
-//                     println!("\nIEND CHUNK: \n (NO DATA)");
-//                 }
-//                 ChunkTypes::IDAT => {
-//                     println!("\nIDAT CHUNK:");
-//                     Color::from_color_type(color_type.clone(), chunk_data);
-//                 }
-//                 ChunkTypes::IHDR => {
-//                     let mut data = chunk_data.as_slice();
-
-//                     let mut byte_width: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut byte_width).unwrap();
-
-//                     let mut byte_height: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut byte_height).unwrap();
-
-//                     let mut byte_bit_depth: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_bit_depth).unwrap();
-
-//                     let mut byte_color_type: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_color_type).unwrap();
-
-//                     let mut byte_compression: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_compression).unwrap();
-
-//                     let mut byte_filter_method: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_filter_method).unwrap();
-
-//                     let mut byte_interlase_method: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_interlase_method).unwrap();
-
-//                     let width: usize = u32::from_be_bytes(byte_width).try_into().unwrap();
-//                     let height: usize = u32::from_be_bytes(byte_height).try_into().unwrap();
-//                     let bit_depth: usize = u8::from_be_bytes(byte_bit_depth).try_into().unwrap();
-//                     color_type = u8::from_be_bytes(byte_color_type).try_into().unwrap();
-//                     let compression: usize =
-//                         u8::from_be_bytes(byte_compression).try_into().unwrap();
-//                     let filter_method: usize =
-//                         u8::from_be_bytes(byte_filter_method).try_into().unwrap();
-//                     let interlase_method: usize =
-//                         u8::from_be_bytes(byte_interlase_method).try_into().unwrap();
-
-//                     if output == Output::Descriptive {
-//                         println!("\nIHDR CHUNK: \n WIDTH:           {} | Width in Pixels\n HEIGHT:          {} | Height in Pixels\n BIT_DEPTH        {} | Bits Per Sample / Bits per pallet index\n COLOR_TYPE       {} | Allowed Bit Depths. 0 = Greyscale; 2 = RGB; 3 = Pallet Index; 4 = Greyscale + Alpha; 6 = RGB + Alpha\n COMPRESSION      {} | Only \"0\" is defined.\n FILTER_METHOD    {} | Any preprossessing applied before compression. \n INTERLASE_METHOD {} | Type of interlasing. 0 = None; 1 = Adam7",
-//                                  &width, &height, &bit_depth, &color_type, &compression, &filter_method, &interlase_method);
-//                     } else {
-//                         println!("\nIHDR CHUNK: \n WIDTH: {}\n HEIGHT: {}\n BIT_DEPTH {}\n COLOR_TYPE {}\n COMPRESSION {}\n FILTER_METHOD {}\n INTERLASE_METHOD {}",
-//                                  &width, &height, &bit_depth, &color_type, &compression, &filter_method, &interlase_method);
-//                     }
-//                 }
-//                 ChunkTypes::PLTE => {
-//                     if output == Output::Descriptive {
-//                         println!("\nPLTE CHUNK: \n PALLET_DATA: {:?} | This is an indexed list of 255 colors in the image", chunk_data);
-//                     } else {
-//                         println!("\nPLTE CHUNK: \n PALLET_DATA: {:?}", chunk_data);
-//                     }
-//                 }
-//                 ChunkTypes::tRNS => {
-//                     if output == Output::Descriptive {
-//                         println!("\ntRNS CHUNK: \n ALPHA_DATA: {:?} | This is an indexed list holding each alpha channel for all 255 colors in PLTE, but only includes alpha", chunk_data);
-//                     } else {
-//                         println!("\ntRNS CHUNK: \n ALPHA_DATA: {:?}", chunk_data);
-//                     }
-//                 }
-//                 ChunkTypes::gAMA => {
-//                     let mut byte_data: [u8; 4] = [0; 4];
-//                     chunk_data.as_slice().read_exact(&mut byte_data).unwrap();
-
-//                     if output == Output::Descriptive {
-//                         println!(
-//                             "\ngAMA CHUNK: \n GAMMA: {} | Holds gamma times 10,000",
-//                             u32::from_be_bytes(byte_data)
-//                         );
-//                     } else {
-//                         println!("\ngAMA CHUNK: \n GAMMA: {}", u32::from_be_bytes(byte_data));
-//                     }
-//                 }
-//                 ChunkTypes::cHRM => {
-//                     let mut data = chunk_data.as_slice();
-
-//                     let mut whitex: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut whitex).unwrap();
-
-//                     let mut whitey: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut whitey).unwrap();
-
-//                     let mut redx: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut redx).unwrap();
-
-//                     let mut redy: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut redy).unwrap();
-
-//                     let mut greenx: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut greenx).unwrap();
-
-//                     let mut greeny: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut greeny).unwrap();
-
-//                     let mut bluex: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut bluex).unwrap();
-
-//                     let mut bluey: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut bluey).unwrap();
-
-//                     if output == Output::Descriptive {
-//                         println!("\ncHRM CHUNK: \n WHITE POINT ({}, {}) | The point which should always be displayed as white \n RED ({}, {}) | Red chromaticy \n GREEN ({}, {}) | Green chromaticy \n BLUE ({}, {}) | Blue chromaticy", u32::from_be_bytes(whitex), u32::from_be_bytes(whitey), u32::from_be_bytes(redx), u32::from_be_bytes(redy), u32::from_be_bytes(greenx), u32::from_be_bytes(greeny), u32::from_be_bytes(bluex),u32::from_be_bytes(bluey));
-//                     } else {
-//                         println!("\ncHRM CHUNK: \n WHITE POINT ({}, {}) \n RED ({}, {}) \n GREEN ({}, {}) \n BLUE ({}, {})", u32::from_be_bytes(whitex), u32::from_be_bytes(whitey), u32::from_be_bytes(redx), u32::from_be_bytes(redy), u32::from_be_bytes(greenx), u32::from_be_bytes(greeny), u32::from_be_bytes(bluex),u32::from_be_bytes(bluey));
-//                     }
-//                 }
-//                 ChunkTypes::sRGB => {
-//                     let mut srgb: [u8; 1] = [0; 1];
-//                     chunk_data.as_slice().read_exact(&mut srgb).unwrap();
-
-//                     if output == Output::Descriptive {
-//                         println!("\nsRGB CHUNK: \n RENDERING INTENT: {} | Modes for sRGB, 0=Perseptual;1=Relative;2=Saturation;3=Absolute", u8::from_be_bytes(srgb));
-//                     } else {
-//                         println!(
-//                             "\nsRGB CHUNK: \n RENDERING INTENT: {}",
-//                             u8::from_be_bytes(srgb)
-//                         );
-//                     }
-//                 }
-
-//                 ChunkTypes::bKGD => match color_type.clone() {
-//                     0 | 4 => {
-//                         let mut grey: [u8; 2] = [0; 2];
-//                         chunk_data.as_slice().read_exact(&mut grey).unwrap();
-
-//                         println!(
-//                             "\nbKGD CHUNK: \n GREYSCALE_BACKGROUND {}",
-//                             u16::from_be_bytes(grey)
-//                         );
-//                     }
-//                     2 | 6 => {
-//                         let mut data = chunk_data.as_slice();
-
-//                         let mut red: [u8; 2] = [0; 2];
-//                         data.read_exact(&mut red).unwrap();
-
-//                         let mut blue: [u8; 2] = [0; 2];
-//                         data.read_exact(&mut blue).unwrap();
-
-//                         let mut green: [u8; 2] = [0; 2];
-//                         data.read_exact(&mut green).unwrap();
-
-//                         println!(
-//                             "\nbKGD CHUNK: \n RGB_BACKGROUND ({}, {} , {})",
-//                             u16::from_be_bytes(red),
-//                             u16::from_be_bytes(green),
-//                             u16::from_be_bytes(blue)
-//                         );
-//                     }
-//                     3 => {
-//                         let mut index: [u8; 1] = [0; 1];
-//                         chunk_data.as_slice().read_exact(&mut index).unwrap();
-
-//                         println!(
-//                             "\nbKGD CHUNK: \n PALLET_BACKGROUND {}",
-//                             u8::from_be_bytes(index)
-//                         );
-//                     }
-//                     _ => {}
-//                 },
-//                 ChunkTypes::pHYs => {
-//                     let mut data = chunk_data.as_slice();
-
-//                     let mut sizex: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut sizex).unwrap();
-
-//                     let mut sizey: [u8; 4] = [0; 4];
-//                     data.read_exact(&mut sizey).unwrap();
-
-//                     let mut unit: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut unit).unwrap();
-
-//                     if output == Output::Descriptive {
-//                         println!("\npHYs CHUNK: \n PIXEL_PER_UNIT_X {} \n PIXEL_PER_UNIT_Y {} \n UNIT {} | 0=Unknown;1=Meter", u32::from_be_bytes(sizex), u32::from_be_bytes(sizey), u8::from_be_bytes(unit));
-//                     } else {
-//                         println!("\npHYs CHUNK: \n PIXEL_PER_UNIT_X {} \n PIXEL_PER_UNIT_Y {} \n UNIT {}", u32::from_be_bytes(sizex), u32::from_be_bytes(sizey), u8::from_be_bytes(unit));
-//                     }
-//                 }
-//                 ChunkTypes::sBIT => match color_type.clone() {
-//                     0 => {
-//                         let mut sig: [u8; 1] = [0; 1];
-//                         chunk_data.as_slice().read_exact(&mut sig).unwrap();
-
-//                         println!(
-//                             "\nsBIT CHUNK: \n SIGNIFICANT_BITS: {}",
-//                             u8::from_be_bytes(sig)
-//                         );
-//                     }
-//                     2 => {
-//                         let mut data = chunk_data.as_slice();
-
-//                         let mut red: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut red).unwrap();
-
-//                         let mut blue: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut blue).unwrap();
-
-//                         let mut green: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut green).unwrap();
-
-//                         println!(
-//                             "\nsBIT CHUNK: \n SIGNIFICANT_BITS: ({}, {}, {})",
-//                             u8::from_be_bytes(red),
-//                             u8::from_be_bytes(blue),
-//                             u8::from_be_bytes(green)
-//                         );
-//                     }
-//                     4 => {
-//                         let mut data = chunk_data.as_slice();
-
-//                         let mut grey: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut grey).unwrap();
-
-//                         let mut alpha: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut alpha).unwrap();
-
-//                         let mut green: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut green).unwrap();
-
-//                         println!(
-//                             "\nsBIT CHUNK: \n SIGNIFICANT_BITS: ({}, {})",
-//                             u8::from_be_bytes(grey),
-//                             u8::from_be_bytes(alpha)
-//                         );
-//                     }
-//                     6 => {
-//                         let mut data = chunk_data.as_slice();
-
-//                         let mut red: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut red).unwrap();
-
-//                         let mut blue: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut blue).unwrap();
-
-//                         let mut green: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut green).unwrap();
-
-//                         let mut alpha: [u8; 1] = [0; 1];
-//                         data.read_exact(&mut alpha).unwrap();
-
-//                         println!(
-//                             "\nsBIT CHUNK: \n SIGNIFICANT_BITS: ({}, {}, {}, {})",
-//                             u8::from_be_bytes(red),
-//                             u8::from_be_bytes(blue),
-//                             u8::from_be_bytes(green),
-//                             u8::from_be_bytes(alpha)
-//                         );
-//                     }
-
-//                     _ => {}
-//                 },
-//                 ChunkTypes::tIME => {
-//                     let mut data = chunk_data.as_slice();
-
-//                     let mut year: [u8; 2] = [0; 2];
-//                     data.read_exact(&mut year).unwrap();
-
-//                     let mut month: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut month).unwrap();
-
-//                     let mut day: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut day).unwrap();
-
-//                     let mut hour: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut hour).unwrap();
-
-//                     let mut minute: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut minute).unwrap();
-
-//                     let mut second: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut second).unwrap();
-
-//                     println!("\ntIME CHUNK: \n YEAR: {} \n MONTH: {} \n DAY: {} \n HOUR: {} \n MINUTE: {} \n SECOND: {}", u16::from_be_bytes(year), u8::from_be_bytes(month), u8::from_be_bytes(day), u8::from_be_bytes(hour), u8::from_be_bytes(minute), u8::from_be_bytes(second));
-//                 }
-//                 _ => (),
-//             }
-//         }
-//     }
-// }
-
-// #[derive(Debug)]
-// pub struct Color {
-//     red: u8,
-//     green: u8,
-//     blue: u8,
-// }
-
-// #[allow(non_camel_case_types)]
-// #[derive(PartialEq)]
-// enum ChunkTypes {
-//     IHDR,
-//     PLTE,
-//     IDAT,
-//     IEND,
-
-//     tRNS,
-//     gAMA,
-//     cHRM,
-//     sRGB,
-//     bKGD,
-//     pHYs,
-//     sBIT,
-//     sPLT,
-//     hIST,
-//     tIME,
-
-//     Other,
-// }
-
-// impl Color {
-//     pub fn from_color_type(color_type: u8, input: Vec<u8>) -> () {
-//         match color_type {
-//             0 => {
-//                 let mut color_vec: Vec<(u8)> = Vec::new();
-
-//                 for x in input {
-//                     color_vec.push((x));
-//                 }
-
-//                 println!(" GREYSCALE_DATA: {:?}", color_vec);
-//             }
-//             2 => {
-//                 let mut color_vec: Vec<(u8, u8, u8)> = Vec::new();
-
-//                 let mut data = input.as_slice();
-
-//                 loop {
-//                     let mut byte_red: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_red) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let mut byte_green: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_green) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let mut byte_blue: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_blue) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let red = u8::from_be_bytes(byte_red);
-//                     let green = u8::from_be_bytes(byte_green);
-//                     let blue = u8::from_be_bytes(byte_blue);
-
-//                     color_vec.push((red, green, blue))
-//                 }
-
-//                 println!(" RGB_DATA: {:?}", color_vec);
-//             }
-//             3 => {
-//                 let mut color_vec: Vec<(u8)> = Vec::new();
-
-//                 for x in input {
-//                     color_vec.push((x));
-//                 }
-
-//                 println!(" PALLET_INDEXES: {:?}", color_vec);
-//             }
-//             4 => {
-//                 let mut color_vec: Vec<(u8, u8)> = Vec::new();
-//                 let mut data = input.as_slice();
-
-//                 loop {
-//                     let mut byte_grey: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_grey) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     }
-
-//                     let mut byte_alpha: [u8; 1] = [0; 1];
-//                     data.read_exact(&mut byte_alpha).unwrap();
-
-//                     color_vec.push((u8::from_be_bytes(byte_grey), u8::from_be_bytes(byte_alpha)));
-//                 }
-
-//                 println!(" GREYSCALE+ALPHA_DATA: {:?}", color_vec);
-//             }
-//             6 => {
-//                 let mut color_vec: Vec<(u8, u8, u8, u8)> = Vec::new();
-//                 let mut data = input.as_slice();
-
-//                 loop {
-//                     let mut byte_red: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_red) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let mut byte_green: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_green) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let mut byte_blue: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_blue) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let mut byte_alpha: [u8; 1] = [0; 1];
-//                     match data.read_exact(&mut byte_alpha) {
-//                         Err(..) => break (),
-//                         _ => (),
-//                     };
-
-//                     let red = u8::from_be_bytes(byte_red);
-//                     let green = u8::from_be_bytes(byte_green);
-//                     let blue = u8::from_be_bytes(byte_blue);
-//                     let alpha = u8::from_be_bytes(byte_alpha);
-
-//                     color_vec.push((red, green, blue, alpha))
-//                 }
-//                 println!(" RGB+ALPHA_DATA: {:?}", color_vec);
-//             }
-//             _ => panic!(),
-//         }
-//     }
-// }
-
-// impl ChunkTypes {
-//     pub fn from_bytes(input: [u8; 4]) -> ChunkTypes {
-//         match input {
-// [73, 69, 78, 68] => ChunkTypes::IEND,
-// [73, 68, 65, 84] => ChunkTypes::IDAT,
-// [73, 72, 68, 82] => ChunkTypes::IHDR,
-// [80, 76, 84, 69] => ChunkTypes::PLTE,
-
-// [116, 82, 78, 83] => ChunkTypes::tRNS,
-// [103, 65, 77, 65] => ChunkTypes::gAMA,
-// [99, 72, 82, 77] => ChunkTypes::cHRM,
-// [115, 82, 71, 66] => ChunkTypes::sRGB,
-// [98, 75, 71, 68] => ChunkTypes::bKGD,
-// [112, 72, 89, 115] => ChunkTypes::pHYs,
-// [115, 66, 73, 84] => ChunkTypes::sBIT,
-// [115, 80, 76, 84] => ChunkTypes::sPLT,
-// [104, 73, 83, 84] => ChunkTypes::hIST,
-// [116, 73, 77, 69] => ChunkTypes::tIME,
-// _ => ChunkTypes::Other,
-//         }
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing, writer};
+
+    // IHDR, one IDAT & IEND. IHDR's length field is at 8, IDAT's at 33 & IEND's at 49.
+    fn image() -> Vec<u8> {
+        testing::png(&[
+            (b"IHDR", &testing::ihdr(1, 1, 8, 0)),
+            (b"IDAT", &[1, 2, 3, 4]),
+            (b"IEND", &[]),
+        ])
+    }
+
+    fn decode(bytes: &[u8]) -> PngError {
+        Png::from_bytes(bytes).unwrap_err()
+    }
+
+    #[test]
+    fn decodes_an_image() {
+        let png = Png::from_bytes(&image()).unwrap();
+        let types: Vec<_> = png
+            .chunks()
+            .iter()
+            .map(|chunk| *chunk.chunk_type())
+            .collect();
+        assert_eq!(types, [ChunkType::IHDR, ChunkType::IDAT, ChunkType::IEND]);
+        assert_eq!(png.chunks()[1].offset(), 33);
+        assert_eq!(png.chunks()[1].length(), 4);
+    }
+
+    #[test]
+    fn extra_image_data_is_ignored() {
+        let bytes = testing::png(&[
+            (b"IHDR", &testing::ihdr(2, 1, 8, 0)),
+            (b"IDAT", &writer::zlib_stored(&[0, 7, 9, 1, 2, 3])),
+            (b"IEND", &[]),
+        ]);
+        let png = Png::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            png.image_data(),
+            Ok(&Inflated {
+                data: vec![0, 7, 9],
+                excess: true,
+            })
+        );
+        assert_eq!(png.pixels().unwrap().samples().collect::<Vec<_>>(), [7, 9]);
+
+        let display = png.display(&DisplayOptions::default()).to_string();
+        assert!(display.contains(" decompressed: 3 bytes, then extra data that's ignored\n"));
+    }
+
+    #[test]
+    fn pixels_are_decoded_once() {
+        let bytes = testing::png(&[
+            (b"IHDR", &testing::ihdr(2, 1, 8, 0)),
+            (b"IDAT", &writer::zlib_stored(&[0, 0, 128])),
+            (b"IEND", &[]),
+        ]);
+        let png = Png::from_bytes(&bytes).unwrap();
+
+        // Only the descriptive view needs the pixels
+        let plain = png.display(&DisplayOptions::default()).to_string();
+        assert!(!plain.contains(" pixels:"));
+        assert!(png.pixels.get().is_none());
+
+        let descriptive = DisplayOptions { descriptive: true };
+        let display = png.display(&descriptive).to_string();
+        assert!(display.contains(" pixels: 2 x 1, 1 channels at 8 bits\n"));
+        assert!(std::ptr::eq(png.pixels().unwrap(), png.pixels().unwrap()));
+    }
+
+    #[test]
+    fn invalid_signature() {
+        let mut bytes = image();
+        bytes[0] = b'G';
+        let error = decode(&bytes);
+        assert!(matches!(&error, PngError::InvalidSignature { found } if found[0] == b'G'));
+        assert_eq!(error.offset(), 0);
+        assert_eq!(error.chunk_index(), None);
+
+        // Shorter than a signature
+        let error = decode(&PNG_SIGNATURE[..3]);
+        assert!(matches!(&error, PngError::InvalidSignature { found } if found.len() == 3));
+    }
+
+    #[test]
+    fn truncated_chunk_header() {
+        // Part way through IDAT's length, then part way through its type
+        for length in [35, 39] {
+            let error = decode(&image()[..length]);
+            assert!(matches!(error, PngError::TruncatedChunkHeader { .. }));
+            assert_eq!(error.offset(), 33);
+            assert_eq!(error.chunk_index(), Some(1));
+        }
+    }
+
+    #[test]
+    fn truncated_chunk_data() {
+        // Part way through IDAT's data
+        let error = decode(&image()[..43]);
+        assert!(matches!(
+            error,
+            PngError::TruncatedChunkData {
+                chunk_type: [b'I', b'D', b'A', b'T'],
+                needed: 8,
+                available: 2,
+                ..
+            }
+        ));
+        assert_eq!(error.offset(), 33);
+        assert_eq!(error.chunk_index(), Some(1));
+
+        // All of the data, but only some of the CRC
+        let error = decode(&image()[..47]);
+        assert!(matches!(
+            error,
+            PngError::TruncatedChunkData {
+                needed: 8,
+                available: 6,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn oversized_length() {
+        let mut bytes = image();
+        bytes[33..37].copy_from_slice(&0x8000_0000u32.to_be_bytes());
+        let error = decode(&bytes);
+        assert!(matches!(
+            error,
+            PngError::OversizedLength {
+                length: 0x8000_0000,
+                ..
+            }
+        ));
+        assert_eq!(error.offset(), 33);
+        assert_eq!(error.chunk_index(), Some(1));
+    }
+
+    #[test]
+    fn missing_iend() {
+        let error = decode(&image()[..49]);
+        assert!(matches!(error, PngError::UnexpectedEof { .. }));
+        assert_eq!(error.offset(), 49);
+        assert_eq!(error.chunk_index(), Some(2));
+    }
+
+    #[test]
+    fn missing_ihdr() {
+        let error = decode(&testing::png(&[(b"IDAT", &[1]), (b"IEND", &[])]));
+        assert!(matches!(error, PngError::MissingIhdr));
+        assert_eq!(error.offset(), 0);
+        assert_eq!(error.chunk_index(), None);
+    }
+
+    #[test]
+    fn unknown_critical_chunk() {
+        let bytes = testing::png(&[
+            (b"IHDR", &testing::ihdr(1, 1, 8, 0)),
+            (b"ABCD", &[]),
+            (b"IEND", &[]),
+        ]);
+        let error = decode(&bytes);
+        assert!(matches!(
+            error,
+            PngError::UnknownCriticalChunk {
+                chunk_type: [b'A', b'B', b'C', b'D'],
+                ..
+            }
+        ));
+        assert_eq!(error.offset(), 33);
+        assert_eq!(error.chunk_index(), Some(1));
+
+        // Unknown ancillary chunks are kept
+        let bytes = testing::png(&[
+            (b"IHDR", &testing::ihdr(1, 1, 8, 0)),
+            (b"abCD", &[1]),
+            (b"IEND", &[]),
+        ]);
+        let png = Png::from_bytes(&bytes).unwrap();
+        assert_eq!(*png.chunks()[1].chunk_type(), ChunkType::Unknown(*b"abCD"));
+    }
+
+    // IDAT with a bit flipped in its type, making it IDAt but leaving the CRC as it was
+    fn damaged_type() -> Vec<u8> {
+        let mut bytes = image();
+        bytes[40] ^= 0x20;
+        bytes
+    }
+
+    #[test]
+    fn crc_mismatch_is_an_error_when_strict() {
+        let error = decode(&damaged_type());
+        let expected = crc::chunk_crc(b"IDAt", &[1, 2, 3, 4]);
+        let actual = crc::chunk_crc(b"IDAT", &[1, 2, 3, 4]);
+        assert!(matches!(
+            error,
+            PngError::CrcMismatch { chunk_type: [b'I', b'D', b'A', b't'], expected: e, actual: a, .. }
+                if e == expected && a == actual
+        ));
+        assert_eq!(error.offset(), 33);
+        assert_eq!(error.chunk_index(), Some(1));
+    }
+
+    #[test]
+    fn crc_mismatch_is_kept_when_lenient() {
+        let options = ParseOptions {
+            crc_check: CrcCheck::Lenient,
+        };
+        let png = Png::from_bytes_with_options(&damaged_type(), &options).unwrap();
+
+        let chunk = &png.chunks()[1];
+        assert_eq!(*chunk.chunk_type(), ChunkType::Unknown(*b"IDAt"));
+        assert!(!chunk.crc_matches());
+        assert_eq!(chunk.computed_crc(), crc::chunk_crc(b"IDAt", &[1, 2, 3, 4]));
+        assert!(png.chunks()[0].crc_matches());
+        assert_eq!(png.chunks().len(), 3);
+    }
+}
@@ -0,0 +1,758 @@
+// Turns the decompressed image data back into pixels: reversing the scanline
+// filters, then splitting each scanline into samples.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#7Scanline
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#9Filters
+
+use crate::chunks::{tRNS, IHDR, PLTE};
+use crate::error::ImageError;
+use crate::interlace;
+
+// A single pixel, with samples at the image's bit depth (not scaled).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixel {
+    // Color type 0
+    Greyscale(u16),
+    // Type 2: red, green, blue
+    Truecolor(u16, u16, u16),
+    // Type 3: index into PLTE
+    Indexed(u8),
+    // Type 4: grey, alpha
+    GreyscaleAlpha(u16, u16),
+    // Type 6: red, green, blue, alpha
+    TruecolorAlpha(u16, u16, u16, u16),
+}
+
+// The decoded image, rows top to bottom. The rows are kept unfiltered but still packed
+// at the image's bit depth, so a 1 bit image takes a bit a pixel, and samples are read
+// out of them as they're asked for.
+#[derive(Debug, Clone)]
+pub struct PixelBuffer {
+    width: u32,
+    height: u32,
+    color_type: u8,
+    bit_depth: u8,
+    // Bytes in each row, the last one padded out to a whole byte
+    row_length: usize,
+    rows: Vec<u8>,
+    // Copied from PLTE, so indexed pixels can be turned into colors
+    pallet: Option<Vec<(u8, u8, u8)>>,
+    // From tRNS, the key color or the alpha of each pallet entry
+    transparency: Option<tRNS>,
+}
+
+impl PixelBuffer {
+    // Builds the buffer from decompressed, still filtered, image data
+    pub(crate) fn from_image_data(
+        header: &IHDR,
+        pallet: Option<&PLTE>,
+        transparency: Option<&tRNS>,
+        data: &[u8],
+    ) -> Result<PixelBuffer, ImageError> {
+        let height = header.height as usize;
+        let row_length = header.scanline_length(header.width);
+
+        // Both sizes come straight from IHDR, so they're checked before anything is allocated
+        let too_large = || ImageError::TooLarge {
+            width: header.width,
+            height: header.height,
+        };
+        let expected = header.image_data_size().ok_or_else(too_large)?;
+        if data.len() < expected {
+            return Err(ImageError::NotEnoughData {
+                expected,
+                actual: data.len(),
+            });
+        }
+        let size = row_length.checked_mul(height).ok_or_else(too_large)?;
+
+        let rows = match header.interlace_method {
+            0 => unfilter(data, row_length, height, header.filter_distance())?,
+            _ => deinterlace(header, data, size)?,
+        };
+
+        Ok(PixelBuffer {
+            width: header.width,
+            height: header.height,
+            color_type: header.color_type,
+            bit_depth: header.bit_depth,
+            row_length,
+            rows,
+            pallet: pallet.map(|plte| plte.pallet.clone()),
+            transparency: transparency.cloned(),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn color_type(&self) -> u8 {
+        self.color_type
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    // Samples per pixel
+    pub fn channels(&self) -> usize {
+        IHDR::channels_for(self.color_type)
+    }
+
+    // Every sample, `channels()` per pixel
+    pub fn samples(&self) -> impl Iterator<Item = u16> + '_ {
+        let row_samples = self.width as usize * self.channels();
+        self.rows
+            .chunks_exact(self.row_length)
+            .flat_map(move |row| read_samples(row, self.bit_depth).take(row_samples))
+    }
+
+    // The pixel at (x, y), counting from the top left
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Pixel> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let row = &self.rows[y as usize * self.row_length..][..self.row_length];
+        let first = x as usize * self.channels();
+        let s = |channel| read_sample(row, first + channel, self.bit_depth);
+
+        Some(match self.color_type {
+            0 => Pixel::Greyscale(s(0)),
+            2 => Pixel::Truecolor(s(0), s(1), s(2)),
+            3 => Pixel::Indexed(s(0) as u8),
+            4 => Pixel::GreyscaleAlpha(s(0), s(1)),
+            _ => Pixel::TruecolorAlpha(s(0), s(1), s(2), s(3)),
+        })
+    }
+
+    // Whether tRNS makes the pixel fully transparent. Only greyscale & truecolor
+    // pixels are checked, indexed pixels get their alpha from `pallet_entry`.
+    pub fn is_transparent_key(&self, pixel: Pixel) -> bool {
+        match (pixel, &self.transparency) {
+            (Pixel::Greyscale(grey), Some(tRNS::Greyscale { grey_sample_value })) => {
+                grey == *grey_sample_value
+            }
+            (
+                Pixel::Truecolor(red, green, blue),
+                Some(tRNS::Truecolor {
+                    red_sample_value,
+                    green_sample_value,
+                    blue_sample_value,
+                }),
+            ) => (red, green, blue) == (*red_sample_value, *green_sample_value, *blue_sample_value),
+            _ => false,
+        }
+    }
+
+    // Every pixel as 8 bit red, green, blue, alpha
+    pub fn to_rgba8(&self) -> Result<Vec<[u8; 4]>, ImageError> {
+        self.pixels().map(|pixel| self.rgba8(pixel)).collect()
+    }
+
+    // One pixel as 8 bit red, green, blue, alpha
+    pub fn rgba8(&self, pixel: Pixel) -> Result<[u8; 4], ImageError> {
+        let to_8_bit = |sample: u16| scale_sample(sample, self.bit_depth, 8) as u8;
+        let key_alpha = |pixel| match self.is_transparent_key(pixel) {
+            true => 0,
+            false => 255,
+        };
+
+        Ok(match pixel {
+            Pixel::Greyscale(grey) => {
+                let alpha = key_alpha(pixel);
+                let grey = to_8_bit(grey);
+                [grey, grey, grey, alpha]
+            }
+            Pixel::Truecolor(red, green, blue) => [
+                to_8_bit(red),
+                to_8_bit(green),
+                to_8_bit(blue),
+                key_alpha(pixel),
+            ],
+            Pixel::Indexed(index) => {
+                let (red, green, blue, alpha) = self.pallet_entry(index)?;
+                [red, green, blue, alpha]
+            }
+            Pixel::GreyscaleAlpha(grey, alpha) => {
+                let grey = to_8_bit(grey);
+                [grey, grey, grey, to_8_bit(alpha)]
+            }
+            Pixel::TruecolorAlpha(red, green, blue, alpha) => [
+                to_8_bit(red),
+                to_8_bit(green),
+                to_8_bit(blue),
+                to_8_bit(alpha),
+            ],
+        })
+    }
+
+    // Every pixel as 16 bit red, green, blue, alpha
+    pub fn to_rgba16(&self) -> Result<Vec<[u16; 4]>, ImageError> {
+        let to_16_bit = |sample: u16| scale_sample(sample, self.bit_depth, 16);
+        let key_alpha = |pixel| match self.is_transparent_key(pixel) {
+            true => 0,
+            false => u16::MAX,
+        };
+
+        self.pixels()
+            .map(|pixel| {
+                Ok(match pixel {
+                    Pixel::Greyscale(grey) => {
+                        let alpha = key_alpha(pixel);
+                        let grey = to_16_bit(grey);
+                        [grey, grey, grey, alpha]
+                    }
+                    Pixel::Truecolor(red, green, blue) => [
+                        to_16_bit(red),
+                        to_16_bit(green),
+                        to_16_bit(blue),
+                        key_alpha(pixel),
+                    ],
+                    Pixel::Indexed(index) => {
+                        let (red, green, blue, alpha) = self.pallet_entry(index)?;
+                        [
+                            red as u16 * 257,
+                            green as u16 * 257,
+                            blue as u16 * 257,
+                            alpha as u16 * 257,
+                        ]
+                    }
+                    Pixel::GreyscaleAlpha(grey, alpha) => {
+                        let grey = to_16_bit(grey);
+                        [grey, grey, grey, to_16_bit(alpha)]
+                    }
+                    Pixel::TruecolorAlpha(red, green, blue, alpha) => [
+                        to_16_bit(red),
+                        to_16_bit(green),
+                        to_16_bit(blue),
+                        to_16_bit(alpha),
+                    ],
+                })
+            })
+            .collect()
+    }
+
+    // Every pixel, left to right then top to bottom
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel> + '_ {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).map(move |x| self.pixel(x, y).expect("x & y are in bounds"))
+        })
+    }
+
+    // The pallet color with its alpha from tRNS
+    fn pallet_entry(&self, index: u8) -> Result<(u8, u8, u8, u8), ImageError> {
+        let pallet = match &self.pallet {
+            Some(x) => x,
+            None => return Err(ImageError::MissingPalette),
+        };
+
+        let alpha = self
+            .transparency
+            .as_ref()
+            .map_or(255, |transparency| transparency.pallet_alpha(index));
+
+        match pallet.get(index as usize) {
+            Some((red, green, blue)) => Ok((*red, *green, *blue, alpha)),
+            None => Err(ImageError::PaletteIndexOutOfRange {
+                index,
+                pallet_length: pallet.len(),
+            }),
+        }
+    }
+}
+
+// Unfilters each Adam7 pass on its own, then places its pixels into the full image's
+// packed rows. The data has to already be checked to hold every pass.
+fn deinterlace(header: &IHDR, data: &[u8], size: usize) -> Result<Vec<u8>, ImageError> {
+    let channels = header.channels();
+    let row_length = header.scanline_length(header.width);
+    let mut rows = vec![0; size];
+
+    let mut start = 0;
+    for pass in interlace::adam7_passes(header)
+        .iter()
+        .filter(|pass| !pass.is_empty())
+    {
+        let pass_row_length = header.scanline_length(pass.width);
+        let size = pass.byte_size(header).expect("the passes' total size fits");
+        let raw = unfilter(
+            &data[start..start + size],
+            pass_row_length,
+            pass.height as usize,
+            header.filter_distance(),
+        )
+        .map_err(|error| match error {
+            ImageError::InvalidFilterType { row, filter_type } => {
+                ImageError::InvalidPassFilterType {
+                    pass: pass.number,
+                    row,
+                    filter_type,
+                }
+            }
+            error => error,
+        })?;
+        start += size;
+
+        for (y, row) in raw.chunks_exact(pass_row_length).enumerate() {
+            let pass_samples =
+                read_samples(row, header.bit_depth).take(pass.width as usize * channels);
+            for (i, sample) in pass_samples.enumerate() {
+                let (x, y) = pass.to_image((i / channels) as u32, y as u32);
+                let row = &mut rows[y as usize * row_length..][..row_length];
+                write_sample(
+                    row,
+                    x as usize * channels + i % channels,
+                    header.bit_depth,
+                    sample,
+                );
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+// Rescales a sample from one bit depth to another, so the largest value maps to the largest value
+fn scale_sample(sample: u16, from_bit_depth: u8, to_bit_depth: u8) -> u16 {
+    if from_bit_depth == to_bit_depth {
+        return sample;
+    }
+
+    let from_max = (1u32 << from_bit_depth) - 1;
+    let to_max = (1u32 << to_bit_depth) - 1;
+    ((sample as u32 * to_max + from_max / 2) / from_max) as u16
+}
+
+// Splits a scanline into samples. Samples under 8 bits are packed into bytes
+// starting at the most significant bit, 16 bit samples are big-endian.
+fn read_samples(row: &[u8], bit_depth: u8) -> Box<dyn Iterator<Item = u16> + '_> {
+    match bit_depth {
+        16 => Box::new(
+            row.chunks_exact(2)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]])),
+        ),
+        8 => Box::new(row.iter().map(|sample| *sample as u16)),
+        _ => {
+            let per_byte = 8 / bit_depth;
+            let mask = (1u8 << bit_depth) - 1;
+            Box::new(row.iter().flat_map(move |byte| {
+                (0..per_byte).map(move |i| ((byte >> (8 - bit_depth * (i + 1))) & mask) as u16)
+            }))
+        }
+    }
+}
+
+// The `index`th sample in a scanline, packed the same way as `read_samples`
+fn read_sample(row: &[u8], index: usize, bit_depth: u8) -> u16 {
+    match bit_depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+        8 => row[index] as u16,
+        _ => {
+            let bit = index * bit_depth as usize;
+            let shift = 8 - bit_depth as usize - bit % 8;
+            ((row[bit / 8] >> shift) & ((1u8 << bit_depth) - 1)) as u16
+        }
+    }
+}
+
+fn write_sample(row: &mut [u8], index: usize, bit_depth: u8, sample: u16) {
+    match bit_depth {
+        16 => row[index * 2..index * 2 + 2].copy_from_slice(&sample.to_be_bytes()),
+        8 => row[index] = sample as u8,
+        _ => {
+            let bit = index * bit_depth as usize;
+            let shift = 8 - bit_depth as usize - bit % 8;
+            let mask = ((1u8 << bit_depth) - 1) << shift;
+            row[bit / 8] = (row[bit / 8] & !mask) | ((sample as u8) << shift);
+        }
+    }
+}
+
+// Reverses the filter on each scanline. Every scanline starts with a filter type byte,
+// which is dropped from the output.
+// `filter_distance` is the bytes per complete pixel, rounded up to 1 for bit depths under 8.
+pub(crate) fn unfilter(
+    data: &[u8],
+    scanline_length: usize,
+    height: usize,
+    filter_distance: usize,
+) -> Result<Vec<u8>, ImageError> {
+    // Saturating, as a size that doesn't fit in a usize is more than any data
+    let expected = (scanline_length + 1).saturating_mul(height);
+    if data.len() < expected {
+        return Err(ImageError::NotEnoughData {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    let mut output = vec![0; scanline_length * height];
+
+    for row in 0..height {
+        let filter_type = data[row * (scanline_length + 1)];
+        if filter_type > 4 {
+            return Err(ImageError::InvalidFilterType { row, filter_type });
+        }
+
+        let filtered = &data[row * (scanline_length + 1) + 1..(row + 1) * (scanline_length + 1)];
+
+        // The row above is treated as zeros for the first row
+        let (previous_rows, current_rows) = output.split_at_mut(row * scanline_length);
+        let previous = match row {
+            0 => None,
+            _ => Some(&previous_rows[(row - 1) * scanline_length..]),
+        };
+        let current = &mut current_rows[..scanline_length];
+
+        for i in 0..scanline_length {
+            // a = left, b = above, c = above left
+            let a = if i >= filter_distance {
+                current[i - filter_distance]
+            } else {
+                0
+            };
+            let b = previous.map_or(0, |previous| previous[i]);
+            let c = match previous {
+                Some(previous) if i >= filter_distance => previous[i - filter_distance],
+                _ => 0,
+            };
+
+            let predictor = match filter_type {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                _ => paeth_predictor(a, b, c),
+            };
+
+            current[i] = filtered[i].wrapping_add(predictor);
+        }
+    }
+
+    Ok(output)
+}
+
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#9Filter-type-4-Paeth
+fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32, bit_depth: u8, color_type: u8) -> IHDR {
+        IHDR {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    // Two rows of 4 bytes, the second filtered with `filter_type`. Unfiltered they're
+    // [10, 20, 30, 40] and [5, 200, 100, 255].
+    fn unfilter_second_row(filter_type: u8, filtered: [u8; 4]) -> Vec<u8> {
+        let mut data = vec![0, 10, 20, 30, 40, filter_type];
+        data.extend_from_slice(&filtered);
+        unfilter(&data, 4, 2, 1).unwrap()
+    }
+
+    const UNFILTERED: [u8; 8] = [10, 20, 30, 40, 5, 200, 100, 255];
+
+    #[test]
+    fn unfilter_none() {
+        assert_eq!(unfilter_second_row(0, [5, 200, 100, 255]), UNFILTERED);
+    }
+
+    #[test]
+    fn unfilter_sub() {
+        assert_eq!(unfilter_second_row(1, [5, 195, 156, 155]), UNFILTERED);
+    }
+
+    #[test]
+    fn unfilter_up() {
+        assert_eq!(unfilter_second_row(2, [251, 180, 70, 215]), UNFILTERED);
+    }
+
+    #[test]
+    fn unfilter_average() {
+        assert_eq!(unfilter_second_row(3, [0, 188, 241, 185]), UNFILTERED);
+    }
+
+    #[test]
+    fn unfilter_paeth() {
+        assert_eq!(unfilter_second_row(4, [251, 180, 156, 155]), UNFILTERED);
+    }
+
+    #[test]
+    fn unfilter_first_row_treats_above_as_zero() {
+        // Up & Paeth on the first row are the same as None & Sub
+        assert_eq!(unfilter(&[2, 1, 2, 3], 3, 1, 1).unwrap(), [1, 2, 3]);
+        assert_eq!(unfilter(&[4, 1, 2, 3], 3, 1, 1).unwrap(), [1, 3, 6]);
+    }
+
+    #[test]
+    fn unfilter_uses_filter_distance() {
+        // Sub with 2 bytes per pixel adds the byte 2 back
+        assert_eq!(unfilter(&[1, 1, 2, 3, 4], 4, 1, 2).unwrap(), [1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn unfilter_errors() {
+        assert_eq!(
+            unfilter(&[0, 1, 2, 5, 1, 2], 2, 2, 1),
+            Err(ImageError::InvalidFilterType {
+                row: 1,
+                filter_type: 5
+            })
+        );
+        assert_eq!(
+            unfilter(&[0, 1, 2, 0, 1], 2, 2, 1),
+            Err(ImageError::NotEnoughData {
+                expected: 6,
+                actual: 5
+            })
+        );
+    }
+
+    #[test]
+    fn paeth_ties() {
+        // Left wins a tie with above left
+        assert_eq!(paeth_predictor(0, 3, 2), 0);
+        // Above wins a tie with above left
+        assert_eq!(paeth_predictor(0, 3, 1), 3);
+        // Left wins a three way tie
+        assert_eq!(paeth_predictor(7, 7, 7), 7);
+        // Above left only wins when it's strictly closest
+        assert_eq!(paeth_predictor(10, 20, 15), 15);
+    }
+
+    #[test]
+    fn sub_byte_depths_skip_padding() {
+        // 3 pixels a row, the bits after them in the last byte are set but ignored
+        let cases: [(u8, &[u8], [u16; 3]); 3] = [
+            (1, &[0b1011_1111], [1, 0, 1]),
+            (2, &[0b1101_1011], [3, 1, 2]),
+            (4, &[0xa5, 0xcf], [10, 5, 12]),
+        ];
+
+        for (bit_depth, row, expected) in cases {
+            let mut data = vec![0];
+            data.extend_from_slice(row);
+            let pixels =
+                PixelBuffer::from_image_data(&header(3, 1, bit_depth, 0), None, None, &data)
+                    .unwrap();
+            assert_eq!(pixels.samples().collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn sub_byte_rows_start_on_a_byte() {
+        // 1 bit, 3 pixels a row, 2 rows: each row is its own byte
+        let data = [0, 0b1010_0000, 0, 0b0110_0000];
+        let pixels = PixelBuffer::from_image_data(&header(3, 2, 1, 0), None, None, &data).unwrap();
+        assert_eq!(pixels.samples().collect::<Vec<_>>(), [1, 0, 1, 0, 1, 1]);
+        assert_eq!(pixels.pixel(0, 1), Some(Pixel::Greyscale(0)));
+        assert_eq!(pixels.pixel(2, 1), Some(Pixel::Greyscale(1)));
+        assert_eq!(pixels.pixel(3, 1), None);
+    }
+
+    #[test]
+    fn sixteen_bit_samples() {
+        let data = [0, 0x12, 0x34, 0xff, 0xff, 0x00, 0x00];
+        let pixels = PixelBuffer::from_image_data(&header(3, 1, 16, 0), None, None, &data).unwrap();
+
+        assert_eq!(pixels.samples().collect::<Vec<_>>(), [0x1234, 0xffff, 0]);
+        assert_eq!(pixels.pixel(0, 0), Some(Pixel::Greyscale(0x1234)));
+        assert_eq!(
+            pixels.to_rgba8().unwrap(),
+            [[18, 18, 18, 255], [255, 255, 255, 255], [0, 0, 0, 255]]
+        );
+        assert_eq!(
+            pixels.to_rgba16().unwrap()[0],
+            [0x1234, 0x1234, 0x1234, 0xffff]
+        );
+    }
+
+    #[test]
+    fn greyscale_key_is_transparent() {
+        let transparency = tRNS::Greyscale {
+            grey_sample_value: 0x1234,
+        };
+        let data = [0, 0x12, 0x34, 0x12, 0x35];
+        let pixels =
+            PixelBuffer::from_image_data(&header(2, 1, 16, 0), None, Some(&transparency), &data)
+                .unwrap();
+
+        assert!(pixels.is_transparent_key(Pixel::Greyscale(0x1234)));
+        let rgba = pixels.to_rgba16().unwrap();
+        assert_eq!(rgba[0][3], 0);
+        assert_eq!(rgba[1][3], 0xffff);
+    }
+
+    #[test]
+    fn truecolor_key_is_transparent() {
+        // Only a match on all three samples counts
+        let transparency = tRNS::Truecolor {
+            red_sample_value: 1,
+            green_sample_value: 2,
+            blue_sample_value: 3,
+        };
+        let data = [0, 1, 2, 3, 1, 2, 4];
+        let pixels =
+            PixelBuffer::from_image_data(&header(2, 1, 8, 2), None, Some(&transparency), &data)
+                .unwrap();
+
+        assert_eq!(pixels.to_rgba8().unwrap(), [[1, 2, 3, 0], [1, 2, 4, 255]]);
+    }
+
+    #[test]
+    fn indexed_pixels_use_the_pallet() {
+        let pallet = PLTE {
+            pallet: vec![(255, 0, 0), (0, 0, 255)],
+        };
+        let transparency = tRNS::Indexed { pallet: vec![128] };
+        let data = [0, 0b0100_0000];
+        let pixels = PixelBuffer::from_image_data(
+            &header(2, 1, 1, 3),
+            Some(&pallet),
+            Some(&transparency),
+            &data,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pixels.to_rgba8().unwrap(),
+            [[255, 0, 0, 128], [0, 0, 255, 255]]
+        );
+    }
+
+    fn interlaced(width: u32, height: u32, bit_depth: u8) -> IHDR {
+        IHDR {
+            interlace_method: 1,
+            ..header(width, height, bit_depth, 0)
+        }
+    }
+
+    #[test]
+    fn deinterlace_order() {
+        // Passes 2 & 3 are empty, so have no bytes at all
+        let data = [
+            0, 1, // pass 1: (0, 0)
+            0, 2, // pass 4: (2, 0)
+            0, 3, 4, // pass 5: (0, 2), (2, 2)
+            0, 5, 0, 6, // pass 6: (1, 0), (1, 2)
+            0, 7, 8, 9, // pass 7: row 1
+        ];
+        let pixels = PixelBuffer::from_image_data(&interlaced(3, 3, 8), None, None, &data).unwrap();
+        assert_eq!(
+            pixels.samples().collect::<Vec<_>>(),
+            [1, 5, 2, 7, 8, 9, 3, 6, 4]
+        );
+    }
+
+    #[test]
+    fn deinterlace_matches_the_adam7_pattern() {
+        // Every pixel set to its pass number should give the pattern from the spec
+        let header = interlaced(8, 8, 8);
+        let mut data = Vec::new();
+        for pass in interlace::adam7_passes(&header) {
+            for _ in 0..pass.height {
+                data.push(0);
+                data.extend(std::iter::repeat_n(pass.number, pass.width as usize));
+            }
+        }
+
+        let pixels = PixelBuffer::from_image_data(&header, None, None, &data).unwrap();
+        let pattern: Vec<u16> = [
+            [1, 6, 4, 6, 2, 6, 4, 6],
+            [7, 7, 7, 7, 7, 7, 7, 7],
+            [5, 6, 5, 6, 5, 6, 5, 6],
+            [7, 7, 7, 7, 7, 7, 7, 7],
+            [3, 6, 4, 6, 3, 6, 4, 6],
+            [7, 7, 7, 7, 7, 7, 7, 7],
+            [5, 6, 5, 6, 5, 6, 5, 6],
+            [7, 7, 7, 7, 7, 7, 7, 7],
+        ]
+        .iter()
+        .flatten()
+        .copied()
+        .collect();
+        assert_eq!(pixels.samples().collect::<Vec<_>>(), pattern);
+    }
+
+    #[test]
+    fn deinterlace_sub_byte_passes() {
+        // 1 bit, 3 x 1: pass 1 has (0, 0), pass 4 (2, 0) and pass 6 (1, 0), each
+        // row padded to a byte
+        let data = [0, 0b1000_0000, 0, 0b0111_1111, 0, 0b1000_0000];
+        let pixels = PixelBuffer::from_image_data(&interlaced(3, 1, 1), None, None, &data).unwrap();
+        assert_eq!(pixels.samples().collect::<Vec<_>>(), [1, 1, 0]);
+    }
+
+    #[test]
+    fn deinterlace_pass_filter_errors() {
+        let data = [0, 1, 0, 2, 0, 3, 4, 0, 5, 0, 6, 9, 7, 8, 9];
+        assert_eq!(
+            PixelBuffer::from_image_data(&interlaced(3, 3, 8), None, None, &data).unwrap_err(),
+            ImageError::InvalidPassFilterType {
+                pass: 7,
+                row: 0,
+                filter_type: 9
+            }
+        );
+    }
+
+    #[test]
+    fn sizes_are_checked_before_allocating() {
+        assert_eq!(
+            PixelBuffer::from_image_data(&interlaced(60000, 60000, 8), None, None, &[0; 10])
+                .unwrap_err(),
+            ImageError::NotEnoughData {
+                expected: 3_600_112_500,
+                actual: 10
+            }
+        );
+
+        let huge = IHDR {
+            bit_depth: 16,
+            color_type: 6,
+            ..interlaced(0x7fff_ffff, 0x7fff_ffff, 16)
+        };
+        assert_eq!(
+            PixelBuffer::from_image_data(&huge, None, None, &[0; 10]).unwrap_err(),
+            ImageError::TooLarge {
+                width: 0x7fff_ffff,
+                height: 0x7fff_ffff
+            }
+        );
+    }
+
+    #[test]
+    fn scale_sample_ends() {
+        assert_eq!(scale_sample(1, 1, 8), 255);
+        assert_eq!(scale_sample(2, 2, 8), 170);
+        assert_eq!(scale_sample(0xffff, 16, 8), 255);
+        assert_eq!(scale_sample(255, 8, 16), 0xffff);
+    }
+}
@@ -0,0 +1,94 @@
+// A cursor over a chunk's data. Every read moves the cursor forward, so fields
+// are read one after another instead of each starting back at byte 0.
+
+use crate::error::ChunkError;
+
+pub struct ChunkReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ChunkReader { data, position: 0 }
+    }
+
+    // How far into the data the cursor is
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // How many bytes are left to read
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ChunkError> {
+        if length > self.remaining() {
+            return Err(ChunkError::UnexpectedEnd {
+                offset: self.data.len(),
+            });
+        }
+
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ChunkError> {
+        let mut bytes: [u8; N] = [0; N];
+        bytes.copy_from_slice(self.read_bytes(N)?);
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ChunkError> {
+        Ok(u8::from_be_bytes(self.read_array()?))
+    }
+
+    // PNG Uses Big-Edian
+    // https://www.w3.org/TR/2003/REC-PNG-20031110/#7Integers-and-byte-order
+    pub fn read_u16(&mut self) -> Result<u16, ChunkError> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ChunkError> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    // Reads up to (and skips over) the next null byte
+    pub fn read_null_terminated(&mut self) -> Result<&'a [u8], ChunkError> {
+        let rest = &self.data[self.position..];
+
+        let separator = match rest.iter().position(|byte| *byte == 0) {
+            Some(x) => x,
+            None => return Err(ChunkError::MissingNullSeparator),
+        };
+
+        self.position += separator + 1;
+        Ok(&rest[..separator])
+    }
+
+    // A null terminated Latin-1 keyword (1-79 bytes), as used by iCCP, sPLT & the text chunks
+    pub fn read_keyword(&mut self) -> Result<String, ChunkError> {
+        let keyword = self.read_null_terminated()?;
+
+        if keyword.is_empty() || keyword.len() > 79 {
+            return Err(ChunkError::InvalidKeywordLength {
+                length: keyword.len(),
+            });
+        }
+
+        Ok(keyword.iter().map(|byte| *byte as char).collect())
+    }
+
+    // Everything that hasn't been read yet
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+}
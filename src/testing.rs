@@ -0,0 +1,69 @@
+// Builds small PNG datastreams for the tests
+
+use crate::crc;
+use crate::parser::PNG_SIGNATURE;
+
+// The signature, then each chunk with its length & CRC filled in. The chunk types
+// are written as they are, so they can be unknown or damaged.
+pub fn png(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let mut output = PNG_SIGNATURE.to_vec();
+    for (chunk_type, data) in chunks {
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        output.extend_from_slice(*chunk_type);
+        output.extend_from_slice(data);
+        output.extend_from_slice(&crc::chunk_crc(chunk_type, data).to_be_bytes());
+    }
+    output
+}
+
+// IHDR's data for a non-interlaced image
+pub fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(13);
+    data.extend_from_slice(&width.to_be_bytes());
+    data.extend_from_slice(&height.to_be_bytes());
+    data.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+    data
+}
+
+// A version 4 RGB display profile holding the given tags, each one padded to 4 bytes
+pub fn icc_profile(tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut header = vec![0; 128];
+    header[8] = 4;
+    header[9] = 0x30;
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    // 2024-05-06 07:08:09
+    for (index, value) in [2024u16, 5, 6, 7, 8, 9].iter().enumerate() {
+        header[24 + index * 2..26 + index * 2].copy_from_slice(&value.to_be_bytes());
+    }
+    header[36..40].copy_from_slice(b"acsp");
+    // D50
+    for (index, value) in [0x0000_f6d6u32, 0x0001_0000, 0x0000_d32d]
+        .iter()
+        .enumerate()
+    {
+        header[68 + index * 4..72 + index * 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut body = Vec::new();
+    let body_start = 128 + 4 + tags.len() * 12;
+    for (signature, data) in tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&((body_start + body.len()) as u32).to_be_bytes());
+        table.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        body.resize(body.len().next_multiple_of(4), 0);
+    }
+
+    let mut profile = [header, table, body].concat();
+    let size = profile.len() as u32;
+    profile[..4].copy_from_slice(&size.to_be_bytes());
+    profile
+}
+
+// A tag's type signature, 4 reserved bytes, then its data
+pub fn icc_tag(tag_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    [tag_type, &[0; 4][..], data].concat()
+}
@@ -0,0 +1,210 @@
+// Bytes after IEND. The spec says decoders ignore them, which is exactly why they're
+// used to hide things: a ZIP appended to a PNG is still a valid ZIP (and PNG).
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#11IEND
+
+use std::fmt;
+
+use crate::parser::PNG_SIGNATURE;
+
+// Local file header & end of central directory signatures
+// https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_END_OF_DIRECTORY: &[u8] = b"PK\x05\x06";
+
+// How many bytes the hex preview shows
+const PREVIEW_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailingData {
+    // Where the data starts in the datastream, straight after IEND's CRC
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+// What the trailing data looks like. Offsets are from the start of the trailing data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingKind {
+    // Only zero bytes, usually padding
+    Zeros,
+    // Another PNG signature
+    Png { offset: usize },
+    // A ZIP local file header, or failing that an end of central directory record
+    Zip { offset: usize },
+    // Printable text (UTF-8)
+    Text,
+    Unknown,
+}
+
+impl TrailingData {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn kind(&self) -> TrailingKind {
+        if self.data.iter().all(|byte| *byte == 0) {
+            return TrailingKind::Zeros;
+        }
+        if let Some(offset) = find(&self.data, &PNG_SIGNATURE) {
+            return TrailingKind::Png { offset };
+        }
+        if let Some(offset) =
+            find(&self.data, ZIP_LOCAL_HEADER).or_else(|| find(&self.data, ZIP_END_OF_DIRECTORY))
+        {
+            return TrailingKind::Zip { offset };
+        }
+
+        let is_text = match std::str::from_utf8(&self.data) {
+            Ok(text) => text
+                .chars()
+                .all(|c| !c.is_control() || c.is_ascii_whitespace()),
+            Err(..) => false,
+        };
+        if is_text {
+            return TrailingKind::Text;
+        }
+
+        TrailingKind::Unknown
+    }
+
+    // The first bytes, where a payload's magic number would be
+    pub fn preview(&self) -> &[u8] {
+        &self.data[..self.data.len().min(PREVIEW_LENGTH)]
+    }
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+}
+
+impl fmt::Display for TrailingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrailingKind::Zeros => write!(f, "zero padding"),
+            TrailingKind::Png { offset: 0 } => write!(f, "another PNG"),
+            TrailingKind::Png { offset } => write!(f, "another PNG, {} bytes in", offset),
+            TrailingKind::Zip { offset: 0 } => write!(f, "a ZIP archive"),
+            TrailingKind::Zip { offset } => write!(f, "a ZIP archive, {} bytes in", offset),
+            TrailingKind::Text => write!(f, "text"),
+            TrailingKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+// A hex dump of the preview, 16 bytes a line with the printable ones next to it
+impl fmt::Display for TrailingData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, " offset: {}", self.offset)?;
+        writeln!(f, " size: {} bytes", self.len())?;
+        if f.alternate() {
+            writeln!(
+                f,
+                " looks like: {} | Decoders ignore data after IEND, so anything can be hidden here",
+                self.kind()
+            )?;
+        } else {
+            writeln!(f, " looks like: {}", self.kind())?;
+        }
+
+        for (line, bytes) in self.preview().chunks(16).enumerate() {
+            write!(f, "  {:08x} ", line * 16)?;
+            for index in 0..16 {
+                match bytes.get(index) {
+                    Some(byte) => write!(f, " {:02x}", byte)?,
+                    None => write!(f, "   ")?,
+                }
+            }
+            let text: String = bytes
+                .iter()
+                .map(|byte| match byte {
+                    0x20..=0x7e => *byte as char,
+                    _ => '.',
+                })
+                .collect();
+            writeln!(f, "  |{}|", text)?;
+        }
+        if self.len() > PREVIEW_LENGTH {
+            writeln!(f, "  ... {} more bytes", self.len() - PREVIEW_LENGTH)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trailing(data: &[u8]) -> TrailingData {
+        TrailingData {
+            offset: 100,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn zero_padding() {
+        assert_eq!(trailing(&[0; 20]).kind(), TrailingKind::Zeros);
+    }
+
+    #[test]
+    fn appended_png() {
+        assert_eq!(
+            trailing(&PNG_SIGNATURE).kind(),
+            TrailingKind::Png { offset: 0 }
+        );
+
+        let data = [&[0, 0, 0][..], &PNG_SIGNATURE, &[1, 2]].concat();
+        assert_eq!(trailing(&data).kind(), TrailingKind::Png { offset: 3 });
+    }
+
+    #[test]
+    fn zip_archive() {
+        let data = [&b"PK\x03\x04"[..], &[20, 0, 0, 0, 8, 0]].concat();
+        assert_eq!(trailing(&data).kind(), TrailingKind::Zip { offset: 0 });
+
+        let data = [&[0xff, 0xfe][..], b"PK\x03\x04"].concat();
+        assert_eq!(trailing(&data).kind(), TrailingKind::Zip { offset: 2 });
+
+        // Only the end of the central directory
+        let data = [&[9; 5][..], b"PK\x05\x06", &[0; 18]].concat();
+        assert_eq!(trailing(&data).kind(), TrailingKind::Zip { offset: 5 });
+    }
+
+    #[test]
+    fn printable_text() {
+        assert_eq!(trailing(b"hello\r\n\tworld\n").kind(), TrailingKind::Text);
+        assert_eq!(trailing("caf\u{e9}".as_bytes()).kind(), TrailingKind::Text);
+
+        // Control characters & invalid UTF-8 aren't text
+        assert_eq!(trailing(b"a\x01b").kind(), TrailingKind::Unknown);
+        assert_eq!(trailing(&[b'a', 0xff]).kind(), TrailingKind::Unknown);
+    }
+
+    #[test]
+    fn preview_is_cut_short() {
+        let data: Vec<u8> = (0..100).collect();
+        let trailing_data = trailing(&data);
+        assert_eq!(trailing_data.preview(), &data[..PREVIEW_LENGTH]);
+        assert_eq!(trailing(&data[..10]).preview(), &data[..10]);
+
+        let text = trailing_data.to_string();
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("  0")).count(),
+            4
+        );
+        assert!(text.ends_with("  ... 36 more bytes\n"));
+    }
+
+    #[test]
+    fn hex_dump() {
+        let text = trailing(b"PNG\x00\x01").to_string();
+        assert_eq!(
+            text.lines().nth(3).unwrap(),
+            format!("  00000000  50 4e 47 00 01{}  |PNG..|", " ".repeat(33))
+        );
+    }
+}
@@ -0,0 +1,660 @@
+// Checks a decoded PNG against the rules the parser itself doesn't enforce: which
+// chunks can appear more than once, the order they have to come in, and which
+// chunks each color type needs. Each problem says how serious it is and where in
+// the spec the rule comes from, like pngcheck.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5ChunkOrdering
+
+use std::fmt;
+
+use crate::chunks::ParsedChunk;
+use crate::consistency::ColorWarning;
+use crate::error::ChunkError;
+use crate::parser::{ChunkType, Png};
+use crate::trailing::TrailingKind;
+
+const ORDERING: &str = "https://www.w3.org/TR/2003/REC-PNG-20031110/#5ChunkOrdering";
+
+// Chunks that can only appear once
+const ONCE_ONLY: [ChunkType; 22] = [
+    ChunkType::IHDR,
+    ChunkType::PLTE,
+    ChunkType::IEND,
+    ChunkType::cHRM,
+    ChunkType::gAMA,
+    ChunkType::iCCP,
+    ChunkType::sBIT,
+    ChunkType::sRGB,
+    ChunkType::bKGD,
+    ChunkType::hIST,
+    ChunkType::tRNS,
+    ChunkType::pHYs,
+    ChunkType::tIME,
+    ChunkType::acTL,
+    ChunkType::cICP,
+    ChunkType::mDCV,
+    ChunkType::cLLI,
+    ChunkType::eXIf,
+    ChunkType::oFFs,
+    ChunkType::pCAL,
+    ChunkType::sCAL,
+    ChunkType::sTER,
+];
+
+// Chunks that have to come before PLTE (and so before IDAT too)
+const BEFORE_PLTE: [ChunkType; 8] = [
+    ChunkType::cHRM,
+    ChunkType::cICP,
+    ChunkType::mDCV,
+    ChunkType::cLLI,
+    ChunkType::gAMA,
+    ChunkType::iCCP,
+    ChunkType::sBIT,
+    ChunkType::sRGB,
+];
+
+// Chunks that have to come after PLTE, but before IDAT
+const AFTER_PLTE: [ChunkType; 3] = [ChunkType::bKGD, ChunkType::hIST, ChunkType::tRNS];
+
+// Chunks that only have to come before IDAT
+const BEFORE_IDAT: [ChunkType; 8] = [
+    ChunkType::pHYs,
+    ChunkType::sPLT,
+    ChunkType::acTL,
+    ChunkType::eXIf,
+    ChunkType::oFFs,
+    ChunkType::pCAL,
+    ChunkType::sCAL,
+    ChunkType::sTER,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // Something the spec recommends against, most decoders cope with it
+    Warning,
+    // Something the spec doesn't allow
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    IhdrNotFirst,
+    // There's data after IEND
+    TrailingData {
+        size: usize,
+        kind: TrailingKind,
+    },
+    // A chunk that can only appear once appears again
+    Repeated {
+        chunk_type: ChunkType,
+    },
+    PlteAfterIdat,
+    // The IDAT chunks have other chunks between them
+    IdatNotContiguous,
+    MissingIdat,
+    // The chunk has to come before PLTE
+    AfterPlte {
+        chunk_type: ChunkType,
+    },
+    // The chunk has to come after PLTE
+    BeforePlte {
+        chunk_type: ChunkType,
+    },
+    // The chunk has to come before the first IDAT
+    AfterIdat {
+        chunk_type: ChunkType,
+    },
+    // Indexed images need a PLTE chunk
+    MissingPlte,
+    // Greyscale images can't have a PLTE chunk
+    PlteNotAllowed {
+        color_type: u8,
+    },
+    SrgbWithIccp,
+    // The stored CRC is wrong, only seen when parsing leniently
+    CrcMismatch {
+        chunk_type: ChunkType,
+    },
+    // An ancillary chunk's data isn't valid
+    InvalidChunk {
+        chunk_type: ChunkType,
+        error: ChunkError,
+    },
+    // The color chunks disagree with each other
+    Color(ColorWarning),
+    // fcTL & fdAT sequence numbers have to count up from 0 without gaps
+    SequenceNumber {
+        expected: u32,
+        found: u32,
+    },
+    // fcTL or fdAT chunks without an acTL, so the image isn't an APNG
+    MissingActl,
+    // acTL's frame count doesn't match the number of fcTL chunks
+    FrameCountMismatch {
+        declared: u32,
+        actual: usize,
+    },
+    // The fcTL before IDAT has to cover the whole image
+    DefaultFrameSize,
+    // The image data decompresses to more than the image needs, which is ignored
+    ExtraImageData,
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::SrgbWithIccp
+            | Problem::Color(..)
+            | Problem::TrailingData { .. }
+            | Problem::ExtraImageData => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    // Where in the spec the rule comes from
+    pub fn spec_reference(&self) -> &'static str {
+        match self {
+            Problem::IhdrNotFirst => "https://www.w3.org/TR/2003/REC-PNG-20031110/#11IHDR",
+            Problem::TrailingData { .. } => "https://www.w3.org/TR/2003/REC-PNG-20031110/#11IEND",
+            Problem::MissingIdat | Problem::IdatNotContiguous | Problem::ExtraImageData => {
+                "https://www.w3.org/TR/2003/REC-PNG-20031110/#11IDAT"
+            }
+            Problem::MissingPlte | Problem::PlteNotAllowed { .. } => {
+                "https://www.w3.org/TR/2003/REC-PNG-20031110/#11PLTE"
+            }
+            Problem::SrgbWithIccp | Problem::Color(..) => {
+                "https://www.w3.org/TR/2003/REC-PNG-20031110/#11iCCP"
+            }
+            Problem::CrcMismatch { .. } => {
+                "https://www.w3.org/TR/2003/REC-PNG-20031110/#5CRC-algorithm"
+            }
+            Problem::InvalidChunk { .. } => {
+                "https://www.w3.org/TR/2003/REC-PNG-20031110/#11Ancillary-chunks"
+            }
+            Problem::SequenceNumber { .. } => "https://www.w3.org/TR/png-3/#fcTL-chunk",
+            Problem::DefaultFrameSize => "https://www.w3.org/TR/png-3/#fcTL-chunk",
+            Problem::MissingActl | Problem::FrameCountMismatch { .. } => {
+                "https://www.w3.org/TR/png-3/#acTL-chunk"
+            }
+            Problem::Repeated { .. }
+            | Problem::PlteAfterIdat
+            | Problem::AfterPlte { .. }
+            | Problem::BeforePlte { .. }
+            | Problem::AfterIdat { .. } => ORDERING,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::IhdrNotFirst => write!(f, "IHDR isn't the first chunk"),
+            Problem::TrailingData { size, kind } => {
+                write!(f, "there are {} bytes after IEND ({})", size, kind)
+            }
+            Problem::Repeated { chunk_type } => {
+                write!(f, "{} can only appear once", chunk_type)
+            }
+            Problem::PlteAfterIdat => write!(f, "PLTE comes after IDAT"),
+            Problem::IdatNotContiguous => {
+                write!(f, "IDAT chunks have to be next to each other")
+            }
+            Problem::MissingIdat => write!(f, "there are no IDAT chunks"),
+            Problem::AfterPlte { chunk_type } => {
+                write!(f, "{} has to come before PLTE", chunk_type)
+            }
+            Problem::BeforePlte { chunk_type } => {
+                write!(f, "{} has to come after PLTE", chunk_type)
+            }
+            Problem::AfterIdat { chunk_type } => {
+                write!(f, "{} has to come before the first IDAT", chunk_type)
+            }
+            Problem::MissingPlte => write!(f, "color type 3 needs a PLTE chunk"),
+            Problem::PlteNotAllowed { color_type } => {
+                write!(f, "PLTE isn't allowed for color type {}", color_type)
+            }
+            Problem::SrgbWithIccp => write!(f, "sRGB and iCCP shouldn't both be present"),
+            Problem::CrcMismatch { chunk_type } => write!(f, "{} has a bad CRC", chunk_type),
+            Problem::InvalidChunk { chunk_type, error } => {
+                write!(f, "{} is invalid: {}", chunk_type, error)
+            }
+            Problem::Color(warning) => write!(f, "{}", warning),
+            Problem::SequenceNumber { expected, found } => write!(
+                f,
+                "expected sequence number {}, but found {}",
+                expected, found
+            ),
+            Problem::MissingActl => write!(f, "there are fcTL or fdAT chunks but no acTL"),
+            Problem::FrameCountMismatch { declared, actual } => write!(
+                f,
+                "acTL says there are {} frames, but there are {} fcTL chunks",
+                declared, actual
+            ),
+            Problem::DefaultFrameSize => write!(
+                f,
+                "the fcTL before IDAT doesn't cover the whole image at (0, 0)"
+            ),
+            Problem::ExtraImageData => write!(
+                f,
+                "the image data decompresses to more than the image needs"
+            ),
+        }
+    }
+}
+
+// One problem, and the chunk it was found at (if it's about a specific chunk)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub chunk_index: Option<usize>,
+    pub offset: Option<u64>,
+    pub problem: Problem,
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        self.problem.severity()
+    }
+}
+
+// Like "error: chunk 3 at byte 57: PLTE comes after IDAT (https://...)"
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity())?;
+        match (self.chunk_index, self.offset) {
+            (Some(chunk_index), Some(offset)) => {
+                write!(f, "chunk {} at byte {}: ", chunk_index, offset)?
+            }
+            (None, Some(offset)) => write!(f, "at byte {}: ", offset)?,
+            _ => (),
+        }
+        write!(f, "{} ({})", self.problem, self.problem.spec_reference())
+    }
+}
+
+// Every problem found, in the order of the chunks they're about
+pub fn validate(png: &Png) -> Vec<Finding> {
+    let chunks = png.chunks();
+    let mut findings = Vec::new();
+    let mut at = |index: usize, problem| {
+        findings.push(Finding {
+            chunk_index: Some(index),
+            offset: Some(chunks[index].offset()),
+            problem,
+        })
+    };
+
+    let position = |chunk_type: ChunkType| {
+        chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == chunk_type)
+    };
+    let first_plte = position(ChunkType::PLTE);
+    let first_idat = position(ChunkType::IDAT);
+    let mut seen: Vec<ChunkType> = Vec::new();
+    let mut last_idat: Option<usize> = None;
+    let mut expected_sequence_number = 0;
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let chunk_type = *chunk.chunk_type();
+
+        if index == 0 && chunk_type != ChunkType::IHDR {
+            at(index, Problem::IhdrNotFirst);
+        }
+
+        if ONCE_ONLY.contains(&chunk_type) && seen.contains(&chunk_type) {
+            at(index, Problem::Repeated { chunk_type });
+        }
+        seen.push(chunk_type);
+
+        // IDAT has to be one unbroken run
+        if chunk_type == ChunkType::IDAT {
+            if last_idat.is_some_and(|last| last != index - 1) {
+                at(index, Problem::IdatNotContiguous);
+            }
+            last_idat = Some(index);
+        }
+
+        let after_idat = first_idat.is_some_and(|first| index > first);
+        let after_plte = first_plte.is_some_and(|first| index > first);
+        let before_plte = first_plte.is_some_and(|first| index < first);
+
+        if chunk_type == ChunkType::PLTE && after_idat {
+            at(index, Problem::PlteAfterIdat);
+        }
+        if BEFORE_PLTE.contains(&chunk_type) && after_plte {
+            at(index, Problem::AfterPlte { chunk_type });
+        }
+        if AFTER_PLTE.contains(&chunk_type) && before_plte {
+            at(index, Problem::BeforePlte { chunk_type });
+        }
+        let before_idat_only = BEFORE_PLTE.contains(&chunk_type)
+            || AFTER_PLTE.contains(&chunk_type)
+            || BEFORE_IDAT.contains(&chunk_type);
+        if before_idat_only && after_idat {
+            at(index, Problem::AfterIdat { chunk_type });
+        }
+
+        // Frame chunks share one sequence, whichever of the two they are
+        let sequence_number = match chunk.parsed() {
+            ParsedChunk::fcTL(control) => Some(control.sequence_number),
+            ParsedChunk::fdAT(data) => Some(data.sequence_number),
+            _ => None,
+        };
+        if let Some(found) = sequence_number {
+            if found != expected_sequence_number {
+                at(
+                    index,
+                    Problem::SequenceNumber {
+                        expected: expected_sequence_number,
+                        found,
+                    },
+                );
+            }
+            expected_sequence_number = found.wrapping_add(1);
+        }
+
+        if let ParsedChunk::fcTL(control) = chunk.parsed() {
+            let covers_image = control.x_offset == 0
+                && control.y_offset == 0
+                && control.width == png.ihdr().width
+                && control.height == png.ihdr().height;
+            if first_idat.is_some_and(|first| index < first) && !covers_image {
+                at(index, Problem::DefaultFrameSize);
+            }
+        }
+
+        if !chunk.crc_matches() {
+            at(index, Problem::CrcMismatch { chunk_type });
+        }
+
+        // Coming before PLTE is already reported as an ordering problem
+        if let ParsedChunk::Invalid(error) = chunk.parsed() {
+            if !(*error == ChunkError::MissingPlte && first_plte.is_some()) {
+                at(
+                    index,
+                    Problem::InvalidChunk {
+                        chunk_type,
+                        error: error.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut general = |problem| {
+        findings.push(Finding {
+            chunk_index: None,
+            offset: None,
+            problem,
+        })
+    };
+
+    // Image data that can't be decompressed at all shows up when decoding the pixels
+    match first_idat {
+        None => general(Problem::MissingIdat),
+        Some(..) if png.image_data().is_ok_and(|inflated| inflated.excess) => {
+            general(Problem::ExtraImageData)
+        }
+        Some(..) => (),
+    }
+
+    let color_type = png.ihdr().color_type;
+    match (color_type, first_plte) {
+        (3, None) => general(Problem::MissingPlte),
+        (0 | 4, Some(..)) => general(Problem::PlteNotAllowed { color_type }),
+        _ => (),
+    }
+
+    let frame_controls = chunks
+        .iter()
+        .filter(|chunk| *chunk.chunk_type() == ChunkType::fcTL)
+        .count();
+    let has_frame_data = chunks
+        .iter()
+        .any(|chunk| *chunk.chunk_type() == ChunkType::fdAT);
+    match png.animation_control() {
+        Some(actl) if actl.num_frames as usize != frame_controls => {
+            general(Problem::FrameCountMismatch {
+                declared: actl.num_frames,
+                actual: frame_controls,
+            })
+        }
+        None if frame_controls > 0 || has_frame_data => general(Problem::MissingActl),
+        _ => (),
+    }
+
+    // sRGB with iCCP is one of the color warnings
+    for warning in png.color_warnings() {
+        match warning {
+            ColorWarning::SrgbWithIccp => general(Problem::SrgbWithIccp),
+            warning => general(Problem::Color(warning)),
+        }
+    }
+
+    if let Some(trailing_data) = png.trailing_data() {
+        findings.push(Finding {
+            chunk_index: None,
+            offset: Some(trailing_data.offset),
+            problem: Problem::TrailingData {
+                size: trailing_data.len(),
+                kind: trailing_data.kind(),
+            },
+        });
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing, writer};
+
+    const GAMA: &[u8] = &[0, 0, 0xb1, 0x8f];
+    const PLTE: &[u8] = &[0, 0, 0];
+    const IDAT: &[u8] = &[1, 2, 3];
+
+    // Each problem found, with the index of the chunk it's about & its severity
+    fn problems(
+        color_type: u8,
+        chunks: &[(&[u8; 4], &[u8])],
+    ) -> Vec<(Option<usize>, Problem, Severity)> {
+        let ihdr = testing::ihdr(1, 1, 8, color_type);
+        let mut all = vec![(b"IHDR", &ihdr[..])];
+        all.extend_from_slice(chunks);
+        all.push((b"IEND", &[]));
+
+        let png = Png::from_bytes(&testing::png(&all)).unwrap();
+        validate(&png)
+            .into_iter()
+            .map(|finding| {
+                let severity = finding.severity();
+                (finding.chunk_index, finding.problem, severity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_order() {
+        let chunks = [
+            (b"gAMA", GAMA),
+            (b"PLTE", PLTE),
+            (b"tRNS", &[0][..]),
+            (b"IDAT", IDAT),
+            (b"IDAT", IDAT),
+        ];
+        assert_eq!(problems(3, &chunks), []);
+    }
+
+    #[test]
+    fn ihdr_once_only() {
+        let ihdr = testing::ihdr(1, 1, 8, 0);
+        assert_eq!(
+            problems(0, &[(b"IHDR", &ihdr), (b"IDAT", IDAT)]),
+            [(
+                Some(1),
+                Problem::Repeated {
+                    chunk_type: ChunkType::IHDR
+                },
+                Severity::Error
+            )]
+        );
+    }
+
+    #[test]
+    fn ancillary_once_only() {
+        assert_eq!(
+            problems(0, &[(b"gAMA", GAMA), (b"gAMA", GAMA), (b"IDAT", IDAT)]),
+            [(
+                Some(2),
+                Problem::Repeated {
+                    chunk_type: ChunkType::gAMA
+                },
+                Severity::Error
+            )]
+        );
+    }
+
+    #[test]
+    fn gama_after_plte() {
+        assert_eq!(
+            problems(3, &[(b"PLTE", PLTE), (b"gAMA", GAMA), (b"IDAT", IDAT)]),
+            [(
+                Some(2),
+                Problem::AfterPlte {
+                    chunk_type: ChunkType::gAMA
+                },
+                Severity::Error
+            )]
+        );
+    }
+
+    #[test]
+    fn gama_after_idat() {
+        assert_eq!(
+            problems(2, &[(b"IDAT", IDAT), (b"gAMA", GAMA)]),
+            [(
+                Some(2),
+                Problem::AfterIdat {
+                    chunk_type: ChunkType::gAMA
+                },
+                Severity::Error
+            )]
+        );
+    }
+
+    #[test]
+    fn trns_before_plte() {
+        // tRNS can't be decoded without the pallet, but that's only reported as the order
+        assert_eq!(
+            problems(3, &[(b"tRNS", &[0]), (b"PLTE", PLTE), (b"IDAT", IDAT)]),
+            [(
+                Some(1),
+                Problem::BeforePlte {
+                    chunk_type: ChunkType::tRNS
+                },
+                Severity::Error
+            )]
+        );
+    }
+
+    #[test]
+    fn before_idat_only() {
+        let phys = [0, 0, 0, 1, 0, 0, 0, 1, 0];
+        assert_eq!(
+            problems(0, &[(b"IDAT", IDAT), (b"pHYs", &phys)]),
+            [(
+                Some(2),
+                Problem::AfterIdat {
+                    chunk_type: ChunkType::pHYs
+                },
+                Severity::Error
+            )]
+        );
+        // pHYs doesn't care about PLTE
+        assert_eq!(
+            problems(3, &[(b"PLTE", PLTE), (b"pHYs", &phys), (b"IDAT", IDAT)]),
+            []
+        );
+    }
+
+    #[test]
+    fn plte_after_idat() {
+        assert_eq!(
+            problems(2, &[(b"IDAT", IDAT), (b"PLTE", PLTE)]),
+            [(Some(2), Problem::PlteAfterIdat, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn split_idat() {
+        assert_eq!(
+            problems(0, &[(b"IDAT", IDAT), (b"tEXt", b"a\0b"), (b"IDAT", IDAT)]),
+            [(Some(3), Problem::IdatNotContiguous, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn missing_idat() {
+        assert_eq!(
+            problems(0, &[]),
+            [(None, Problem::MissingIdat, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn extra_image_data() {
+        // One filter type byte & one sample, then a byte too many
+        let image_data = writer::zlib_stored(&[0, 0, 0]);
+        assert_eq!(
+            problems(0, &[(b"IDAT", &image_data)]),
+            [(None, Problem::ExtraImageData, Severity::Warning)]
+        );
+        let image_data = writer::zlib_stored(&[0, 0]);
+        assert_eq!(problems(0, &[(b"IDAT", &image_data)]), []);
+    }
+
+    #[test]
+    fn plte_for_the_color_type() {
+        assert_eq!(
+            problems(3, &[(b"IDAT", IDAT)]),
+            [(None, Problem::MissingPlte, Severity::Error)]
+        );
+        for color_type in [0, 4] {
+            assert_eq!(
+                problems(color_type, &[(b"PLTE", PLTE), (b"IDAT", IDAT)]),
+                [(
+                    None,
+                    Problem::PlteNotAllowed { color_type },
+                    Severity::Error
+                )]
+            );
+        }
+        // Optional for truecolor
+        assert_eq!(problems(2, &[(b"PLTE", PLTE), (b"IDAT", IDAT)]), []);
+    }
+
+    #[test]
+    fn ihdr_not_first() {
+        let bytes = testing::png(&[
+            (b"gAMA", GAMA),
+            (b"IHDR", &testing::ihdr(1, 1, 8, 0)),
+            (b"IDAT", IDAT),
+            (b"IEND", &[]),
+        ]);
+        let png = Png::from_bytes(&bytes).unwrap();
+        let findings = validate(&png);
+        assert_eq!(findings[0].chunk_index, Some(0));
+        assert_eq!(findings[0].offset, Some(8));
+        assert_eq!(findings[0].problem, Problem::IhdrNotFirst);
+        assert_eq!(findings[0].severity(), Severity::Error);
+    }
+}
@@ -0,0 +1,184 @@
+// Writes PNG datastreams, used to save APNG frames as PNGs of their own.
+// Image data is stored without compression (DEFLATE's stored blocks), which is
+// always valid and keeps the writer small. The files are bigger than they need to
+// be, but any decoder can read them.
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5DataRep
+
+use crate::chunks::IHDR;
+use crate::crc;
+use crate::inflate;
+use crate::parser::{Chunk, ChunkType, PNG_SIGNATURE};
+
+// Stored blocks hold at most 2^16 - 1 bytes
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.4
+const MAX_STORED_BLOCK: usize = u16::MAX as usize;
+
+// Deflate (CM 8) with a 32K window (CINFO 7), no dictionary & the lowest level,
+// with FCHECK making the two bytes a multiple of 31
+// https://www.rfc-editor.org/rfc/rfc1950#section-2.2
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x01];
+
+// A whole PNG: the signature, IHDR, the given chunks as they are, the image data
+// in one IDAT, then IEND
+pub fn write_png(header: &IHDR, chunks: &[&Chunk], image_data: &[u8]) -> Vec<u8> {
+    let mut output = PNG_SIGNATURE.to_vec();
+
+    write_chunk(&mut output, ChunkType::IHDR, &ihdr_data(header));
+    for chunk in chunks {
+        write_chunk(&mut output, *chunk.chunk_type(), chunk.data());
+    }
+    write_chunk(&mut output, ChunkType::IDAT, image_data);
+    write_chunk(&mut output, ChunkType::IEND, &[]);
+
+    output
+}
+
+// Length, type, data, then the CRC over the type & data
+// https://www.w3.org/TR/2003/REC-PNG-20031110/#5Chunk-layout
+pub fn write_chunk(output: &mut Vec<u8>, chunk_type: ChunkType, data: &[u8]) {
+    let chunk_type = <[u8; 4]>::from(chunk_type);
+
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    output.extend_from_slice(&chunk_type);
+    output.extend_from_slice(data);
+    output.extend_from_slice(&crc::chunk_crc(&chunk_type, data).to_be_bytes());
+}
+
+// 8 bit RGBA pixels (rows top to bottom) as filtered, zlib wrapped image data.
+// Every row uses filter type 0 (none).
+pub fn rgba8_image_data(width: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(pixels.len() * 4 + pixels.len() / width as usize);
+    for row in pixels.chunks(width as usize) {
+        raw.push(0);
+        raw.extend(row.iter().flatten());
+    }
+    zlib_stored(&raw)
+}
+
+// Wraps the data in a zlib stream made of stored (uncompressed) DEFLATE blocks
+pub fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = ZLIB_HEADER.to_vec();
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    // Even empty data needs one (final) block
+    if blocks.peek().is_none() {
+        output.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        // BFINAL in the lowest bit, then BTYPE 00. The rest of the byte is padding.
+        let is_final = blocks.peek().is_none();
+        output.push(is_final as u8);
+
+        let length = block.len() as u16;
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+
+    output.extend_from_slice(&inflate::adler32(data).to_be_bytes());
+    output
+}
+
+fn ihdr_data(header: &IHDR) -> Vec<u8> {
+    let mut data = Vec::with_capacity(13);
+    data.extend_from_slice(&header.width.to_be_bytes());
+    data.extend_from_slice(&header.height.to_be_bytes());
+    data.extend_from_slice(&[
+        header.bit_depth,
+        header.color_type,
+        header.compression_method,
+        header.filter_method,
+        header.interlace_method,
+    ]);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Png;
+
+    #[test]
+    fn stored_empty() {
+        let stored = zlib_stored(&[]);
+        assert_eq!(stored, [0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]);
+        assert_eq!(inflate::zlib_decompress(&stored, 0).unwrap(), []);
+    }
+
+    #[test]
+    fn stored_round_trip() {
+        let data: Vec<u8> = (0..1000).map(|n| (n * 7) as u8).collect();
+        let stored = zlib_stored(&data);
+        assert_eq!(stored.len(), 2 + 5 + 1000 + 4);
+        assert_eq!(inflate::zlib_decompress(&stored, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn stored_blocks_are_split() {
+        let data: Vec<u8> = (0..MAX_STORED_BLOCK + 10).map(|n| n as u8).collect();
+        let stored = zlib_stored(&data);
+
+        // A full block that isn't the last, then the rest in the final block
+        assert_eq!(stored[2..7], [0, 0xff, 0xff, 0, 0]);
+        let second = 2 + 5 + MAX_STORED_BLOCK;
+        assert_eq!(stored[second..second + 5], [1, 10, 0, 0xf5, 0xff]);
+        assert_eq!(stored.len(), 2 + 5 * 2 + data.len() + 4);
+
+        assert_eq!(inflate::zlib_decompress(&stored, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn chunk_layout() {
+        let mut output = Vec::new();
+        write_chunk(&mut output, ChunkType::IEND, &[]);
+        assert_eq!(
+            output,
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+
+        let mut output = Vec::new();
+        write_chunk(&mut output, ChunkType::gAMA, &[0, 0, 0xb1, 0x8f]);
+        assert_eq!(output[..8], [0, 0, 0, 4, b'g', b'A', b'M', b'A']);
+        assert_eq!(output[8..12], [0, 0, 0xb1, 0x8f]);
+        let crc = crc::chunk_crc(b"gAMA", &[0, 0, 0xb1, 0x8f]);
+        assert_eq!(output[12..], crc.to_be_bytes());
+    }
+
+    #[test]
+    fn rgba8_rows() {
+        let pixels = [
+            [1, 2, 3, 4],
+            [5, 6, 7, 8],
+            [9, 10, 11, 12],
+            [13, 14, 15, 16],
+        ];
+        let image_data = inflate::zlib_decompress(&rgba8_image_data(2, &pixels), 18).unwrap();
+        assert_eq!(
+            image_data,
+            [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, //
+                0, 9, 10, 11, 12, 13, 14, 15, 16
+            ]
+        );
+    }
+
+    #[test]
+    fn written_pngs_decode() {
+        let header = IHDR {
+            width: 1,
+            height: 2,
+            bit_depth: 8,
+            color_type: 6,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        };
+        let pixels = [[255, 0, 0, 255], [0, 0, 255, 128]];
+        let bytes = write_png(&header, &[], &rgba8_image_data(1, &pixels));
+
+        let png = Png::from_bytes(&bytes).unwrap();
+        assert_eq!((png.ihdr().width, png.ihdr().height), (1, 2));
+        assert_eq!(png.ihdr().color_type, 6);
+        assert_eq!(png.pixels().unwrap().to_rgba8().unwrap(), pixels);
+    }
+}